@@ -131,6 +131,10 @@ Future<String?> windowsHtmlFromSystem(
     return null;
   }
   if (format == cfHtml) {
+    // Native reader already extracts the HTML fragment.
+    if (value is String) {
+      return value;
+    }
     if (value is List<int>) {
       String decoded = utf8.decode(value, allowMalformed: true);
       final lines = const LineSplitter().convert(decoded);
@@ -153,11 +153,15 @@ abstract class ClipboardDataReader extends DataReader {
 
 /// Clipboard reader exposes contents of the clipboard.
 class ClipboardReader extends ClipboardDataReader {
-  ClipboardReader(this.items);
+  ClipboardReader(this.items, {this.clipboardIntent});
 
   /// Individual items of this clipboard reader.
   final List<ClipboardDataReader> items;
 
+  /// Whether files on clipboard were copied or cut, if known. When cut, the
+  /// files should be moved on paste. Currently only provided on Linux.
+  final raw.ClipboardIntent? clipboardIntent;
+
   @Deprecated('Use SystemClipboard.instance?.read() instead.')
   static Future<ClipboardReader> readClipboard() async {
     final clipboard = SystemClipboard.instance;
@@ -40,7 +40,10 @@ class SystemClipboard implements ClipboardWriter {
     for (final item in itemInfo) {
       items.add(ClipboardDataReader.forItemInfo(item));
     }
-    return ClipboardReader(items);
+    return ClipboardReader(
+      items,
+      clipboardIntent: await reader.getClipboardIntent(),
+    );
   }
 
   SystemClipboard._();
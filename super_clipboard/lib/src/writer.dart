@@ -7,7 +7,8 @@ export 'package:super_native_extensions/raw_clipboard.dart'
         VirtualFileProvider,
         VirtualFileEventSinkProvider,
         WriteProgress,
-        VirtualFileStorage;
+        VirtualFileStorage,
+        ClipboardIntent;
 
 import 'format.dart';
 import 'util.dart';
@@ -20,7 +21,7 @@ import 'events.dart';
 /// To get encoded data for values use [DataFormat.call] or
 /// [DataFormat.lazy];
 class DataWriterItem {
-  DataWriterItem({this.suggestedName});
+  DataWriterItem({this.suggestedName, this.clipboardIntent});
 
   /// Adds representation to the data item. On item can contain multiple
   /// representations, each in a different format. Representation should
@@ -73,6 +74,10 @@ class DataWriterItem {
   /// File name suggestion for the client receiving this data item.
   final String? suggestedName;
 
+  /// Whether file URIs in this item should be copied or moved when pasted
+  /// in file manager. Currently only supported on Linux.
+  final raw.ClipboardIntent? clipboardIntent;
+
   List<FutureOr<EncodedData>> get data => _data;
 }
 
@@ -20,6 +20,7 @@ extension ClipboardWriterItemDataProvider on DataWriterItem {
     return raw.DataProvider(
       representations: representations,
       suggestedName: suggestedName,
+      clipboardIntent: clipboardIntent,
     );
   }
 
@@ -33,6 +34,7 @@ extension ClipboardWriterItemDataProvider on DataWriterItem {
     return raw.DataProvider(
       representations: representations,
       suggestedName: suggestedName,
+      clipboardIntent: clipboardIntent,
     );
   }
 
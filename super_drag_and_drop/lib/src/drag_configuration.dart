@@ -44,6 +44,8 @@ class DragOptions {
   const DragOptions({
     this.animatesToStartingPositionOnCancelOrFail = true,
     this.prefersFullSizePreviews = true,
+    this.shadowStyle,
+    this.previewSizePolicy,
   });
 
   /// macOS specific
@@ -51,6 +53,12 @@ class DragOptions {
 
   /// iOS specific
   final bool prefersFullSizePreviews;
+
+  /// Linux and Windows specific; Shadow drawn below the drag image.
+  final ShadowStyle? shadowStyle;
+
+  /// Size limit for drag images. Larger images are downscaled.
+  final PreviewSizePolicy? previewSizePolicy;
 }
 
 /// Initial configuration of a drag session.
@@ -36,6 +36,8 @@ extension DragConfigurationIntoRaw on DragConfiguration {
       animatesToStartingPositionOnCancelOrFail:
           options.animatesToStartingPositionOnCancelOrFail,
       prefersFullSizePreviews: options.prefersFullSizePreviews,
+      shadowStyle: options.shadowStyle,
+      previewSizePolicy: options.previewSizePolicy,
     );
   }
 }
@@ -0,0 +1 @@
+export 'src/capabilities.dart';
@@ -0,0 +1 @@
+export 'src/diagnostics.dart';
@@ -0,0 +1 @@
+export 'src/lifecycle.dart';
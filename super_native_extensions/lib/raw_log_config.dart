@@ -0,0 +1 @@
+export 'src/log_config.dart';
@@ -2,6 +2,7 @@ export 'src/cancellation_token.dart';
 export 'src/widget_snapshot/widget_snapshot.dart';
 export 'src/menu.dart';
 export 'src/menu_model.dart';
+export 'src/image_data.dart' show PreviewSizePolicy, ResampleFilter;
 export 'src/gesture/single_drag.dart';
 export 'src/gesture/multi_touch_detector.dart';
 export 'src/gesture/pointer_device_kind.dart';
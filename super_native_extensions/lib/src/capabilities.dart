@@ -0,0 +1,100 @@
+import 'native/capabilities.dart'
+    if (dart.library.js_interop) 'web/capabilities.dart';
+
+class ClipboardCapabilities {
+  const ClipboardCapabilities({
+    required this.multipleItems,
+    required this.primarySelection,
+    required this.events,
+  });
+
+  /// Clipboard can hold more than one item at a time.
+  final bool multipleItems;
+
+  /// Primary selection (middle-click paste) can be read and written.
+  final bool primarySelection;
+
+  /// Platform delivers cut / copy / paste / select all events.
+  final bool events;
+}
+
+class VirtualFileCapabilities {
+  const VirtualFileCapabilities({
+    required this.read,
+    required this.copy,
+    required this.provide,
+  });
+
+  /// Virtual files from other applications can be read as a stream.
+  final bool read;
+
+  /// Virtual files from other applications can be copied to a folder.
+  final bool copy;
+
+  /// Data providers can offer virtual files to other applications.
+  final bool provide;
+}
+
+class HotKeyCapabilities {
+  const HotKeyCapabilities({required this.supported});
+
+  final bool supported;
+}
+
+class MenuCapabilities {
+  const MenuCapabilities({required this.previewImage});
+
+  /// Menu preview image can be updated after the menu is shown.
+  final bool previewImage;
+}
+
+class DragCapabilities {
+  const DragCapabilities({required this.previewAnimations});
+
+  /// Dropped items can animate into destination provided by the application.
+  final bool previewAnimations;
+}
+
+/// Features supported by the current platform. Subsystems that were not
+/// compiled into the native library are reported as unsupported.
+class Capabilities {
+  const Capabilities({
+    required this.clipboard,
+    required this.virtualFiles,
+    required this.hotKeys,
+    required this.menu,
+    required this.drag,
+  });
+
+  static Capabilities deserialize(dynamic capabilities) {
+    final map = capabilities as Map;
+    final clipboard = map['clipboard'] as Map;
+    final virtualFiles = map['virtualFiles'] as Map;
+    return Capabilities(
+      clipboard: ClipboardCapabilities(
+        multipleItems: clipboard['multipleItems'],
+        primarySelection: clipboard['primarySelection'],
+        events: clipboard['events'],
+      ),
+      virtualFiles: VirtualFileCapabilities(
+        read: virtualFiles['read'],
+        copy: virtualFiles['copy'],
+        provide: virtualFiles['provide'],
+      ),
+      hotKeys: HotKeyCapabilities(supported: map['hotKeys']['supported']),
+      menu: MenuCapabilities(previewImage: map['menu']['previewImage']),
+      drag: DragCapabilities(
+          previewAnimations: map['drag']['previewAnimations']),
+    );
+  }
+
+  final ClipboardCapabilities clipboard;
+  final VirtualFileCapabilities virtualFiles;
+  final HotKeyCapabilities hotKeys;
+  final MenuCapabilities menu;
+  final DragCapabilities drag;
+
+  /// Queries capabilities of the current platform. The result does not
+  /// change during lifetime of the process.
+  static Future<Capabilities> get() => getCapabilities();
+}
@@ -7,10 +7,14 @@ import 'util.dart';
 import 'clipboard_writer.dart';
 import 'drag.dart';
 
+/// Whether files on clipboard should be copied or moved on paste.
+enum ClipboardIntent { copy, cut }
+
 class DataProvider {
   DataProvider({
     required this.representations,
     this.suggestedName,
+    this.clipboardIntent,
   });
 
   /// Registers this source with native code. The source data will be kept alive
@@ -21,6 +25,10 @@ class DataProvider {
 
   final List<DataRepresentation> representations;
   final String? suggestedName;
+
+  /// Lets file managers move (cut) or copy the files referenced by file URIs
+  /// in this provider on paste. Currently only used on Linux.
+  final ClipboardIntent? clipboardIntent;
 }
 
 sealed class DataRepresentation {
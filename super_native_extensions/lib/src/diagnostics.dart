@@ -0,0 +1,53 @@
+import 'native/diagnostics.dart'
+    if (dart.library.js_interop) 'web/diagnostics.dart';
+
+/// Native resource suspected of being leaked, i.e. still alive after the
+/// leak watchdog threshold.
+class LeakReport {
+  LeakReport({
+    required this.kind,
+    required this.age,
+    required this.creationSite,
+  });
+
+  static LeakReport deserialize(dynamic report) {
+    final map = report as Map;
+    return LeakReport(
+      kind: map['kind'],
+      age: Duration(milliseconds: map['ageMillis']),
+      creationSite: map['creationSite'],
+    );
+  }
+
+  /// Resource kind, such as `reader`, `provider` or `virtualSession`.
+  final String kind;
+  final Duration age;
+
+  /// Dart code location that created the resource (debug builds only) or
+  /// description of the native operation that created it.
+  final String creationSite;
+
+  @override
+  String toString() =>
+      'LeakReport($kind alive for ${age.inMilliseconds} ms, created at $creationSite)';
+}
+
+abstract class NativeDiagnostics {
+  static final _instance = NativeDiagnosticsImpl();
+
+  static NativeDiagnostics get instance => _instance;
+
+  /// Returns snapshot of native resources per isolate and buffered bytes.
+  Future<Map<String, Object?>> getDiagnostics();
+
+  /// Reports native readers, providers and virtual file sessions alive for
+  /// longer than [threshold]. Passing null disables the watchdog. When
+  /// [notifyDart] is false suspected leaks are only logged natively.
+  Future<void> configureLeakWatchdog({
+    required Duration? threshold,
+    bool notifyDart = true,
+  });
+
+  /// Invoked for each suspected leak of resource owned by this isolate.
+  set onLeakSuspected(void Function(LeakReport report)? onLeakSuspected);
+}
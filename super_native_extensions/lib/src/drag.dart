@@ -18,6 +18,8 @@ class DragConfiguration {
     required this.allowedOperations,
     this.animatesToStartingPositionOnCancelOrFail = true,
     this.prefersFullSizePreviews = false,
+    this.shadowStyle,
+    this.previewSizePolicy,
   });
 
   final List<DragItem> items;
@@ -29,6 +31,14 @@ class DragConfiguration {
   /// iOS specific
   final bool prefersFullSizePreviews;
 
+  /// Shadow drawn below the drag image on Linux and Windows. Other platforms
+  /// use native drag shadow. When not specified the shadow has blur radius
+  /// of 10 physical pixels regardless of device pixel ratio.
+  final ShadowStyle? shadowStyle;
+
+  /// Size limit for drag images. Uses default policy when not specified.
+  final PreviewSizePolicy? previewSizePolicy;
+
   DragConfiguration clone() {
     return DragConfiguration(
       items: items.map((e) => e).toList(),
@@ -36,6 +46,8 @@ class DragConfiguration {
       animatesToStartingPositionOnCancelOrFail:
           animatesToStartingPositionOnCancelOrFail,
       prefersFullSizePreviews: prefersFullSizePreviews,
+      shadowStyle: shadowStyle,
+      previewSizePolicy: previewSizePolicy,
     );
   }
 
@@ -46,6 +58,25 @@ class DragConfiguration {
   }
 }
 
+class ShadowStyle {
+  const ShadowStyle({
+    this.color = const ui.Color(0xFF000000),
+    this.opacity = 0.5,
+    this.offset = ui.Offset.zero,
+    this.spread = 0,
+    this.radius = 5,
+  });
+
+  /// Shadow color. Alpha of the color is multiplied by [opacity].
+  final ui.Color color;
+  final double opacity;
+
+  /// Offset, spread and radius are in logical pixels.
+  final ui.Offset offset;
+  final double spread;
+  final double radius;
+}
+
 class DragItem {
   DragItem({
     required this.dataProvider,
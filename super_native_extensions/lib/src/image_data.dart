@@ -65,6 +65,26 @@ class ImageData {
   }
 }
 
+enum ResampleFilter {
+  box,
+  bilinear,
+  lanczos3,
+}
+
+/// Maximum size of drag and menu preview images in physical pixels. Larger
+/// images are downscaled; Their size in logical pixels stays the same.
+class PreviewSizePolicy {
+  const PreviewSizePolicy({
+    this.maxWidth = 1024,
+    this.maxHeight = 1024,
+    this.filter = ResampleFilter.lanczos3,
+  });
+
+  final int maxWidth;
+  final int maxHeight;
+  final ResampleFilter filter;
+}
+
 class TargetedImageData {
   TargetedImageData({
     required this.imageData,
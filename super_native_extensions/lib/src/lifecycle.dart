@@ -0,0 +1,11 @@
+import 'native/lifecycle.dart'
+    if (dart.library.js_interop) 'web/lifecycle.dart' as impl;
+
+/// Releases all native resources held by the plugin. Hot keys are
+/// unregistered, clipboard content provided by the application is released
+/// and pending sessions are cancelled. Meant for hosts that tear down the
+/// Flutter engine while the process keeps running.
+///
+/// Shutdown is performed on the platform thread; when this is called from
+/// another thread it is only scheduled.
+void shutdownNativeExtensions() => impl.shutdownNativeExtensions();
@@ -0,0 +1,51 @@
+import 'native/log_config.dart'
+    if (dart.library.js_interop) 'web/log_config.dart';
+
+/// Native subsystems with separately configurable log level.
+enum LogSubsystem { drag, drop, reader, provider, menu, hotKey }
+
+enum LogLevel { off, error, warn, info, debug, trace }
+
+/// Log record produced by native code.
+class NativeLogRecord {
+  NativeLogRecord({
+    required this.level,
+    required this.target,
+    required this.message,
+  });
+
+  static NativeLogRecord deserialize(dynamic record) {
+    final map = record as Map;
+    return NativeLogRecord(
+      level: LogLevel.values.byName(map['level']),
+      target: map['target'],
+      message: map['message'],
+    );
+  }
+
+  final LogLevel level;
+
+  /// Module or source file that produced the record.
+  final String target;
+  final String message;
+
+  @override
+  String toString() => '[${level.name}] $target: $message';
+}
+
+abstract class LogConfig {
+  static final _instance = LogConfigImpl();
+
+  static LogConfig get instance => _instance;
+
+  /// Sets native log level. Subsystems not present in [subsystems] use
+  /// [defaultLevel].
+  Future<void> setLogLevels({
+    required LogLevel defaultLevel,
+    Map<LogSubsystem, LogLevel> subsystems = const {},
+  });
+
+  /// When set, native log records are forwarded to this callback in
+  /// addition to the platform log.
+  set onLog(void Function(NativeLogRecord record)? onLog);
+}
@@ -4,6 +4,7 @@ import 'dart:ui' as ui;
 import 'package:flutter/foundation.dart';
 import 'package:flutter/widgets.dart';
 
+import 'image_data.dart';
 import 'menu_model.dart';
 import 'mutex.dart';
 
@@ -35,6 +36,7 @@ class MobileMenuConfiguration {
     required this.liftImage,
     this.previewImage,
     this.previewSize,
+    this.previewSizePolicy,
     required this.handle,
     required this.backgroundBuilder,
     required this.previewBuilder,
@@ -47,6 +49,10 @@ class MobileMenuConfiguration {
   final TargetedWidgetSnapshot liftImage;
   final WidgetSnapshot? previewImage;
   final ui.Size? previewSize;
+
+  /// Size limit for preview and lift images. Uses default policy when not
+  /// specified.
+  final PreviewSizePolicy? previewSizePolicy;
   final MenuHandle handle;
   final IconThemeData iconTheme;
 
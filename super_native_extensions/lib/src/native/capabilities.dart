@@ -0,0 +1,15 @@
+import 'package:irondash_message_channel/irondash_message_channel.dart';
+
+import '../capabilities.dart';
+import 'context.dart';
+
+final _channel = NativeMethodChannel('CapabilitiesManager',
+    context: superNativeExtensionsContext);
+
+Future<Capabilities>? _capabilities;
+
+Future<Capabilities> getCapabilities() {
+  return _capabilities ??= _channel
+      .invokeMethod('getCapabilities')
+      .then((value) => Capabilities.deserialize(value));
+}
@@ -6,11 +6,14 @@ import 'context.dart';
 import '../clipboard_reader.dart';
 import '../reader.dart';
 import '../reader_manager.dart';
+import '../util.dart';
 
 class ClipboardReaderImpl extends ClipboardReader {
   @override
   Future<DataReader> newClipboardReader() async {
-    final handle = await _channel.invokeMethod('newClipboardReader');
+    final handle = await _channel.invokeMethod('newClipboardReader', {
+      'creationSite': debugCreationSite(),
+    });
     return DataReader(handle: DataReaderHandle.deserialize(handle));
   }
 
@@ -20,6 +20,7 @@ extension DataProviderExt on DataProvider {
   dynamic serialize() => {
         'representations': representations.map((e) => e.serialize()),
         'suggestedName': suggestedName,
+        'clipboardIntent': clipboardIntent?.name,
       };
 }
 
@@ -30,8 +31,10 @@ class DataProviderManagerImpl implements DataProviderManager {
 
   @override
   Future<DataProviderHandle> registerDataProvider(DataProvider provider) async {
-    final id = await _channel.invokeMethod(
-        "registerDataProvider", provider.serialize());
+    final id = await _channel.invokeMethod("registerDataProvider", {
+      'provider': provider.serialize(),
+      'creationSite': debugCreationSite(),
+    });
     final handle = DataProviderHandle(id, provider);
     _handles[id] = handle;
     for (final representation in provider.representations) {
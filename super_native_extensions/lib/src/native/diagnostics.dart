@@ -0,0 +1,44 @@
+import 'package:flutter/services.dart';
+import 'package:irondash_message_channel/irondash_message_channel.dart';
+
+import '../diagnostics.dart';
+import 'context.dart';
+
+class NativeDiagnosticsImpl extends NativeDiagnostics {
+  NativeDiagnosticsImpl() {
+    _channel.setMethodCallHandler(_onMethodCall);
+  }
+
+  Future<dynamic> _onMethodCall(MethodCall call) async {
+    if (call.method == 'onLeakSuspected') {
+      _onLeakSuspected?.call(LeakReport.deserialize(call.arguments));
+    }
+  }
+
+  @override
+  Future<Map<String, Object?>> getDiagnostics() async {
+    final res = await _channel.invokeMethod('getDiagnostics');
+    return (res as Map).cast<String, Object?>();
+  }
+
+  @override
+  Future<void> configureLeakWatchdog({
+    required Duration? threshold,
+    bool notifyDart = true,
+  }) async {
+    await _channel.invokeMethod('configureLeakWatchdog', {
+      'thresholdMillis': threshold?.inMilliseconds,
+      'notifyDart': notifyDart,
+    });
+  }
+
+  @override
+  set onLeakSuspected(void Function(LeakReport report)? onLeakSuspected) {
+    _onLeakSuspected = onLeakSuspected;
+  }
+
+  void Function(LeakReport report)? _onLeakSuspected;
+
+  final _channel = NativeMethodChannel('DiagnosticsManager',
+      context: superNativeExtensionsContext);
+}
@@ -7,7 +7,6 @@ import 'package:flutter/widgets.dart';
 import 'package:irondash_message_channel/irondash_message_channel.dart';
 
 import '../data_provider.dart';
-import '../drag_internal.dart';
 import '../drag.dart';
 import '../drop.dart';
 import '../image_data.dart';
@@ -23,6 +22,20 @@ extension DragConfigurationExt on DragConfiguration {
         'animatesToStartingPositionOnCancelOrFail':
             animatesToStartingPositionOnCancelOrFail,
         'prefersFullSizePreviews': prefersFullSizePreviews,
+        'shadowStyle': shadowStyle?.serialize(),
+        'previewSizePolicy': previewSizePolicy?.serialize(),
+      };
+}
+
+extension ShadowStyleExt on ShadowStyle {
+  dynamic serialize() => {
+        // ignore: deprecated_member_use
+        'color': color.value,
+        'opacity': opacity,
+        'offsetX': offset.dx,
+        'offsetY': offset.dy,
+        'spread': spread,
+        'radius': radius,
       };
 }
 
@@ -231,10 +244,10 @@ class DragContextImpl extends DragContext {
     final request = DragRequest(
       configuration: configuration,
       position: position,
-      combinedDragImage: needsCombinedDragImage
-          ? (await combinedDragImage?.intoRaw()) ??
-              await combineDragImage(configuration)
-          : null,
+      // When no combined image is provided the native side composites item
+      // images.
+      combinedDragImage:
+          needsCombinedDragImage ? await combinedDragImage?.intoRaw() : null,
     );
 
     final sessionId =
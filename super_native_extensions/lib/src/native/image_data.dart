@@ -11,6 +11,14 @@ extension ImageDataExt on ImageData {
       };
 }
 
+extension PreviewSizePolicyExt on PreviewSizePolicy {
+  dynamic serialize() => {
+        'maxWidth': maxWidth,
+        'maxHeight': maxHeight,
+        'filter': filter.name,
+      };
+}
+
 extension TargettedImageDataExt on TargetedImageData {
   dynamic serialize() => {
         'imageData': imageData.serialize(),
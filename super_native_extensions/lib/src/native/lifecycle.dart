@@ -0,0 +1,15 @@
+import 'dart:ffi';
+import 'dart:io' show Platform;
+
+import 'context.dart';
+
+void shutdownNativeExtensions() {
+  if (Platform.environment.containsKey('FLUTTER_TEST')) {
+    // FFI doesn't work in Flutter Tester
+    return;
+  }
+  final shutdown = openNativeLibrary()
+      .lookupFunction<Void Function(), void Function()>(
+          'super_native_extensions_shutdown');
+  shutdown();
+}
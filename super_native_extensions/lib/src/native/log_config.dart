@@ -0,0 +1,49 @@
+import 'package:flutter/services.dart';
+import 'package:irondash_message_channel/irondash_message_channel.dart';
+
+import '../log_config.dart';
+import 'context.dart';
+
+class LogConfigImpl extends LogConfig {
+  LogConfigImpl() {
+    _channel.setMethodCallHandler(_onMethodCall);
+  }
+
+  Future<dynamic> _onMethodCall(MethodCall call) async {
+    if (call.method == 'onLog') {
+      _onLog?.call(NativeLogRecord.deserialize(call.arguments));
+    }
+  }
+
+  @override
+  Future<void> setLogLevels({
+    required LogLevel defaultLevel,
+    Map<LogSubsystem, LogLevel> subsystems = const {},
+  }) async {
+    await _channel.invokeMethod('setLogLevels', {
+      'defaultLevel': defaultLevel.name,
+      'subsystems': subsystems.entries
+          .map((e) => {
+                'subsystem': e.key.name,
+                'level': e.value.name,
+              })
+          .toList(growable: false),
+    });
+  }
+
+  @override
+  set onLog(void Function(NativeLogRecord record)? onLog) {
+    final forward = onLog != null;
+    _onLog = onLog;
+    if (forward != _forwarding) {
+      _forwarding = forward;
+      _channel.invokeMethod('setForwardToDart', forward);
+    }
+  }
+
+  void Function(NativeLogRecord record)? _onLog;
+  bool _forwarding = false;
+
+  final _channel =
+      NativeMethodChannel('LogConfig', context: superNativeExtensionsContext);
+}
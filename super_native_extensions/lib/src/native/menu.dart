@@ -234,8 +234,10 @@ class MenuContextImpl extends MenuContext {
     // The cast is necessary for correct extension method to be called.
     // ignore: unnecessary_cast
     final serialized = await (menu as MenuElement).serialize(options);
-    final handle =
-        await _channel.invokeMethod('registerMenu', serialized) as int;
+    final handle = await _channel.invokeMethod('registerMenu', {
+      'menu': serialized,
+      'creationSite': debugCreationSite(),
+    }) as int;
     final res = NativeMenuHandle(
       menu: menu,
       elements: [menu],
@@ -364,5 +366,6 @@ extension MenuConfigurationExt on MobileMenuConfiguration {
         'previewSize': previewSize?.serialize(),
         'liftImage': (await liftImage.intoRaw()).serialize(),
         'menuHandle': (handle as NativeMenuHandle).handle,
+        'previewSizePolicy': previewSizePolicy?.serialize(),
       };
 }
@@ -1,11 +1,13 @@
 import 'dart:async';
 import 'dart:io';
 
+import 'package:collection/collection.dart';
 import 'package:flutter/foundation.dart';
 import 'package:flutter/services.dart';
 import 'package:irondash_message_channel/irondash_message_channel.dart';
 
 import 'context.dart';
+import '../data_provider.dart';
 import '../reader.dart';
 import '../reader_manager.dart';
 import 'virtual_file.dart';
@@ -84,6 +86,13 @@ class ReaderManagerImpl extends ReaderManager {
         .toList(growable: false);
   }
 
+  @override
+  Future<ClipboardIntent?> getClipboardIntent(DataReaderHandle reader) async {
+    final intent =
+        await _channel.invokeMethod("getClipboardIntent", reader._handle);
+    return ClipboardIntent.values.firstWhereOrNull((e) => e.name == intent);
+  }
+
   @override
   Future<List<String>> getItemFormats(DataReaderItemHandle handle) async {
     final formats = await _channel.invokeMethod("getItemFormats", {
@@ -93,6 +102,14 @@ class ReaderManagerImpl extends ReaderManager {
     return formats.cast<String>();
   }
 
+  @override
+  Future<String?> getItemLocalPath(DataReaderItemHandle handle) async {
+    return await _channel.invokeMethod("getItemLocalPath", {
+      "itemHandle": handle._itemHandle,
+      "readerHandle": handle._readerHandle,
+    }) as String?;
+  }
+
   @override
   (Future<Object?>, ReadProgress) getItemData(
     DataReaderItemHandle handle, {
@@ -2,6 +2,7 @@ import 'dart:async';
 
 import 'package:flutter/foundation.dart';
 
+import 'data_provider.dart';
 import 'mutex.dart';
 import 'reader_manager.dart';
 
@@ -15,6 +16,12 @@ class DataReader {
     });
   }
 
+  /// Returns whether files were copied or cut by the source application, if
+  /// known. Currently only provided on Linux.
+  Future<ClipboardIntent?> getClipboardIntent() {
+    return ReaderManager.instance.getClipboardIntent(_handle);
+  }
+
   DataReader({
     required DataReaderHandle handle,
   }) : _handle = handle;
@@ -71,6 +78,12 @@ class DataReaderItem {
     });
   }
 
+  /// Returns local path of file item, if known. Currently only provided on
+  /// Linux.
+  Future<String?> getLocalPath() {
+    return ReaderManager.instance.getItemLocalPath(_handle);
+  }
+
   (Future<Object?>, ReadProgress) getDataForFormat(
     String format,
   ) {
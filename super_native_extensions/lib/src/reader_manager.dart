@@ -1,3 +1,4 @@
+import 'data_provider.dart';
 import 'reader.dart';
 
 import 'native/reader_manager.dart'
@@ -16,8 +17,12 @@ abstract class ReaderManager {
 
   Future<List<DataReaderItemHandle>> getItems(DataReaderHandle reader);
 
+  Future<ClipboardIntent?> getClipboardIntent(DataReaderHandle reader);
+
   Future<List<String>> getItemFormats(DataReaderItemHandle handle);
 
+  Future<String?> getItemLocalPath(DataReaderItemHandle handle);
+
   (Future<Object?>, ReadProgress) getItemData(
     DataReaderItemHandle handle, {
     required String format,
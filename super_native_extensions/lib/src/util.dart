@@ -61,3 +61,32 @@ Future<T> handleError<T>(Future<T> Function() f, T Function() def) async {
     return def();
   }
 }
+
+const _internalPackages = [
+  'package:super_native_extensions/',
+  'package:super_clipboard/',
+  'package:super_drag_and_drop/',
+  'package:flutter/',
+  'dart:',
+];
+
+/// Returns first stack frame outside of the plugin packages. Sent along with
+/// requests that create native resources so that leak diagnostics can point
+/// to the code that created them. Returns null in release and profile builds.
+String? debugCreationSite() {
+  if (!kDebugMode) {
+    return null;
+  }
+  for (final line in StackTrace.current.toString().split('\n')) {
+    final start = line.indexOf('(');
+    final end = line.lastIndexOf(')');
+    if (start == -1 || end <= start) {
+      continue;
+    }
+    final location = line.substring(start + 1, end);
+    if (!_internalPackages.any(location.startsWith)) {
+      return line.substring(line.indexOf(' ')).trim();
+    }
+  }
+  return null;
+}
@@ -0,0 +1,19 @@
+import '../capabilities.dart';
+
+/// Web implementation only supports single item clipboard and drag and drop
+/// without virtual files.
+Future<Capabilities> getCapabilities() async => const Capabilities(
+      clipboard: ClipboardCapabilities(
+        multipleItems: false,
+        primarySelection: false,
+        events: true,
+      ),
+      virtualFiles: VirtualFileCapabilities(
+        read: false,
+        copy: false,
+        provide: false,
+      ),
+      hotKeys: HotKeyCapabilities(supported: false),
+      menu: MenuCapabilities(previewImage: false),
+      drag: DragCapabilities(previewAnimations: false),
+    );
@@ -0,0 +1,15 @@
+import '../diagnostics.dart';
+
+class NativeDiagnosticsImpl extends NativeDiagnostics {
+  @override
+  Future<Map<String, Object?>> getDiagnostics() async => {};
+
+  @override
+  Future<void> configureLeakWatchdog({
+    required Duration? threshold,
+    bool notifyDart = true,
+  }) async {}
+
+  @override
+  set onLeakSuspected(void Function(LeakReport report)? onLeakSuspected) {}
+}
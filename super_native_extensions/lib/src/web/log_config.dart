@@ -0,0 +1,12 @@
+import '../log_config.dart';
+
+class LogConfigImpl extends LogConfig {
+  @override
+  Future<void> setLogLevels({
+    required LogLevel defaultLevel,
+    Map<LogSubsystem, LogLevel> subsystems = const {},
+  }) async {}
+
+  @override
+  set onLog(void Function(NativeLogRecord record)? onLog) {}
+}
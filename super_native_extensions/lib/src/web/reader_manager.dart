@@ -2,6 +2,7 @@ import 'dart:async';
 
 import 'package:flutter/foundation.dart';
 
+import '../data_provider.dart';
 import '../reader.dart';
 import '../reader_manager.dart';
 
@@ -73,6 +74,16 @@ class ReaderManagerImpl extends ReaderManager {
     return impl.getFormats();
   }
 
+  @override
+  Future<String?> getItemLocalPath(DataReaderItemHandle handle) async {
+    return null;
+  }
+
+  @override
+  Future<ClipboardIntent?> getClipboardIntent(DataReaderHandle reader) async {
+    return null;
+  }
+
   @override
   Future<List<DataReaderItemHandle>> getItems(DataReaderHandle reader) async {
     final handle = reader as $DataReaderHandle;
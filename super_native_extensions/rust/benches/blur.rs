@@ -0,0 +1,38 @@
+//! Timing of single threaded and parallel blur.
+//!
+//! Run with `cargo bench --bench blur`.
+
+use std::time::Instant;
+
+// The module only depends on std, so it is compiled into the benchmark
+// directly instead of being exported from the crate.
+#[path = "../src/blur.rs"]
+mod blur;
+
+use blur::{blur, blur_with_workers, BlurKind};
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7919 % 251) as u8).collect()
+}
+
+fn main() {
+    for (width, height, channels) in [(2048, 2048, 1), (1024, 1024, 4), (4096, 2048, 1)] {
+        let source = pattern(width * height * channels);
+        for kind in [BlurKind::Box, BlurKind::Gaussian] {
+            for workers in [1, 0] {
+                let mut pixels = source.clone();
+                let start = Instant::now();
+                if workers == 0 {
+                    blur(&mut pixels, width, height, channels, 20, kind);
+                } else {
+                    blur_with_workers(&mut pixels, width, height, channels, 20, kind, workers);
+                }
+                println!(
+                    "{width}x{height}x{channels} {kind:?} {}: {:?}",
+                    if workers == 0 { "parallel" } else { "single" },
+                    start.elapsed()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Conversion of image alpha channel to simplified polygon outline. Contours
+//! are traced with marching squares over pixel centers (with sub-pixel
+//! interpolation of the alpha value) and simplified using Douglas-Peucker
+//! algorithm.
+//!
+//! Outer contours and holes have opposite winding; Polygons should be filled
+//! using even-odd rule.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::api_model::{ImageData, PixelFormat, Point};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlphaOutlineOptions {
+    /// Pixels with alpha above threshold are inside of the outline.
+    pub alpha_threshold: u8,
+    /// Maximum distance in pixels between simplified and traced outline.
+    pub tolerance: f64,
+}
+
+impl Default for AlphaOutlineOptions {
+    fn default() -> Self {
+        Self {
+            alpha_threshold: 128,
+            tolerance: 0.5,
+        }
+    }
+}
+
+/// Identifies edge between two neighbouring pixel centers; Horizontal edge
+/// from (x, y) to (x + 1, y) or vertical edge from (x, y) to (x, y + 1).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum EdgeKey {
+    Horizontal(i32, i32),
+    Vertical(i32, i32),
+}
+
+struct AlphaMask {
+    width: i32,
+    height: i32,
+    alpha: Vec<u8>,
+    /// Crossing level; Slightly above threshold so that pixels with alpha
+    /// exactly at threshold are outside.
+    level: f64,
+}
+
+impl AlphaMask {
+    fn new(image: &ImageData, threshold: u8) -> Self {
+        let image = image.to_format(PixelFormat::Rgba);
+        let alpha = (0..image.height)
+            .flat_map(|y| image.row(y).chunks_exact(4).map(|p| p[3]))
+            .collect();
+        Self {
+            width: image.width,
+            height: image.height,
+            alpha,
+            level: threshold as f64 + 0.5,
+        }
+    }
+
+    /// Alpha at pixel; Pixels outside of image are transparent.
+    fn alpha(&self, x: i32, y: i32) -> f64 {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            0.0
+        } else {
+            self.alpha[(y * self.width + x) as usize] as f64
+        }
+    }
+
+    fn inside(&self, x: i32, y: i32) -> bool {
+        self.alpha(x, y) > self.level
+    }
+
+    /// Position where the edge crosses the alpha level, in pixels.
+    fn crossing(&self, key: EdgeKey) -> (f64, f64) {
+        let ((x0, y0), (x1, y1)) = match key {
+            EdgeKey::Horizontal(x, y) => ((x, y), (x + 1, y)),
+            EdgeKey::Vertical(x, y) => ((x, y), (x, y + 1)),
+        };
+        let (a0, a1) = (self.alpha(x0, y0), self.alpha(x1, y1));
+        let t = ((self.level - a0) / (a1 - a0)).clamp(0.0, 1.0);
+        // Pixel centers are at half pixel offsets.
+        (
+            x0 as f64 + 0.5 + t * (x1 - x0) as f64,
+            y0 as f64 + 0.5 + t * (y1 - y0) as f64,
+        )
+    }
+
+    /// Returns contour segments, each going from the edge where the contour
+    /// enters the cell to the edge where it leaves. Cell (x, y) has pixel
+    /// (x, y) at its bottom right corner.
+    fn segments(&self) -> Vec<(EdgeKey, EdgeKey)> {
+        let mut res = Vec::new();
+        let mut crossings = Vec::with_capacity(4);
+        for y in 0..=self.height {
+            for x in 0..=self.width {
+                // Corners and edges in clockwise order; Each edge goes from
+                // corner with the same index to the next one.
+                let corners = [(x - 1, y - 1), (x, y - 1), (x, y), (x - 1, y)];
+                let inside = corners.map(|(x, y)| self.inside(x, y));
+                if inside.iter().all(|i| *i) || inside.iter().all(|i| !*i) {
+                    continue;
+                }
+                let edges = [
+                    EdgeKey::Horizontal(x - 1, y - 1),
+                    EdgeKey::Vertical(x, y - 1),
+                    EdgeKey::Horizontal(x - 1, y),
+                    EdgeKey::Vertical(x - 1, y - 1),
+                ];
+                // (entering, edge)
+                crossings.clear();
+                for i in 0..4 {
+                    if inside[i] != inside[(i + 1) % 4] {
+                        crossings.push((inside[(i + 1) % 4], edges[i]));
+                    }
+                }
+                // Crossings alternate between entering and leaving. Contour
+                // goes from entering crossing to leaving crossing that is
+                // next (inside corners are separated) or previous (outside
+                // corners are separated) in clockwise order.
+                let center_inside =
+                    corners.iter().map(|(x, y)| self.alpha(*x, *y)).sum::<f64>() / 4.0 > self.level;
+                let separate_outside = crossings.len() == 4 && center_inside;
+                let n = crossings.len();
+                for (i, (entering, edge)) in crossings.iter().enumerate() {
+                    if *entering {
+                        let exit = if separate_outside {
+                            crossings[(i + n - 1) % n].1
+                        } else {
+                            crossings[(i + 1) % n].1
+                        };
+                        res.push((*edge, exit));
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    /// Returns closed contours in pixels.
+    fn contours(&self) -> Vec<Vec<(f64, f64)>> {
+        let segments = self.segments();
+        let next: HashMap<EdgeKey, EdgeKey> = segments.iter().copied().collect();
+        let mut visited = HashSet::with_capacity(segments.len());
+        let mut res = Vec::new();
+        for (start, _) in &segments {
+            if visited.contains(start) {
+                continue;
+            }
+            let mut contour = Vec::new();
+            let mut key = *start;
+            while visited.insert(key) {
+                contour.push(self.crossing(key));
+                match next.get(&key) {
+                    Some(n) => key = *n,
+                    None => break,
+                }
+            }
+            res.push(contour);
+        }
+        res
+    }
+}
+
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - x).powi(2) + (p.1 - y).powi(2)).sqrt()
+}
+
+/// Marks points of open polyline that are kept after simplification.
+fn douglas_peucker(points: &[(f64, f64)], tolerance: f64, keep: &mut [bool]) {
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let (index, distance) = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    distance_to_segment(points[i], points[first], points[last]),
+                )
+            })
+            .fold((first, 0.0), |a, b| if b.1 > a.1 { b } else { a });
+        if distance > tolerance {
+            keep[index] = true;
+            stack.push((first, index));
+            stack.push((index, last));
+        }
+    }
+}
+
+/// Simplifies closed polygon. Returns `None` if the polygon degenerates.
+fn simplify_polygon(points: &[(f64, f64)], tolerance: f64) -> Option<Vec<(f64, f64)>> {
+    if points.len() < 3 {
+        return None;
+    }
+    // Split polygon at first point and the point farthest from it.
+    let far = (1..points.len())
+        .max_by(|a, b| {
+            let d = |i: &usize| distance_to_segment(points[*i], points[0], points[0]);
+            d(a).total_cmp(&d(b))
+        })
+        .unwrap();
+    let mut closed = points.to_vec();
+    closed.push(points[0]);
+    let mut keep = vec![false; closed.len()];
+    keep[0] = true;
+    keep[far] = true;
+    douglas_peucker(&closed[..=far], tolerance, &mut keep[..=far]);
+    douglas_peucker(&closed[far..], tolerance, &mut keep[far..]);
+    let res: Vec<_> = closed[..points.len()]
+        .iter()
+        .zip(&keep)
+        .filter_map(|(p, keep)| keep.then_some(*p))
+        .collect();
+    (res.len() >= 3).then_some(res)
+}
+
+/// Returns outline of the opaque part of the image as closed polygons in
+/// logical points.
+pub fn outline_for_alpha(image: &ImageData, options: &AlphaOutlineOptions) -> Vec<Vec<Point>> {
+    let ratio = image.device_pixel_ratio.unwrap_or(1.0);
+    AlphaMask::new(image, options.alpha_threshold)
+        .contours()
+        .iter()
+        .filter_map(|c| simplify_polygon(c, options.tolerance))
+        .map(|polygon| {
+            polygon
+                .into_iter()
+                .map(|(x, y)| Point {
+                    x: x / ratio,
+                    y: y / ratio,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns whether the point is inside of the outline using even-odd rule.
+pub fn outline_contains(polygons: &[Vec<Point>], point: &Point) -> bool {
+    let mut inside = false;
+    for polygon in polygons {
+        let mut prev = match polygon.last() {
+            Some(p) => p,
+            None => continue,
+        };
+        for p in polygon {
+            if (p.y > point.y) != (prev.y > point.y)
+                && point.x < (prev.x - p.x) * (point.y - p.y) / (prev.y - p.y) + p.x
+            {
+                inside = !inside;
+            }
+            prev = p;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{outline_contains, outline_for_alpha, simplify_polygon, AlphaOutlineOptions};
+    use crate::api_model::{ImageData, PixelFormat, Point};
+
+    fn image_from_mask(mask: &[&str]) -> ImageData {
+        let mut image = ImageData::new(
+            mask[0].len() as i32,
+            mask.len() as i32,
+            PixelFormat::Rgba,
+            None,
+        );
+        image.data = mask
+            .iter()
+            .flat_map(|row| row.chars())
+            .flat_map(|c| [0, 0, 0, if c == '#' { 255 } else { 0 }])
+            .collect();
+        image
+    }
+
+    fn area(polygon: &[Point]) -> f64 {
+        let mut res = 0.0;
+        let mut prev = polygon.last().unwrap();
+        for p in polygon {
+            res += prev.x * p.y - p.x * prev.y;
+            prev = p;
+        }
+        res / 2.0
+    }
+
+    #[test]
+    fn test_square() {
+        let image = image_from_mask(&["......", ".####.", ".####.", ".####.", "......"]);
+        let outline = outline_for_alpha(&image, &AlphaOutlineOptions::default());
+        assert_eq!(outline.len(), 1);
+        // Corners are cut by marching squares, edges are straight.
+        assert!((4..=8).contains(&outline[0].len()));
+        for p in &outline[0] {
+            assert!((1.0..=5.0).contains(&p.x) && (1.0..=4.0).contains(&p.y));
+        }
+        assert!(outline_contains(&outline, &Point { x: 3.0, y: 2.5 }));
+        assert!(!outline_contains(&outline, &Point { x: 0.5, y: 2.5 }));
+    }
+
+    #[test]
+    fn test_hole_and_islands() {
+        let image = image_from_mask(&[
+            ".......", ".#####.", ".#...#.", ".#...#.", ".#####.", ".......", "##...##",
+        ]);
+        let options = AlphaOutlineOptions {
+            tolerance: 0.1,
+            ..Default::default()
+        };
+        let outline = outline_for_alpha(&image, &options);
+        assert_eq!(outline.len(), 4);
+        let areas: Vec<f64> = outline.iter().map(|p| area(p)).collect();
+        // Hole has opposite winding to outer contours.
+        let positive = areas.iter().filter(|a| **a > 0.0).count();
+        assert!(positive == 1 || positive == 3, "{areas:?}");
+        assert!(outline_contains(&outline, &Point { x: 1.5, y: 2.5 }));
+        assert!(!outline_contains(&outline, &Point { x: 3.5, y: 3.5 }));
+        assert!(outline_contains(&outline, &Point { x: 0.5, y: 6.5 }));
+        assert!(!outline_contains(&outline, &Point { x: 3.5, y: 6.5 }));
+    }
+
+    #[test]
+    fn test_threshold_and_scale() {
+        let mut image = image_from_mask(&["....", ".##.", ".##.", "...."]);
+        image.device_pixel_ratio = Some(2.0);
+        image.data[(5 * 4 + 3) as usize] = 100;
+        let options = AlphaOutlineOptions::default();
+        let outline = outline_for_alpha(&image, &options);
+        assert_eq!(outline.len(), 1);
+        assert!(outline[0].iter().all(|p| p.x <= 2.0 && p.y <= 2.0));
+        let options = AlphaOutlineOptions {
+            alpha_threshold: 255,
+            ..options
+        };
+        assert!(outline_for_alpha(&image, &options).is_empty());
+    }
+
+    #[test]
+    fn test_simplify() {
+        let circle: Vec<(f64, f64)> = (0..100)
+            .map(|i| {
+                let a = i as f64 / 100.0 * std::f64::consts::TAU;
+                (50.0 + 40.0 * a.cos(), 50.0 + 40.0 * a.sin())
+            })
+            .collect();
+        let simplified = simplify_polygon(&circle, 1.0).unwrap();
+        assert!(simplified.len() > 8 && simplified.len() < 30);
+        assert_eq!(simplify_polygon(&[(0.0, 0.0), (1.0, 1.0)], 1.0), None);
+    }
+}
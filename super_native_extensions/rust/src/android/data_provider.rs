@@ -23,6 +23,7 @@ use crate::{
     data_provider_manager::{DataProviderHandle, PlatformDataProviderDelegate},
     error::{NativeExtensionsError, NativeExtensionsResult},
     util::NextId,
+    value_cbor::value_for_format,
     value_coerce::{CoerceToData, StringFormat},
     value_promise::{ValuePromise, ValuePromiseResult},
 };
@@ -85,6 +86,16 @@ fn contains(l: &[String], s: &str) -> bool {
     l.iter().any(|v| v == s)
 }
 
+thread_local! {
+    /// Handles of providers on the current clip.
+    static CURRENT_CLIP: RefCell<Vec<Arc<DataProviderHandle>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Lazy values are sent to the content provider caller and not retained.
+pub fn platform_provider_cache_bytes() -> Option<usize> {
+    None
+}
+
 impl PlatformDataProvider {
     pub fn new(
         delegate: Weak<dyn PlatformDataProviderDelegate>,
@@ -278,15 +289,18 @@ impl PlatformDataProvider {
         Ok(clip_data)
     }
 
+    /// Releases data providers kept alive for the current clip.
+    pub fn release_clipboard() {
+        let handles = CURRENT_CLIP.with(|r| r.take());
+        drop(handles);
+    }
+
     pub async fn write_to_clipboard(
         providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
     ) -> NativeExtensionsResult<()> {
         let handles: Vec<_> = providers.iter().map(|p| p.1.clone()).collect();
         let providers: Vec<_> = providers.into_iter().map(|p| p.0).collect();
 
-        thread_local! {
-            static CURRENT_CLIP: RefCell<Vec<Arc<DataProviderHandle>>> = const { RefCell::new(Vec::new()) };
-        }
         // ClipManager doesn't provide any lifetime management for clip so just
         // keep the data awake until the clip is replaced.
         CURRENT_CLIP.with(|r| r.replace(handles));
@@ -420,8 +434,11 @@ fn get_data_for_uri<'a>(
     fn byte_array_from_value<'a>(
         env: &JNIEnv<'a>,
         value: &Value,
+        format: &str,
     ) -> NativeExtensionsResult<JObject<'a>> {
-        let data = value.coerce_to_data(StringFormat::Utf8).unwrap_or_default();
+        let data = value_for_format(value, format)
+            .coerce_to_data(StringFormat::Utf8)
+            .unwrap_or_default();
         let res = env.new_byte_array(data.len() as i32).unwrap();
         let data: &[u8] = &data;
         env.set_byte_array_region(&res, 0, unsafe {
@@ -443,7 +460,7 @@ fn get_data_for_uri<'a>(
             match data {
                 DataRepresentation::Simple { format, data } => {
                     if format == &mime_type {
-                        return byte_array_from_value(env, data);
+                        return byte_array_from_value(env, data, &mime_type);
                     }
                 }
                 DataRepresentation::Lazy { format, id } => {
@@ -464,7 +481,7 @@ fn get_data_for_uri<'a>(
                                 let res = get_value(value)?;
                                 match res {
                                     ValuePromiseResult::Ok { value } => {
-                                        return byte_array_from_value(env, &value);
+                                        return byte_array_from_value(env, &value, &mime_type);
                                     }
                                     ValuePromiseResult::Cancelled => return Ok(JObject::null()),
                                 }
@@ -11,8 +11,12 @@ use jni::{objects::JObject, sys::jsize, JNIEnv};
 
 use crate::{
     android::{DRAG_DROP_HELPER, JAVA_VM},
-    api_model::{DataProviderId, DragConfiguration, DragRequest, DropOperation, ImageData, Point},
+    api_model::{
+        DataProviderId, DragConfiguration, DragRequest, DropOperation, ImageData, PixelFormat,
+        Point,
+    },
     data_provider_manager::DataProviderHandle,
+    drag_image::{combine_drag_images, CombinedImageStyle},
     drag_manager::{
         DataProviderEntry, DragSessionId, PlatformDragContextDelegate, PlatformDragContextId,
     },
@@ -65,6 +69,7 @@ impl PlatformDragContext {
         env: &mut JNIEnv<'a>,
         image: &ImageData,
     ) -> NativeExtensionsResult<JObject<'a>> {
+        let image = image.to_format(PixelFormat::Rgba);
         let mut tmp = vec![0i32; (image.width * image.height) as usize];
 
         for y in 0..image.height as usize {
@@ -137,9 +142,14 @@ impl PlatformDragContext {
 
         let data = PlatformDataProvider::create_clip_data_for_data_providers(&mut env, providers)?;
 
-        let image = &request.combined_drag_image.ok_or_else(|| {
-            NativeExtensionsError::OtherError("Missing combined drag image".into())
-        })?;
+        let image = request.combined_drag_image.unwrap_or_else(|| {
+            combine_drag_images(&request.configuration.items, &CombinedImageStyle::default())
+        });
+        let image = request
+            .configuration
+            .preview_size_policy
+            .unwrap_or_default()
+            .apply_to_targetted_image(image);
         let bitmap = Self::create_bitmap(&mut env, &image.image_data)?;
         let device_pixel_ratio = image.image_data.device_pixel_ratio.unwrap_or(1.0);
         let point_in_rect = Point {
@@ -18,13 +18,20 @@ use url::Url;
 
 use crate::{
     android::{CLIP_DATA_HELPER, CONTEXT, JAVA_VM},
+    api_model::ClipboardIntent,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::{FormatConverter, HtmlToText},
     reader_manager::{ReadProgress, VirtualFileReader},
     util::DropNotifier,
 };
 
 use super::MIME_TYPE_URI_LIST;
 
+/// Converters used to synthesize formats not provided by clip data.
+pub fn platform_format_converters() -> Vec<Rc<dyn FormatConverter>> {
+    vec![Rc::new(HtmlToText::new(&["text/html"], "text/plain"))]
+}
+
 pub struct PlatformDataReader {
     clip_data: Option<GlobalRef>,
     // If needed enhance life of local data source
@@ -32,6 +39,18 @@ pub struct PlatformDataReader {
 }
 
 impl PlatformDataReader {
+    pub async fn get_clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        Ok(None)
+    }
+
+    /// Not provided; Android items reference content URIs, not paths.
+    pub async fn get_local_path_for_item(
+        &self,
+        _item: i64,
+    ) -> NativeExtensionsResult<Option<PathBuf>> {
+        Ok(None)
+    }
+
     pub async fn get_item_format_for_uri(
         &self,
         _item: i64,
@@ -0,0 +1,423 @@
+//! Rust API for reading and writing the clipboard without Flutter.
+//!
+//! The types here use the same platform implementation as the message channel
+//! handlers, but lazy data is supplied by Rust closures instead of Dart
+//! isolates. A [`Context`] must be alive and an `irondash_run_loop` run loop
+//! must be running on current thread (usually the main thread).
+//!
+//! On Linux the clipboard is accessed through GTK. Flutter embedders
+//! initialize GTK themselves, but other hosts must call `gtk::init()` on the
+//! main thread before creating the [`Context`]; The platform code assumes
+//! GTK is initialized and does not initialize it on its own.
+//!
+//! ```ignore
+//! let _context = Context::new();
+//! let writer = ClipboardWriter::new();
+//! writer
+//!     .write(
+//!         DataProvider::new()
+//!             .with_value("text/plain", "Hello")
+//!             .with_lazy_value("text/html", || "<b>Hello</b>".into()),
+//!     )
+//!     .await?;
+//!
+//! let reader = ClipboardReader::new()?;
+//! for item in reader.read_all().await? {
+//!     println!("{:?}", item.formats());
+//! }
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    path::PathBuf,
+    rc::{Rc, Weak},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use irondash_message_channel::IsolateId;
+
+use crate::{
+    api_model::{self, DataProviderValueId, DataRepresentation},
+    data_provider_manager::{
+        DataProviderHandle, PlatformDataProviderDelegate, VirtualFileResult, VirtualSessionHandle,
+    },
+    format_converter::{item_data, item_formats, GetFormatConverterRegistry},
+    platform_impl::platform::{PlatformDataProvider, PlatformDataReader},
+    util::{DropNotifier, NextId},
+    value_promise::{ValuePromise, ValuePromiseResult},
+};
+
+pub use crate::{
+    api_model::ClipboardIntent,
+    context::Context,
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::FormatConverter,
+};
+pub use irondash_message_channel::Value;
+
+/// Providers created through the Rust API are not associated with any
+/// isolate; The platform code only passes this back to the delegate.
+const RUST_ISOLATE: IsolateId = IsolateId(-1);
+
+type LazyValue = Box<dyn Fn() -> Value>;
+
+enum Representation {
+    Value { format: String, value: Value },
+    Lazy { format: String, value: LazyValue },
+}
+
+/// Single clipboard item with one or more representations.
+#[derive(Default)]
+pub struct DataProvider {
+    representations: Vec<Representation>,
+    suggested_name: Option<String>,
+    clipboard_intent: Option<ClipboardIntent>,
+}
+
+impl DataProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds representation with data known upfront.
+    pub fn with_value<V: Into<Value>>(mut self, format: &str, value: V) -> Self {
+        self.representations.push(Representation::Value {
+            format: format.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Adds representation produced on demand. The closure is invoked on
+    /// current thread when another application requests the format and may
+    /// be invoked more than once.
+    pub fn with_lazy_value<F: Fn() -> Value + 'static>(mut self, format: &str, value: F) -> Self {
+        self.representations.push(Representation::Lazy {
+            format: format.into(),
+            value: Box::new(value),
+        });
+        self
+    }
+
+    pub fn with_suggested_name(mut self, name: &str) -> Self {
+        self.suggested_name = Some(name.into());
+        self
+    }
+
+    /// Marks file URIs in this item as copied or cut, so that file managers
+    /// can copy or move the files on paste.
+    pub fn with_clipboard_intent(mut self, intent: ClipboardIntent) -> Self {
+        self.clipboard_intent = Some(intent);
+        self
+    }
+}
+
+/// Answers lazy data requests for all providers written in one call.
+struct LazyValueDelegate {
+    values: RefCell<HashMap<DataProviderValueId, LazyValue>>,
+    next_id: Cell<i64>,
+}
+
+impl LazyValueDelegate {
+    fn new() -> Self {
+        Self {
+            values: RefCell::new(HashMap::new()),
+            next_id: Cell::new(1),
+        }
+    }
+
+    fn convert(&self, provider: DataProvider) -> api_model::DataProvider {
+        let representations = provider
+            .representations
+            .into_iter()
+            .map(|r| match r {
+                Representation::Value { format, value } => DataRepresentation::Simple {
+                    format,
+                    data: value,
+                },
+                Representation::Lazy { format, value } => {
+                    let id: DataProviderValueId = self.next_id.next_id().into();
+                    self.values.borrow_mut().insert(id, value);
+                    DataRepresentation::Lazy { id, format }
+                }
+            })
+            .collect();
+        api_model::DataProvider {
+            representations,
+            suggested_name: provider.suggested_name,
+            clipboard_intent: provider.clipboard_intent,
+        }
+    }
+
+    fn value(&self, id: DataProviderValueId) -> ValuePromiseResult {
+        match self.values.borrow().get(&id) {
+            Some(value) => ValuePromiseResult::Ok { value: value() },
+            None => ValuePromiseResult::Cancelled,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl PlatformDataProviderDelegate for LazyValueDelegate {
+    fn get_lazy_data(
+        &self,
+        _isolate_id: IsolateId,
+        data_id: DataProviderValueId,
+        on_done: Option<Box<dyn FnOnce()>>,
+    ) -> Arc<ValuePromise> {
+        let res = Arc::new(ValuePromise::new());
+        res.set(self.value(data_id));
+        if let Some(on_done) = on_done {
+            on_done();
+        }
+        res
+    }
+
+    async fn get_lazy_data_async(
+        &self,
+        _isolate_id: IsolateId,
+        data_id: DataProviderValueId,
+    ) -> ValuePromiseResult {
+        self.value(data_id)
+    }
+
+    fn get_virtual_file(
+        &self,
+        _isolate_id: IsolateId,
+        _virtual_file_id: DataProviderValueId,
+        _stream_handle: i32,
+        _on_size_known: Box<dyn Fn(Option<i64>)>,
+        _on_progress: Box<dyn Fn(f64 /* 0.0 - 1.0 */)>,
+        on_done: Box<dyn FnOnce(VirtualFileResult)>,
+    ) -> Arc<VirtualSessionHandle> {
+        on_done(VirtualFileResult::Error {
+            message: "virtual files are not supported by the Rust API".into(),
+        });
+        Arc::new(DropNotifier::new(|| {}).into())
+    }
+}
+
+/// Registers converter used by [`ClipboardReader`] and by the readers
+/// exposed to Dart to synthesize formats. Converters registered later take
+/// precedence over earlier ones and over the built-in converters.
+pub fn register_format_converter(converter: Rc<dyn FormatConverter>) {
+    Context::get()
+        .format_converter_registry()
+        .register(converter);
+}
+
+/// Writes items to the system clipboard.
+pub struct ClipboardWriter {}
+
+impl ClipboardWriter {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Replaces clipboard content with single item.
+    pub async fn write(&self, provider: DataProvider) -> NativeExtensionsResult<()> {
+        self.write_items(vec![provider]).await
+    }
+
+    /// Replaces clipboard content with given items. Lazy value closures are
+    /// kept alive until the clipboard content is replaced.
+    pub async fn write_items(&self, providers: Vec<DataProvider>) -> NativeExtensionsResult<()> {
+        let delegate = Rc::new(LazyValueDelegate::new());
+        let weak_delegate: Weak<dyn PlatformDataProviderDelegate> = Rc::downgrade(&delegate) as _;
+        let mut platform_providers = Vec::new();
+        for provider in providers {
+            let provider = Rc::new(PlatformDataProvider::new(
+                weak_delegate.clone(),
+                RUST_ISOLATE,
+                delegate.convert(provider),
+            ));
+            provider.assign_weak_self(Rc::downgrade(&provider));
+            // Every handle holds the delegate; It is released together with
+            // the last provider.
+            let delegate = delegate.clone();
+            let handle: DataProviderHandle = DropNotifier::new(move || drop(delegate)).into();
+            platform_providers.push((provider, Arc::new(handle)));
+        }
+        PlatformDataProvider::write_to_clipboard(platform_providers).await
+    }
+}
+
+/// Content of single clipboard item returned by [`ClipboardReader::read_all`].
+#[derive(Debug, Clone)]
+pub struct ItemData {
+    pub suggested_name: Option<String>,
+    /// Values for all formats of the item, in the order reported by the
+    /// platform.
+    pub values: Vec<(String, Value)>,
+}
+
+impl ItemData {
+    pub fn formats(&self) -> Vec<&str> {
+        self.values.iter().map(|v| v.0.as_str()).collect()
+    }
+
+    pub fn value(&self, format: &str) -> Option<&Value> {
+        self.values.iter().find(|v| v.0 == format).map(|v| &v.1)
+    }
+}
+
+/// Reads snapshot of the system clipboard. Items are identified by handles
+/// returned from [`ClipboardReader::items`].
+pub struct ClipboardReader {
+    reader: Rc<PlatformDataReader>,
+}
+
+impl ClipboardReader {
+    pub fn new() -> NativeExtensionsResult<Self> {
+        Ok(Self {
+            reader: PlatformDataReader::new_clipboard_reader()?,
+        })
+    }
+
+    pub async fn items(&self) -> NativeExtensionsResult<Vec<i64>> {
+        self.reader.get_items().await
+    }
+
+    /// Returns formats of the item, including formats synthesized by
+    /// registered converters.
+    pub async fn formats(&self, item: i64) -> NativeExtensionsResult<Vec<String>> {
+        item_formats(&self.reader, item).await
+    }
+
+    pub async fn suggested_name(&self, item: i64) -> NativeExtensionsResult<Option<String>> {
+        self.reader.get_suggested_name_for_item(item).await
+    }
+
+    /// Returns local path of file item. Currently only provided on Linux.
+    pub async fn local_path(&self, item: i64) -> NativeExtensionsResult<Option<PathBuf>> {
+        self.reader.get_local_path_for_item(item).await
+    }
+
+    /// Returns whether files on clipboard were copied or cut, if the source
+    /// application provided this information.
+    pub async fn clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        self.reader.get_clipboard_intent().await
+    }
+
+    /// Returns value of given item in given format. Formats that the item
+    /// does not provide are read as `Value::Null`.
+    pub async fn value(&self, item: i64, format: &str) -> NativeExtensionsResult<Value> {
+        item_data(&self.reader, item, format.into(), None).await
+    }
+
+    /// Reads all formats of all items, including synthesized formats. Virtual
+    /// files are not included.
+    pub async fn read_all(&self) -> NativeExtensionsResult<Vec<ItemData>> {
+        let mut res = Vec::new();
+        for item in self.items().await? {
+            let mut values = Vec::new();
+            for format in self.formats(item).await? {
+                let value = self.value(item, &format).await?;
+                values.push((format, value));
+            }
+            res.push(ItemData {
+                suggested_name: self.suggested_name(item).await?,
+                values,
+            });
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use irondash_message_channel::ValueTupleList;
+
+    use super::{ClipboardIntent, ClipboardReader, ClipboardWriter, DataProvider, Value};
+    use crate::platform::run_test;
+
+    #[test]
+    fn test_write_and_read() {
+        run_test(async {
+            let calls = Rc::new(Cell::new(0));
+            let calls_clone = calls.clone();
+            ClipboardWriter::new()
+                .write(
+                    DataProvider::new()
+                        .with_value("text/plain", Value::String("Hello".into()))
+                        .with_lazy_value("text/html", move || {
+                            calls_clone.set(calls_clone.get() + 1);
+                            Value::String("<b>Hello</b>".into())
+                        })
+                        .with_suggested_name("hello.txt"),
+                )
+                .await
+                .unwrap();
+            assert_eq!(calls.get(), 0);
+
+            let items = ClipboardReader::new().unwrap().read_all().await.unwrap();
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].formats(), vec!["text/plain", "text/html"]);
+            assert_eq!(
+                items[0].value("text/html"),
+                Some(&Value::String("<b>Hello</b>".into()))
+            );
+            assert_eq!(items[0].suggested_name.as_deref(), Some("hello.txt"));
+            assert_eq!(calls.get(), 1);
+
+            // Replacing clipboard content releases the closures.
+            ClipboardWriter::new()
+                .write(DataProvider::new().with_value("text/plain", Value::String("World".into())))
+                .await
+                .unwrap();
+            assert_eq!(Rc::strong_count(&calls), 1);
+        });
+    }
+
+    #[test]
+    fn test_clipboard_intent() {
+        run_test(async {
+            ClipboardWriter::new()
+                .write(
+                    DataProvider::new()
+                        .with_value("text/uri-list", Value::String("file:///tmp/a".into()))
+                        .with_clipboard_intent(ClipboardIntent::Cut),
+                )
+                .await
+                .unwrap();
+            let reader = ClipboardReader::new().unwrap();
+            assert_eq!(
+                reader.clipboard_intent().await.unwrap(),
+                Some(ClipboardIntent::Cut)
+            );
+
+            ClipboardWriter::new()
+                .write(DataProvider::new().with_value("text/plain", Value::String("a".into())))
+                .await
+                .unwrap();
+            let reader = ClipboardReader::new().unwrap();
+            assert_eq!(reader.clipboard_intent().await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn test_structured_value() {
+        run_test(async {
+            let format = "application/x-test-items+cbor";
+            let value = Value::List(vec![
+                Value::Map(ValueTupleList::new(vec![(
+                    Value::String("id".into()),
+                    Value::I64(1),
+                )])),
+                Value::F64List(vec![0.5]),
+            ]);
+            let value_clone = value.clone();
+            ClipboardWriter::new()
+                .write(DataProvider::new().with_lazy_value(format, move || value_clone.clone()))
+                .await
+                .unwrap();
+            let reader = ClipboardReader::new().unwrap();
+            let item = reader.items().await.unwrap()[0];
+            assert_eq!(reader.value(item, format).await.unwrap(), value);
+        });
+    }
+}
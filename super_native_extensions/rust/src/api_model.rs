@@ -1,7 +1,10 @@
+#[cfg(feature = "menu")]
 use std::rc::Rc;
 
 use irondash_message_channel::{IntoValue, TryFromValue, Value};
 
+use crate::image_resample::PreviewSizePolicy;
+#[cfg(feature = "menu")]
 use crate::platform_impl::platform::PlatformMenu;
 
 #[derive(Clone, Debug, Default, PartialEq, TryFromValue, IntoValue)]
@@ -72,15 +75,30 @@ pub struct Size {
     pub height: f64,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, TryFromValue, IntoValue)]
+#[irondash(rename_all = "camelCase")]
+pub enum PixelFormat {
+    /// Straight (not premultiplied) RGBA. Used by images coming from Dart.
+    #[default]
+    Rgba,
+    Bgra,
+    RgbaPremultiplied,
+    BgraPremultiplied,
+    /// Single byte per pixel, no alpha.
+    Gray,
+}
+
 #[derive(Clone, Debug, Default, PartialEq, TryFromValue, IntoValue)]
 #[irondash(rename_all = "camelCase")]
 pub struct ImageData {
     pub width: i32,
     pub height: i32,
     pub bytes_per_row: i32,
-    /// Pixel data as RGBA bytes.
+    /// Pixel data in [`ImageData::format`].
     pub data: Vec<u8>,
     pub device_pixel_ratio: Option<f64>,
+    /// Format of pixel data; RGBA if not specified.
+    pub pixel_format: Option<PixelFormat>,
 }
 
 impl ImageData {
@@ -100,6 +118,12 @@ impl ImageData {
 #[derive(Debug, TryFromValue, IntoValue, Clone, Copy, PartialEq, Hash, Eq)]
 pub struct DataProviderValueId(i64);
 
+impl From<i64> for DataProviderValueId {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
 #[derive(Debug, TryFromValue, IntoValue, Clone, Copy, PartialEq, Hash, Eq)]
 pub struct DataProviderId(i64);
 
@@ -156,6 +180,16 @@ impl DataRepresentation {
 pub struct DataProvider {
     pub representations: Vec<DataRepresentation>,
     pub suggested_name: Option<String>,
+    /// Whether pasting the item should copy or move it (i.e. file manager
+    /// cut). Only used for items with file URIs.
+    pub clipboard_intent: Option<ClipboardIntent>,
+}
+
+#[derive(Debug, TryFromValue, IntoValue, Copy, Clone, PartialEq, Eq)]
+#[irondash(rename_all = "camelCase")]
+pub enum ClipboardIntent {
+    Copy,
+    Cut,
 }
 
 //
@@ -193,6 +227,40 @@ pub struct DragConfiguration {
     pub allowed_operations: Vec<DropOperation>,
     pub animates_to_starting_position_on_cancel_or_fail: bool,
     pub prefers_full_size_previews: bool,
+    /// Shadow drawn below the drag image on platforms without native drag
+    /// shadow (Linux, Windows). Default style is used if not specified.
+    pub shadow_style: Option<ShadowStyle>,
+    /// Size limit for drag images. Default policy is used if not specified.
+    pub preview_size_policy: Option<PreviewSizePolicy>,
+}
+
+#[derive(TryFromValue, Debug, Clone, PartialEq)]
+#[irondash(rename_all = "camelCase")]
+pub struct ShadowStyle {
+    /// ARGB color; Alpha is multiplied by opacity.
+    pub color: i64,
+    pub opacity: f64,
+    /// Offset, spread and radius are in logical points.
+    pub offset_x: f64,
+    pub offset_y: f64,
+    pub spread: f64,
+    pub radius: f64,
+}
+
+impl ShadowStyle {
+    /// Style used when drag configuration does not specify one. Blur radius
+    /// is 10 physical pixels on every display, same as the shadow drawn
+    /// before the style became configurable.
+    pub fn default_for_device_pixel_ratio(device_pixel_ratio: f64) -> Self {
+        Self {
+            color: 0xFF000000,
+            opacity: 0.5,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            spread: 0.0,
+            radius: 10.0 / device_pixel_ratio,
+        }
+    }
 }
 
 impl DragConfiguration {
@@ -220,6 +288,7 @@ pub enum DropOperation {
     Link,          // macOS, Windows, Linux
 }
 
+#[cfg(feature = "menu")]
 #[derive(TryFromValue, Debug)]
 #[irondash(rename_all = "camelCase")]
 pub struct MenuConfiguration {
@@ -228,6 +297,9 @@ pub struct MenuConfiguration {
     pub preview_size: Option<Size>,
     pub lift_image: TargettedImage,
     pub menu_handle: i64,
+    /// Size limit for preview and lift images. Default policy is used if not
+    /// specified.
+    pub preview_size_policy: Option<PreviewSizePolicy>,
     #[irondash(skip)]
     pub menu: Option<Rc<PlatformMenu>>,
 }
@@ -240,6 +312,7 @@ pub struct WritingToolsConfiguration {
     pub text: String,
 }
 
+#[cfg(feature = "menu")]
 #[derive(TryFromValue)]
 #[irondash(rename_all = "camelCase")]
 pub struct ShowContextMenuRequest {
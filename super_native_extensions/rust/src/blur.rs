@@ -1,179 +1,327 @@
-// Original source:
-// https://github.com/flozz/StackBlur/blob/master/src/stackblur.js
-
-/*
- * @copyright (c) 2010 Mario Klingemann
- *
- * Permission is hereby granted, free of charge, to any person
- * obtaining a copy of this software and associated documentation
- * files (the "Software"), to deal in the Software without
- * restriction, including without limitation the rights to use,
- * copy, modify, merge, publish, distribute, sublicense, and/or sell
- * copies of the Software, and to permit persons to whom the
- * Software is furnished to do so, subject to the following
- * conditions:
- *
- * The above copyright notice and this permission notice shall be
- * included in all copies or substantial portions of the Software.
- *
- * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
- * EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES
- * OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND
- * NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
- * HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY,
- * WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING
- * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR
- * OTHER DEALINGS IN THE SOFTWARE.
- */
-
-use std::cmp::min;
-
-pub fn blur_image_data(
+//! Box and approximated Gaussian blur of 8-bit images with interleaved
+//! channels.
+//!
+//! Each box pass is a running sum over columns, where the inner loops work
+//! on whole rows so that they can be vectorized by the compiler. Horizontal
+//! passes are done by transposing the image and blurring columns again. Rows
+//! are split between worker threads for larger images; The workers are
+//! started once per blur and run all passes.
+
+use std::{num::NonZeroUsize, ops::Range, slice, sync::Barrier, thread};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlurKind {
+    /// Single box pass in each direction.
+    Box,
+    /// Three box passes in each direction, approximating Gaussian blur with
+    /// standard deviation of half the radius.
+    Gaussian,
+}
+
+/// Images smaller than this (in bytes) are blurred on current thread.
+const PARALLEL_THRESHOLD: usize = 256 * 1024;
+const MAX_WORKERS: usize = 8;
+/// Keeps the fixed point arithmetic in box pass within `u32`.
+const MAX_RADIUS: usize = 8192;
+
+/// Blurs image with tightly packed rows of `width` pixels, each having
+/// `channels` bytes. Channels are blurred independently; For images with
+/// alpha channel the color channels should be premultiplied.
+pub fn blur(
+    pixels: &mut [u8],
+    width: usize,
+    height: usize,
+    channels: usize,
+    radius: usize,
+    kind: BlurKind,
+) {
+    let workers = if pixels.len() < PARALLEL_THRESHOLD {
+        1
+    } else {
+        thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1)
+            .min(MAX_WORKERS)
+    };
+    blur_with_workers(pixels, width, height, channels, radius, kind, workers);
+}
+
+pub(crate) fn blur_with_workers(
     pixels: &mut [u8],
-    top_x: usize,
-    top_y: usize,
     width: usize,
     height: usize,
+    channels: usize,
     radius: usize,
+    kind: BlurKind,
+    workers: usize,
 ) {
-    let div = 2 * radius + 1;
-    let width_minus_1 = width - 1;
-    let height_minus_1 = height - 1;
-    let radius_plus_1 = radius + 1;
-    let sum_factor = radius_plus_1 * (radius_plus_1 + 1) / 2;
-
-    let mut stack = vec![0_usize; div];
-
-    let stack_start: usize = 0;
-    let stack_end = radius_plus_1;
-
-    let mut stack_in;
-    let mut stack_out;
-
-    let mul_sum = MUL_TABLE[radius] as usize;
-    let shg_sum = SHG_TABLE[radius] as usize;
-
-    let mut p: usize;
-    let mut yw = 0;
-    let mut yi = top_y * width + top_x;
-
-    for _y in 0..height {
-        let mut pr = pixels[yi] as usize;
-        let mut r_out_sum = radius_plus_1 * pr;
-        let mut r_sum = sum_factor * pr;
-
-        (0..radius_plus_1).for_each(|i| {
-            stack[i] = pr;
-        });
-
-        let mut r_in_sum = 0;
-        for i in 1..radius_plus_1 {
-            p = yi + min(width_minus_1, i);
-            let val = pixels[p] as usize;
-            stack[i - 1 + radius_plus_1] = val;
-            r_sum += val * (radius_plus_1 - i);
-            r_in_sum += val;
+    assert_eq!(pixels.len(), width * height * channels);
+    let radius = radius.min(MAX_RADIUS);
+    let radii: Vec<usize> = match kind {
+        BlurKind::Box => vec![radius],
+        BlurKind::Gaussian => gaussian_box_radii(radius as f64 / 2.0).to_vec(),
+    };
+    let radii: Vec<usize> = radii.into_iter().filter(|r| *r > 0).collect();
+    if radii.is_empty() || pixels.is_empty() {
+        return;
+    }
+    let box_passes = |row_len: usize| {
+        radii.iter().map(move |radius| Pass::BoxBlur {
+            row_len,
+            radius: *radius,
+        })
+    };
+    // Even number of passes, so the result ends up in `pixels`.
+    let passes: Vec<Pass> = box_passes(width * channels)
+        .chain([Pass::Transpose { width, height }])
+        .chain(box_passes(height * channels))
+        .chain([Pass::Transpose {
+            width: height,
+            height: width,
+        }])
+        .collect();
+    let mut tmp = vec![0u8; pixels.len()];
+    let workers = workers.clamp(1, width.min(height));
+    run_passes(pixels, &mut tmp, &passes, channels, workers);
+}
+
+/// Radii of three box blurs approximating Gaussian blur with given standard
+/// deviation.
+fn gaussian_box_radii(sigma: f64) -> [usize; 3] {
+    let n = 3.0;
+    let variance = 12.0 * sigma * sigma;
+    let ideal_width = (variance / n + 1.0).sqrt();
+    let mut lower = ideal_width.floor() as i64;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let upper = lower + 2;
+    let l = lower as f64;
+    // Number of passes using the lower width.
+    let m = ((variance - n * l * l - 4.0 * n * l - 3.0 * n) / (-4.0 * l - 4.0)).round() as i64;
+    [0, 1, 2].map(|i| {
+        let width = if i < m { lower } else { upper };
+        ((width - 1) / 2).max(0) as usize
+    })
+}
+
+/// Single pass over the image. Reads whole source buffer and writes rows of
+/// destination buffer.
+enum Pass {
+    /// Box blur of each byte column.
+    BoxBlur { row_len: usize, radius: usize },
+    /// Transposes `width` x `height` image to `height` x `width` image.
+    Transpose { width: usize, height: usize },
+}
+
+impl Pass {
+    /// Length of destination row in bytes.
+    fn row_len(&self, channels: usize) -> usize {
+        match self {
+            Pass::BoxBlur { row_len, .. } => *row_len,
+            Pass::Transpose { height, .. } => height * channels,
+        }
+    }
+
+    /// Produces destination rows starting at `first`.
+    fn run(&self, src: &[u8], first: usize, rows: &mut [u8], channels: usize) {
+        match self {
+            Pass::BoxBlur { row_len, radius } => {
+                box_blur_columns(src, first, rows, *row_len, *radius)
+            }
+            Pass::Transpose { width, height } => {
+                transpose(src, first, rows, *width, *height, channels)
+            }
         }
+    }
+}
+
+/// Buffer accessed by multiple workers at once. Workers only read the source
+/// buffer of current pass and write disjoint rows of destination buffer.
+#[derive(Clone, Copy)]
+struct SharedBuffer {
+    ptr: *mut u8,
+    len: usize,
+}
+
+unsafe impl Send for SharedBuffer {}
+unsafe impl Sync for SharedBuffer {}
 
-        stack_in = stack_start;
-        stack_out = stack_end;
-        for x in 0..width {
-            pixels[yi] = ((r_sum * mul_sum) >> shg_sum) as u8;
-            r_sum -= r_out_sum;
-            r_out_sum -= stack[stack_in];
-            p = x + radius + 1;
-            p = yw + min(width_minus_1, p);
-
-            let val = pixels[p] as usize;
-            stack[stack_in] = val;
-            r_in_sum += val;
-            r_sum += r_in_sum;
-            stack_in = (stack_in + 1) % div;
-            pr = stack[stack_out];
-            r_out_sum += pr;
-            r_in_sum -= pr;
-            stack_out = (stack_out + 1) % div;
-            yi += 1;
+impl SharedBuffer {
+    fn new(data: &mut [u8]) -> Self {
+        Self {
+            ptr: data.as_mut_ptr(),
+            len: data.len(),
         }
-        yw += width;
     }
 
-    for x in 0..width {
-        let mut yi = top_y * width + top_x + x;
-        let mut pr = pixels[yi] as usize;
-        let mut r_out_sum = radius_plus_1 * pr;
-        let mut r_sum = sum_factor * pr;
-
-        (0..radius_plus_1).for_each(|i| {
-            stack[i] = pr;
-        });
-
-        let mut r_in_sum = 0;
-        let mut yp = width;
-        for i in 1..=radius {
-            yi = yp + x;
-            let pr = pixels[yi] as usize;
-            stack[i - 1 + radius_plus_1] = pr;
-            r_sum += pr * (radius_plus_1 - i);
-            r_in_sum += pr;
-
-            if i < height_minus_1 {
-                yp += width;
+    /// Safety: Nothing may write to the buffer while the slice is alive.
+    unsafe fn as_slice<'a>(&self) -> &'a [u8] {
+        slice::from_raw_parts(self.ptr, self.len)
+    }
+
+    /// Safety: The range may not be accessed through any other slice while
+    /// the returned slice is alive.
+    unsafe fn range_mut<'a>(&self, range: Range<usize>) -> &'a mut [u8] {
+        assert!(range.start <= range.end && range.end <= self.len);
+        slice::from_raw_parts_mut(self.ptr.add(range.start), range.len())
+    }
+}
+
+/// Runs the passes alternating between `pixels` and `tmp` buffers, starting
+/// by reading `pixels`. Destination rows of each pass are split between
+/// `workers` threads.
+fn run_passes(pixels: &mut [u8], tmp: &mut [u8], passes: &[Pass], channels: usize, workers: usize) {
+    if workers == 1 {
+        let (mut src, mut dst) = (pixels, tmp);
+        for pass in passes {
+            pass.run(src, 0, dst, channels);
+            std::mem::swap(&mut src, &mut dst);
+        }
+        return;
+    }
+    let buffers = [SharedBuffer::new(pixels), SharedBuffer::new(tmp)];
+    let barrier = Barrier::new(workers);
+    let worker = |index: usize| {
+        for (i, pass) in passes.iter().enumerate() {
+            let (src, dst) = (buffers[i % 2], buffers[(i + 1) % 2]);
+            let row_len = pass.row_len(channels);
+            let rows = dst.len / row_len;
+            let rows_per_worker = rows.div_ceil(workers);
+            let first = (index * rows_per_worker).min(rows);
+            let end = (first + rows_per_worker).min(rows);
+            if first < end {
+                // Safety: Source is only read during the pass and each worker
+                // writes different rows of destination. All workers wait on
+                // the barrier before buffers switch roles.
+                unsafe {
+                    let dst = dst.range_mut(first * row_len..end * row_len);
+                    pass.run(src.as_slice(), first, dst, channels);
+                }
             }
+            barrier.wait();
+        }
+    };
+    thread::scope(|scope| {
+        let worker = &worker;
+        for index in 1..workers {
+            scope.spawn(move || worker(index));
         }
+        worker(0);
+    });
+}
 
-        yi = x;
-        stack_in = stack_start;
-        stack_out = stack_end;
-
-        for y in 0..height {
-            p = yi;
-            pixels[p] = ((r_sum * mul_sum) >> shg_sum) as u8;
-            r_sum -= r_out_sum;
-            r_out_sum -= stack[stack_in];
-            p = y + radius_plus_1;
-            p = x + min(height_minus_1, p) * width;
-            pr = pixels[p] as usize;
-            stack[stack_in] = pr;
-            r_in_sum += pr;
-            r_sum += r_in_sum;
-            stack_in = (stack_in + 1) % div;
-            pr = stack[stack_out];
-            r_out_sum += pr;
-            r_in_sum -= pr;
-            stack_out = (stack_out + 1) % div;
-            yi += width;
+/// Box blur of each byte column of `src`, producing rows of `dst` starting
+/// at `first`. Pixels past the edges repeat the edge pixel.
+fn box_blur_columns(src: &[u8], first: usize, dst: &mut [u8], row_len: usize, radius: usize) {
+    let height = (src.len() / row_len) as isize;
+    let div = 2 * radius as u32 + 1;
+    // Fixed point reciprocal of div with 24 fractional bits.
+    let mul = ((1u32 << 24) + div / 2) / div;
+    let row = |y: isize| {
+        let y = y.clamp(0, height - 1) as usize;
+        &src[y * row_len..(y + 1) * row_len]
+    };
+    let radius = radius as isize;
+    let first = first as isize;
+    let mut sum = vec![0u32; row_len];
+    for y in first - radius..=first + radius {
+        for (s, p) in sum.iter_mut().zip(row(y)) {
+            *s += *p as u32;
+        }
+    }
+    for (i, out) in dst.chunks_exact_mut(row_len).enumerate() {
+        let y = first + i as isize;
+        for (o, s) in out.iter_mut().zip(&sum) {
+            *o = ((s * mul + (1 << 23)) >> 24) as u8;
+        }
+        let (add, remove) = (row(y + radius + 1), row(y - radius));
+        for ((s, a), r) in sum.iter_mut().zip(add).zip(remove) {
+            *s = *s + *a as u32 - *r as u32;
         }
     }
 }
 
-const MUL_TABLE: [i32; 255] = [
-    512, 512, 456, 512, 328, 456, 335, 512, 405, 328, 271, 456, 388, 335, 292, 512, 454, 405, 364,
-    328, 298, 271, 496, 456, 420, 388, 360, 335, 312, 292, 273, 512, 482, 454, 428, 405, 383, 364,
-    345, 328, 312, 298, 284, 271, 259, 496, 475, 456, 437, 420, 404, 388, 374, 360, 347, 335, 323,
-    312, 302, 292, 282, 273, 265, 512, 497, 482, 468, 454, 441, 428, 417, 405, 394, 383, 373, 364,
-    354, 345, 337, 328, 320, 312, 305, 298, 291, 284, 278, 271, 265, 259, 507, 496, 485, 475, 465,
-    456, 446, 437, 428, 420, 412, 404, 396, 388, 381, 374, 367, 360, 354, 347, 341, 335, 329, 323,
-    318, 312, 307, 302, 297, 292, 287, 282, 278, 273, 269, 265, 261, 512, 505, 497, 489, 482, 475,
-    468, 461, 454, 447, 441, 435, 428, 422, 417, 411, 405, 399, 394, 389, 383, 378, 373, 368, 364,
-    359, 354, 350, 345, 341, 337, 332, 328, 324, 320, 316, 312, 309, 305, 301, 298, 294, 291, 287,
-    284, 281, 278, 274, 271, 268, 265, 262, 259, 257, 507, 501, 496, 491, 485, 480, 475, 470, 465,
-    460, 456, 451, 446, 442, 437, 433, 428, 424, 420, 416, 412, 408, 404, 400, 396, 392, 388, 385,
-    381, 377, 374, 370, 367, 363, 360, 357, 354, 350, 347, 344, 341, 338, 335, 332, 329, 326, 323,
-    320, 318, 315, 312, 310, 307, 304, 302, 299, 297, 294, 292, 289, 287, 285, 282, 280, 278, 275,
-    273, 271, 269, 267, 265, 263, 261, 259,
-];
-
-const SHG_TABLE: [i32; 255] = [
-    9, 11, 12, 13, 13, 14, 14, 15, 15, 15, 15, 16, 16, 16, 16, 17, 17, 17, 17, 17, 17, 17, 18, 18,
-    18, 18, 18, 18, 18, 18, 18, 19, 19, 19, 19, 19, 19, 19, 19, 19, 19, 19, 19, 19, 19, 20, 20, 20,
-    20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 20, 21, 21, 21, 21, 21, 21, 21, 21, 21,
-    21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 21, 22, 22, 22, 22, 22, 22,
-    22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22, 22,
-    22, 22, 22, 22, 22, 22, 22, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23,
-    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23,
-    23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 23, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24,
-    24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24,
-    24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24,
-    24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24, 24,
-];
+/// Transposes `width` x `height` image in `src`, producing rows of the
+/// `height` x `width` image in `dst` starting at `first`.
+fn transpose(
+    src: &[u8],
+    first: usize,
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    channels: usize,
+) {
+    // Source is read in tiles of TILE rows so that the cache lines are
+    // reused for consecutive destination rows.
+    const TILE: usize = 32;
+    let row_len = height * channels;
+    for y0 in (0..height).step_by(TILE) {
+        let y1 = (y0 + TILE).min(height);
+        for (i, row) in dst.chunks_exact_mut(row_len).enumerate() {
+            let x = first + i;
+            let row = &mut row[y0 * channels..y1 * channels];
+            if channels == 1 {
+                for (y, p) in (y0..y1).zip(row.iter_mut()) {
+                    *p = src[y * width + x];
+                }
+            } else {
+                for (y, pixel) in (y0..y1).zip(row.chunks_exact_mut(channels)) {
+                    let start = (y * width + x) * channels;
+                    pixel.copy_from_slice(&src[start..start + channels]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blur, blur_with_workers, gaussian_box_radii, BlurKind};
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7919 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_box_blur() {
+        let mut pixels = vec![0u8; 25];
+        pixels[12] = 90;
+        blur(&mut pixels, 5, 5, 1, 1, BlurKind::Box);
+        for y in 0..5 {
+            for x in 0..5 {
+                let expected = if (1..4).contains(&x) && (1..4).contains(&y) {
+                    10
+                } else {
+                    0
+                };
+                assert_eq!(pixels[y * 5 + x], expected, "{x} {y}");
+            }
+        }
+
+        // Uniform image is unchanged.
+        let mut pixels = [10, 20, 30, 255].repeat(7 * 3);
+        blur(&mut pixels, 7, 3, 4, 4, BlurKind::Gaussian);
+        assert!(pixels.chunks(4).all(|p| p == [10, 20, 30, 255]));
+    }
+
+    #[test]
+    fn test_gaussian_box_radii() {
+        assert_eq!(gaussian_box_radii(5.0), [4, 4, 5]);
+        assert_eq!(gaussian_box_radii(0.5), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_workers() {
+        let (width, height) = (131, 67);
+        let source = pattern(width * height * 3);
+        for kind in [BlurKind::Box, BlurKind::Gaussian] {
+            let mut single = source.clone();
+            blur_with_workers(&mut single, width, height, 3, 9, kind, 1);
+            let mut multi = source.clone();
+            blur_with_workers(&mut multi, width, height, 3, 9, kind, 5);
+            assert_eq!(single, multi);
+            assert_ne!(single, source);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use irondash_message_channel::{
+    AsyncMethodHandler, IntoValue, MethodCall, PlatformError, PlatformResult,
+    RegisteredAsyncMethodHandler, Value,
+};
+
+use crate::{context::Context, platform_impl::platform::platform_capabilities};
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct ClipboardCapabilities {
+    /// Clipboard can hold more than one item at a time.
+    pub multiple_items: bool,
+    /// Primary selection (middle-click paste) can be read and written.
+    pub primary_selection: bool,
+    /// Platform delivers cut / copy / paste / select all events.
+    pub events: bool,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct VirtualFileCapabilities {
+    /// Virtual files from other applications can be read as a stream.
+    pub read: bool,
+    /// Virtual files from other applications can be copied to a folder.
+    pub copy: bool,
+    /// Data providers can offer virtual files to other applications.
+    pub provide: bool,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct HotKeyCapabilities {
+    pub supported: bool,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct MenuCapabilities {
+    /// Menu preview image can be updated after the menu is shown.
+    pub preview_image: bool,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct DragCapabilities {
+    /// Dropped items can animate into destination provided by Dart.
+    pub preview_animations: bool,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub clipboard: ClipboardCapabilities,
+    pub virtual_files: VirtualFileCapabilities,
+    pub hot_keys: HotKeyCapabilities,
+    pub menu: MenuCapabilities,
+    pub drag: DragCapabilities,
+}
+
+pub struct CapabilitiesManager {}
+
+impl CapabilitiesManager {
+    pub fn new() -> RegisteredAsyncMethodHandler<Self> {
+        Self {}.register("CapabilitiesManager")
+    }
+
+    /// Returns platform capabilities, with subsystems that were compiled out
+    /// reported as unsupported.
+    pub fn get_capabilities(&self) -> Capabilities {
+        let mut capabilities = platform_capabilities();
+        let clipboard = cfg!(feature = "clipboard");
+        let virtual_files = clipboard || cfg!(feature = "drag-drop");
+        capabilities.clipboard.multiple_items &= clipboard;
+        capabilities.clipboard.primary_selection &= clipboard;
+        capabilities.clipboard.events &= cfg!(feature = "clipboard-events");
+        capabilities.virtual_files.read &= virtual_files;
+        capabilities.virtual_files.copy &= virtual_files;
+        capabilities.virtual_files.provide &= virtual_files;
+        capabilities.hot_keys.supported &= cfg!(feature = "hot-key");
+        capabilities.menu.preview_image &= cfg!(feature = "menu");
+        capabilities.drag.preview_animations &= cfg!(feature = "drag-drop");
+        capabilities
+    }
+}
+
+pub trait GetCapabilitiesManager {
+    fn capabilities_manager(&self) -> Rc<CapabilitiesManager>;
+}
+
+impl GetCapabilitiesManager for Context {
+    fn capabilities_manager(&self) -> Rc<CapabilitiesManager> {
+        self.get_attachment(CapabilitiesManager::new).handler()
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncMethodHandler for CapabilitiesManager {
+    async fn on_method_call(&self, call: MethodCall) -> PlatformResult {
+        match call.method.as_str() {
+            "getCapabilities" => Ok(self.get_capabilities().into()),
+            _ => Err(PlatformError {
+                code: "invalid_method".into(),
+                message: Some(format!("Unknown Method: {}", call.method)),
+                detail: Value::Null,
+            }),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::Value;
+
+    use crate::platform::{run_test, value_at, MockIsolate};
+
+    #[test]
+    fn test_get_capabilities() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let capabilities = isolate
+                .call_method("CapabilitiesManager", "getCapabilities", Value::Null)
+                .await
+                .unwrap();
+            // Mock platform supports everything, only features that were
+            // compiled out are masked.
+            assert_eq!(
+                value_at(&capabilities, &["clipboard", "multipleItems"]),
+                &Value::Bool(cfg!(feature = "clipboard"))
+            );
+            assert_eq!(
+                value_at(&capabilities, &["clipboard", "events"]),
+                &Value::Bool(cfg!(feature = "clipboard-events"))
+            );
+            assert_eq!(
+                value_at(&capabilities, &["virtualFiles", "provide"]),
+                &Value::Bool(cfg!(any(feature = "clipboard", feature = "drag-drop")))
+            );
+            assert_eq!(
+                value_at(&capabilities, &["hotKeys", "supported"]),
+                &Value::Bool(cfg!(feature = "hot-key"))
+            );
+            // Not supported by mock platform regardless of features.
+            assert_eq!(
+                value_at(&capabilities, &["clipboard", "primarySelection"]),
+                &Value::Bool(false)
+            );
+        });
+    }
+}
@@ -12,7 +12,7 @@ use irondash_message_channel::{
 use log::warn;
 
 use crate::{
-    context::Context, error::NativeExtensionsResult, log::OkLog,
+    context::Context, error::NativeExtensionsResult, invoker::AsyncInvoker, log::OkLog,
     platform::PlatformClipboardEventManager,
 };
 
@@ -20,7 +20,7 @@ use crate::{
 pub type PlatformClipboardEventManagerId = IsolateId;
 
 pub struct ClipboardEventManager {
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
     platform_managers:
         RefCell<HashMap<PlatformClipboardEventManagerId, Rc<PlatformClipboardEventManager>>>,
     weak_self: Late<Weak<Self>>,
@@ -54,6 +54,10 @@ impl ClipboardEventManager {
         .register("ClipboardEventManager")
     }
 
+    pub fn get_platform_clipboard_event_managers(&self) -> Vec<Rc<PlatformClipboardEventManager>> {
+        self.platform_managers.borrow().values().cloned().collect()
+    }
+
     fn new_clipboard_events_manager(&self, isolate: IsolateId) -> NativeExtensionsResult<()> {
         if self.platform_managers.borrow().get(&isolate).is_some() {
             // Can happen during hot reload
@@ -108,7 +112,7 @@ impl ClipboardEventManagerDelegate for ClipboardEventManager {
 #[async_trait(?Send)]
 impl AsyncMethodHandler for ClipboardEventManager {
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     fn assign_weak_self(&self, weak_self: Weak<Self>) {
@@ -130,3 +134,41 @@ impl AsyncMethodHandler for ClipboardEventManager {
         }
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::Value;
+
+    use super::GetClipboardEventManager;
+    use crate::{
+        context::Context,
+        platform::{run_test, MockIsolate},
+    };
+
+    #[test]
+    fn test_clipboard_events() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            isolate
+                .call_method(
+                    "ClipboardEventManager",
+                    "newClipboardEventsManager",
+                    Value::Null,
+                )
+                .await
+                .unwrap();
+            let platform_manager = Context::get()
+                .clipboard_event_manager()
+                .get_platform_clipboard_event_managers()[0]
+                .clone();
+
+            isolate.on_call("copy", |_| Ok(Value::Bool(true)));
+            isolate.on_call("selectAll", |_| Ok(Value::Bool(false)));
+            assert!(platform_manager.simulate_copy().await);
+            assert!(!platform_manager.simulate_select_all().await);
+            assert_eq!(isolate.calls_to("copy").len(), 1);
+            assert_eq!(isolate.calls_to("selectAll").len(), 1);
+            assert!(isolate.calls_to("cut").is_empty());
+        });
+    }
+}
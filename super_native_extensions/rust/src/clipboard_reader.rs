@@ -2,15 +2,22 @@ use std::rc::Rc;
 
 use async_trait::async_trait;
 use irondash_message_channel::{
-    AsyncMethodHandler, MethodCall, PlatformError, PlatformResult, RegisteredAsyncMethodHandler,
-    Value,
+    AsyncMethodHandler, IntoValue, MethodCall, PlatformError, PlatformResult,
+    RegisteredAsyncMethodHandler, TryFromValue, Value,
 };
 
 use crate::{
-    context::Context, platform_impl::platform::PlatformDataReader,
+    context::Context, diagnostics::ResourceOrigin, platform_impl::platform::PlatformDataReader,
     reader_manager::GetDataReaderManager,
 };
 
+#[derive(Debug, TryFromValue, IntoValue)]
+#[irondash(rename_all = "camelCase")]
+pub(crate) struct NewClipboardReaderRequest {
+    /// Dart code location creating the reader, reported by leak diagnostics.
+    pub creation_site: Option<String>,
+}
+
 pub struct ClipboardReader {}
 
 impl ClipboardReader {
@@ -34,10 +41,12 @@ impl AsyncMethodHandler for ClipboardReader {
     async fn on_method_call(&self, call: MethodCall) -> PlatformResult {
         match call.method.as_str() {
             "newClipboardReader" => {
+                let request: NewClipboardReaderRequest = call.args.try_into()?;
                 let reader = PlatformDataReader::new_clipboard_reader()?;
+                let origin = ResourceOrigin::new(request.creation_site, "newClipboardReader");
                 Ok(Context::get()
                     .data_reader_manager()
-                    .register_platform_reader(reader, call.isolate)
+                    .register_platform_reader(reader, call.isolate, origin)
                     .into())
             }
             _ => Err(PlatformError {
@@ -11,13 +11,13 @@ use irondash_message_channel::{
 
 use crate::{
     api_model::DataProviderId, context::Context, data_provider_manager::GetDataProviderManager,
-    error::NativeExtensionsResult, log::OkLog, platform_impl::platform::PlatformDataProvider,
-    util::DropNotifier,
+    error::NativeExtensionsResult, invoker::AsyncInvoker, log::OkLog,
+    platform_impl::platform::PlatformDataProvider, util::DropNotifier,
 };
 
 pub struct ClipboardWriter {
     weak_self: Late<Weak<Self>>,
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
 }
 
 impl ClipboardWriter {
@@ -58,6 +58,12 @@ impl ClipboardWriter {
     }
 }
 
+impl Drop for ClipboardWriter {
+    fn drop(&mut self) {
+        PlatformDataProvider::release_clipboard();
+    }
+}
+
 pub trait GetClipboardWriter {
     fn clipboard_writer(&self) -> Rc<ClipboardWriter>;
 }
@@ -89,6 +95,6 @@ impl AsyncMethodHandler for ClipboardWriter {
     }
 
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 }
@@ -10,17 +10,17 @@ pub struct Context {
     outermost: bool,
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(any(not(target_os = "windows"), feature = "mock"))]
 struct Initializer {}
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(any(not(target_os = "windows"), feature = "mock"))]
 impl Initializer {
     fn new() -> Self {
         Self {}
     }
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", not(feature = "mock")))]
 type Initializer = crate::platform_impl::platform::OleInitializer;
 
 pub struct ContextInternal {
@@ -90,14 +90,25 @@ impl Context {
         // Which ever thread local is removed first will clean the attachment.
         let res = CURRENT_CONTEXT.try_with(|c| c.borrow().as_ref().map(|c| c.clone()));
         match res {
-            Ok(res) => res,
+            Ok(Some(res)) => Some(res),
             // (Can happen if attachment accesses context during destruction.)
-            // CURRENT_CONTEXT is being destroyed, use the fallback; Reverse situation
-            // can not happen because attachments will be removed by CURRENT_CONTEXT_FALLBACK
-            // destructor.
-            Err(_) => CURRENT_CONTEXT_FALLBACK.with(|c| c.borrow().as_ref().map(|c| c.clone())),
+            // CURRENT_CONTEXT is being destroyed or was taken by shutdown, use the
+            // fallback; Reverse situation can not happen because attachments will be
+            // removed by CURRENT_CONTEXT_FALLBACK destructor.
+            _ => CURRENT_CONTEXT_FALLBACK.with(|c| c.borrow().as_ref().map(|c| c.clone())),
         }
     }
+
+    /// Removes all attachments in reverse order in which they were inserted
+    /// and disassociates the context from current thread. Afterwards new
+    /// context can be created on this thread. Does nothing if there is no
+    /// context associated with current thread.
+    pub fn shutdown() {
+        // Keep fallback in place while attachments are being removed so that
+        // they can still access the context.
+        let context = CURRENT_CONTEXT.try_with(|c| c.take()).ok().flatten();
+        drop(context);
+    }
 }
 
 thread_local! {
@@ -105,28 +116,33 @@ thread_local! {
     static CURRENT_CONTEXT_FALLBACK: RefCell<Option<Context>> = const { RefCell::new(None) };
 }
 
+impl ContextInternal {
+    fn remove_attachments(&self) {
+        // Remove attachment in reverse order in which they were inserted
+        while !self.attachments.borrow().is_empty() {
+            let to_remove_index = self.attachments.borrow().len() - 1;
+            let to_remove = self
+                .attachments
+                .borrow()
+                .iter()
+                .find(|e| e.1 .1 == to_remove_index)
+                .map(|a| *a.0)
+                .expect("Attachment to remove not found");
+
+            // Hold removed item until RefMut gets dropped.
+            let _removed = { self.attachments.borrow_mut().remove(&to_remove) };
+
+            if to_remove_index == 0 {
+                break;
+            }
+        }
+    }
+}
+
 impl Drop for Context {
     fn drop(&mut self) {
         if self.outermost {
-            // Remove attachment in reverse order in which they were inserted
-            while !self.internal.attachments.borrow().is_empty() {
-                let to_remove_index = self.internal.attachments.borrow().len() - 1;
-                let to_remove = self
-                    .internal
-                    .attachments
-                    .borrow()
-                    .iter()
-                    .find(|e| e.1 .1 == to_remove_index)
-                    .map(|a| *a.0)
-                    .expect("Attachment to remove not found");
-
-                // Hold removed item until RefMut gets dropped.
-                let _removed = { self.internal.attachments.borrow_mut().remove(&to_remove) };
-
-                if to_remove_index == 0 {
-                    break;
-                }
-            }
+            self.internal.remove_attachments();
             CURRENT_CONTEXT.try_with(|c| c.take()).ok();
             CURRENT_CONTEXT_FALLBACK.try_with(|c| c.take()).ok();
         }
@@ -142,3 +158,37 @@ impl Context {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::Context;
+
+    struct Attachment<const N: usize>(Rc<RefCell<Vec<usize>>>);
+
+    impl<const N: usize> Drop for Attachment<N> {
+        fn drop(&mut self) {
+            assert!(Context::current().is_some());
+            self.0.borrow_mut().push(N);
+        }
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let context = Context::new();
+        context.get_attachment(|| Attachment::<1>(dropped.clone()));
+        context.get_attachment(|| Attachment::<2>(dropped.clone()));
+        context.get_attachment(|| Attachment::<3>(dropped.clone()));
+
+        Context::shutdown();
+        assert_eq!(*dropped.borrow(), vec![3, 2, 1]);
+        assert!(Context::current().is_none());
+
+        // Context can be created again after shutdown.
+        let _context = Context::new();
+        assert!(Context::current().is_some());
+        Context::shutdown();
+    }
+}
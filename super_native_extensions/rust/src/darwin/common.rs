@@ -18,7 +18,7 @@ use core_graphics::{
 use objc2::{ffi::NSInteger, rc::Id, runtime::AnyObject, ClassType};
 use objc2_foundation::{ns_string, NSDictionary, NSError, NSString, NSURLTypeIdentifierKey, NSURL};
 
-use crate::api_model::ImageData;
+use crate::api_model::{ImageData, PixelFormat};
 
 pub struct NSURLSecurtyScopeAccess {
     url: Id<NSURL>,
@@ -75,6 +75,7 @@ pub unsafe fn format_from_url(url: &NSURL) -> Option<String> {
 }
 
 pub fn cg_image_from_image_data(image: ImageData) -> CGImage {
+    let image = image.into_format(PixelFormat::Rgba);
     let data = CGDataProvider::from_buffer(Arc::new(image.data));
     let rgb = CGColorSpace::create_with_name(unsafe { kCGColorSpaceSRGB })
         .unwrap_or_else(CGColorSpace::create_device_rgb);
@@ -6,11 +6,14 @@ use std::{
     io::Write,
     path::PathBuf,
     rc::{Rc, Weak},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use block2::RcBlock;
-use irondash_message_channel::{IsolateId, Late};
+use irondash_message_channel::{IsolateId, Late, Value};
 use irondash_run_loop::{spawn, util::Capsule, RunLoop, RunLoopSender};
 use objc2::{
     extern_class, extern_methods, mutability::InteriorMutable, rc::Id, runtime::NSObject, ClassType,
@@ -44,6 +47,31 @@ struct PlatformDataProviderState {
     precached_values: HashMap<DataProviderValueId, ValuePromiseResult>,
 }
 
+static PRECACHED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+fn precached_size(value: &ValuePromiseResult) -> usize {
+    match value {
+        ValuePromiseResult::Ok {
+            value: Value::U8List(data),
+        } => data.len(),
+        ValuePromiseResult::Ok {
+            value: Value::String(string),
+        } => string.len(),
+        _ => 0,
+    }
+}
+
+impl Drop for PlatformDataProviderState {
+    fn drop(&mut self) {
+        let size: usize = self.precached_values.values().map(precached_size).sum();
+        PRECACHED_BYTES.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+pub fn platform_provider_cache_bytes() -> Option<usize> {
+    Some(PRECACHED_BYTES.load(Ordering::Relaxed))
+}
+
 pub struct PlatformDataProvider {
     weak_self: Late<Weak<Self>>,
     delegate: Weak<dyn PlatformDataProviderDelegate>,
@@ -168,6 +196,10 @@ impl PlatformDataProvider {
         }
     }
 
+    /// Lazy values are precached before writing, so item providers on the
+    /// pasteboard don't need the plugin after shutdown.
+    pub fn release_clipboard() {}
+
     pub async fn write_to_clipboard(
         providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
     ) -> NativeExtensionsResult<()> {
@@ -211,7 +243,10 @@ impl PlatformDataProvider {
             for item in to_fetch {
                 let res = delegate.get_lazy_data_async(self.isolate_id, item).await;
                 let mut state = self.state.lock().unwrap();
-                state.precached_values.insert(item, res);
+                PRECACHED_BYTES.fetch_add(precached_size(&res), Ordering::Relaxed);
+                if let Some(previous) = state.precached_values.insert(item, res) {
+                    PRECACHED_BYTES.fetch_sub(precached_size(&previous), Ordering::Relaxed);
+                }
             }
         }
     }
@@ -288,6 +323,7 @@ impl DataProviderSession {
     fn fetch_value(
         &self,
         id: DataProviderValueId,
+        format: String,
         callback: Box<dyn Fn(Option<&NSData>, Option<&NSError>) + Send>,
     ) -> Option<Id<NSProgress>> {
         Self::on_platform_thread(self, move |s| match s {
@@ -303,7 +339,7 @@ impl DataProviderSession {
                     let data = source_delegate
                         .get_lazy_data_async(source.isolate_id, id)
                         .await;
-                    let data = value_promise_res_to_nsdata(&data);
+                    let data = value_promise_res_to_nsdata(&data, &format);
                     callback(data.as_deref(), None);
                 });
             }
@@ -332,7 +368,7 @@ impl DataProviderSession {
             match representation {
                 DataRepresentation::Simple { format, data } => {
                     if format == requested_format {
-                        let data = value_to_nsdata(data);
+                        let data = value_to_nsdata(data, format);
                         callback(data.as_deref(), None);
                         return None;
                     }
@@ -342,11 +378,11 @@ impl DataProviderSession {
                         let precached = state.precached_values.get(id);
                         match precached {
                             Some(value) => {
-                                let data = value_promise_res_to_nsdata(value);
+                                let data = value_promise_res_to_nsdata(value, format);
                                 callback(data.as_deref(), None);
                                 return None;
                             }
-                            None => return self.fetch_value(*id, callback),
+                            None => return self.fetch_value(*id, format.clone(), callback),
                         }
                     }
                 }
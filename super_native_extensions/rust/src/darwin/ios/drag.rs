@@ -294,7 +294,11 @@ impl Session {
                     item.lift_image.as_ref().unwrap_or(&item.image)
                 };
 
-                let image_view = image_view_from_data(drag_image.image_data.clone(), self.mtm);
+                let image_data = configuration
+                    .preview_size_policy
+                    .unwrap_or_default()
+                    .apply(drag_image.image_data.clone());
+                let image_view = image_view_from_data(image_data, self.mtm);
 
                 let frame: CGRect = drag_image
                     .rect
@@ -354,6 +358,7 @@ impl Session {
         }
     }
 
+    #[cfg(feature = "menu")]
     fn menu_active(&self) -> bool {
         if let Some(menu_contexts) = self
             .context_delegate
@@ -365,6 +370,11 @@ impl Session {
         false
     }
 
+    #[cfg(not(feature = "menu"))]
+    fn menu_active(&self) -> bool {
+        false
+    }
+
     fn preview_for_item(&self, index: usize) -> Id<UITargetedDragPreview> {
         // while menu is active (and we're not dragging yet) create items
         // immediately with drag image instead of lift. This will alleviate
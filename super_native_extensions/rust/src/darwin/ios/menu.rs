@@ -305,6 +305,11 @@ impl MenuSession {
     }
 
     fn update_preview_image(&self, image: ImageData) {
+        let image = self
+            .configuration
+            .preview_size_policy
+            .unwrap_or_default()
+            .apply(image);
         let preview_view = image_view_from_data(image, self.mtm);
         unsafe {
             let view = self.view_controller.view().unwrap();
@@ -437,7 +442,13 @@ impl PlatformMenuContext {
                 menu_configuration.preview_size.as_ref(),
             ) {
                 (Some(preview_image), None) => {
-                    let preview_view = image_view_from_data(preview_image.clone(), self.mtm);
+                    let preview_view = image_view_from_data(
+                        menu_configuration
+                            .preview_size_policy
+                            .unwrap_or_default()
+                            .apply(preview_image.clone()),
+                        self.mtm,
+                    );
                     let size = CGSize {
                         width: preview_image.point_width(),
                         height: preview_image.point_height(),
@@ -567,7 +578,14 @@ impl PlatformMenuContext {
         match session {
             Some(session) => unsafe {
                 let image = &session.configuration.lift_image;
-                let lift_image = image_view_from_data(image.image_data.clone(), self.mtm);
+                let lift_image = image_view_from_data(
+                    session
+                        .configuration
+                        .preview_size_policy
+                        .unwrap_or_default()
+                        .apply(image.image_data.clone()),
+                    self.mtm,
+                );
                 let frame: CGRect = image.rect.translated(-100000.0, -100000.0).into();
                 lift_image.setFrame(frame);
                 session.view_container.addSubview(&lift_image);
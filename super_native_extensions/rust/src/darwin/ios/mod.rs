@@ -1,21 +1,36 @@
 mod alpha_to_path;
+mod capabilities;
+#[cfg(feature = "clipboard-events")]
 mod clipboard_events;
 mod data_provider;
+#[cfg(feature = "drag-drop")]
 mod drag;
+#[cfg(feature = "drag-drop")]
 mod drag_common;
+#[cfg(feature = "drag-drop")]
 mod drop;
+#[cfg(feature = "hot-key")]
 mod hot_key;
+#[cfg(feature = "keyboard-layout")]
 mod keyboard_layout;
+#[cfg(feature = "menu")]
 mod menu;
 mod objc_drop_notifier;
 mod reader;
 mod util;
 
+pub use capabilities::*;
+#[cfg(feature = "clipboard-events")]
 pub use clipboard_events::*;
 pub use data_provider::*;
+#[cfg(feature = "drag-drop")]
 pub use drag::*;
+#[cfg(feature = "drag-drop")]
 pub use drop::*;
+#[cfg(feature = "hot-key")]
 pub use hot_key::*;
+#[cfg(feature = "keyboard-layout")]
 pub use keyboard_layout::*;
+#[cfg(feature = "menu")]
 pub use menu::*;
 pub use reader::*;
@@ -30,7 +30,9 @@ use objc2::{
 use objc2_ui_kit::{UIDragItem, UIPasteboard};
 
 use crate::{
+    api_model::ClipboardIntent,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::{FormatConverter, HtmlToText, RtfToText},
     log::OkLog,
     platform_impl::platform::{
         common::{path_from_url, uti_conforms_to, NSURLSecurtyScopeAccess},
@@ -41,6 +43,14 @@ use crate::{
     value_promise::Promise,
 };
 
+/// Converters used to synthesize formats not provided by pasteboard items.
+pub fn platform_format_converters() -> Vec<Rc<dyn FormatConverter>> {
+    vec![
+        Rc::new(HtmlToText::new(&["public.html"], "public.utf8-plain-text")),
+        Rc::new(RtfToText::new(&["public.rtf"], "public.utf8-plain-text")),
+    ]
+}
+
 pub struct PlatformDataReader {
     source: ReaderSource,
 }
@@ -220,6 +230,18 @@ impl PlatformDataReader {
         Ok(formats.iter().any(|f| f == format))
     }
 
+    pub async fn get_clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        Ok(None)
+    }
+
+    /// Not provided; Items on iOS carry no local file paths.
+    pub async fn get_local_path_for_item(
+        &self,
+        _item: i64,
+    ) -> NativeExtensionsResult<Option<PathBuf>> {
+        Ok(None)
+    }
+
     pub async fn get_item_format_for_uri(
         &self,
         _item: i64,
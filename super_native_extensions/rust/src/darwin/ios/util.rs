@@ -14,11 +14,13 @@ use objc2_foundation::{
 };
 use objc2_ui_kit::{CGAffineTransform, UIApplication, UIImage, UIImageOrientation, UIImageView};
 
+#[cfg(feature = "drag-drop")]
+use crate::drag_manager::DragSessionId;
 use crate::{
     api_model::{ImageData, Point, Rect, Size},
-    drag_manager::DragSessionId,
     platform_impl::platform::common::cg_image_from_image_data,
     util::Movable,
+    value_cbor::value_for_format,
     value_coerce::{CoerceToData, StringFormat},
     value_promise::ValuePromiseResult,
 };
@@ -83,10 +85,12 @@ impl From<Size> for CGSize {
     }
 }
 
-pub fn value_to_nsdata(value: &Value) -> Option<Id<NSData>> {
+pub fn value_to_nsdata(value: &Value, format: &str) -> Option<Id<NSData>> {
     fn is_map_or_list(value: &Value) -> bool {
         matches!(value, Value::Map(_) | Value::List(_))
     }
+    let value = value_for_format(value, format);
+    let value = value.as_ref();
     if is_map_or_list(value) {
         let objc = value.to_objc();
         if let Ok(Some(objc)) = objc {
@@ -107,9 +111,9 @@ pub fn value_to_nsdata(value: &Value) -> Option<Id<NSData>> {
     buf.map(NSData::from_vec)
 }
 
-pub fn value_promise_res_to_nsdata(value: &ValuePromiseResult) -> Option<Id<NSData>> {
+pub fn value_promise_res_to_nsdata(value: &ValuePromiseResult, format: &str) -> Option<Id<NSData>> {
     match value {
-        ValuePromiseResult::Ok { value } => value_to_nsdata(value),
+        ValuePromiseResult::Ok { value } => value_to_nsdata(value, format),
         ValuePromiseResult::Cancelled => None,
     }
 }
@@ -216,6 +220,7 @@ impl IntoObjc for i64 {
     }
 }
 
+#[cfg(feature = "drag-drop")]
 impl IntoObjc for DragSessionId {
     fn into_objc(self) -> Id<NSObject> {
         let id: i64 = self.into();
@@ -0,0 +1,26 @@
+use crate::capabilities::{
+    Capabilities, ClipboardCapabilities, DragCapabilities, HotKeyCapabilities, MenuCapabilities,
+    VirtualFileCapabilities,
+};
+
+pub fn platform_capabilities() -> Capabilities {
+    Capabilities {
+        clipboard: ClipboardCapabilities {
+            multiple_items: true,
+            primary_selection: false,
+            events: false,
+        },
+        virtual_files: VirtualFileCapabilities {
+            read: false,
+            copy: true,
+            provide: true,
+        },
+        hot_keys: HotKeyCapabilities { supported: true },
+        menu: MenuCapabilities {
+            preview_image: false,
+        },
+        drag: DragCapabilities {
+            preview_animations: true,
+        },
+    }
+}
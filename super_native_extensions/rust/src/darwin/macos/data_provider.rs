@@ -35,6 +35,7 @@ use crate::{
     error::NativeExtensionsResult,
     log::OkLog,
     platform_impl::platform::common::{path_from_url, to_nserror},
+    value_cbor::value_for_format,
     value_promise::ValuePromiseResult,
 };
 
@@ -58,6 +59,12 @@ pub fn platform_stream_close(handle: i32, delete: bool) {
     }
 }
 
+/// Lazy values are handed to the pasteboard once resolved and not retained
+/// by the provider.
+pub fn platform_provider_cache_bytes() -> Option<usize> {
+    None
+}
+
 pub struct PlatformDataProvider {
     weak_self: Late<Weak<Self>>,
     delegate: Weak<dyn PlatformDataProviderDelegate>,
@@ -117,6 +124,10 @@ impl PlatformDataProvider {
         state.create_item()
     }
 
+    /// Pasteboard items retain their providers until the pasteboard contents
+    /// are replaced, so there is nothing to release here.
+    pub fn release_clipboard() {}
+
     pub async fn write_to_clipboard(
         providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
     ) -> NativeExtensionsResult<()> {
@@ -224,7 +235,7 @@ impl ItemState {
                     match repr {
                         DataRepresentation::Simple { format, data } => {
                             if &ty == format {
-                                return data.to_objc().ok_log().flatten();
+                                return value_for_format(data, format).to_objc().ok_log().flatten();
                             }
                         }
                         DataRepresentation::Lazy { format, id } => {
@@ -237,7 +248,10 @@ impl ItemState {
                                         if let Some(result) = promise.try_take() {
                                             match result {
                                                 ValuePromiseResult::Ok { value } => {
-                                                    return value.to_objc().ok_log().flatten()
+                                                    return value_for_format(&value, format)
+                                                        .to_objc()
+                                                        .ok_log()
+                                                        .flatten()
                                                 }
                                                 ValuePromiseResult::Cancelled => {
                                                     return None;
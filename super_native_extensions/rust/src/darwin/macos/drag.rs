@@ -191,7 +191,12 @@ impl PlatformDragContext {
             let image = &item.image;
             let mut rect: NSRect = image.rect.clone().into();
             flip_rect(&self.view, &mut rect);
-            let snapshot = ns_image_from_image_data(vec![image.image_data.clone()]);
+            let image_data = request
+                .configuration
+                .preview_size_policy
+                .unwrap_or_default()
+                .apply(image.image_data.clone());
+            let snapshot = ns_image_from_image_data(vec![image_data]);
 
             unsafe { dragging_item.setDraggingFrame_contents(rect, Some(&snapshot)) };
             dragging_items.push(dragging_item);
@@ -160,6 +160,9 @@ impl PlatformHotKeyManager {
 
 impl Drop for PlatformHotKeyManager {
     fn drop(&mut self) {
+        for (_, hot_key) in self.hot_keys.get_mut().drain() {
+            unsafe { UnregisterEventHotKey(hot_key.key_ref) };
+        }
         if !self.event_handler_ref.get().is_null() {
             unsafe { RemoveEventHandler(self.event_handler_ref.get()) };
         }
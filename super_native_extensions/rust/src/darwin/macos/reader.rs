@@ -9,12 +9,14 @@ use std::{
     thread,
 };
 
+use async_trait::async_trait;
 use block2::RcBlock;
 use irondash_message_channel::{value_darwin::ValueObjcConversion, Value};
 use irondash_run_loop::{
     util::{Capsule, FutureCompleter},
     RunLoop,
 };
+use log::Level;
 use objc2::{
     msg_send_id,
     rc::{autoreleasepool, Id},
@@ -30,7 +32,9 @@ use objc2_foundation::{
 };
 
 use crate::{
+    api_model::ClipboardIntent,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::{FormatConverter, HtmlToText, RtfToText},
     log::OkLog,
     platform_impl::platform::common::{format_from_url, path_from_url, uti_conforms_to},
     reader_manager::{ReadProgress, VirtualFileReader},
@@ -44,6 +48,71 @@ struct ValueCacheKey {
     format: String,
 }
 
+/// Converters used to synthesize formats not provided by pasteboard items.
+pub fn platform_format_converters() -> Vec<Rc<dyn FormatConverter>> {
+    vec![
+        Rc::new(HtmlToText::new(&["public.html"], "public.utf8-plain-text")),
+        Rc::new(RtfToText::new(&["public.rtf"], "public.utf8-plain-text")),
+        Rc::new(TiffToPng::new()),
+    ]
+}
+
+/// Synthesizes PNG from TIFF, which is what most macOS applications put on
+/// pasteboard for images.
+struct TiffToPng {
+    source_formats: Vec<String>,
+}
+
+impl TiffToPng {
+    fn new() -> Self {
+        Self {
+            source_formats: vec!["public.tiff".into()],
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl FormatConverter for TiffToPng {
+    fn target_format(&self) -> &str {
+        "public.png"
+    }
+
+    fn source_formats(&self) -> &[String] {
+        &self.source_formats
+    }
+
+    async fn convert(&self, _source_format: &str, data: Value) -> NativeExtensionsResult<Value> {
+        let Value::U8List(data) = data else {
+            return Err(NativeExtensionsError::InvalidData);
+        };
+        let (future, completer) = FutureCompleter::new();
+        let mut completer = Capsule::new(completer);
+        let sender = RunLoop::current().new_sender();
+        thread::spawn(move || {
+            autoreleasepool(|_| unsafe {
+                let data = NSData::from_vec(data);
+                let res = match NSBitmapImageRep::imageRepWithData(&data) {
+                    Some(rep) => {
+                        let png = rep.representationUsingType_properties(
+                            NSBitmapImageFileType::PNG,
+                            &NSDictionary::dictionary(),
+                        );
+                        Ok(Value::from_objc(png.map(|png| Id::cast(png)))
+                            .ok_log()
+                            .unwrap_or_default())
+                    }
+                    None => Err(NativeExtensionsError::InvalidData),
+                };
+                sender.send(move || {
+                    let completer = completer.take().unwrap();
+                    completer.complete(res);
+                });
+            });
+        });
+        future.await
+    }
+}
+
 pub struct PlatformDataReader {
     pasteboard: Id<NSPasteboard>,
     pasteboard_items: RefCell<Option<Id<NSArray<NSPasteboardItem>>>>,
@@ -73,6 +142,18 @@ impl PlatformDataReader {
         self.get_items_sync()
     }
 
+    pub async fn get_clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        Ok(None)
+    }
+
+    /// Not provided; File URLs are read as `public.file-url` instead.
+    pub async fn get_local_path_for_item(
+        &self,
+        _item: i64,
+    ) -> NativeExtensionsResult<Option<PathBuf>> {
+        Ok(None)
+    }
+
     pub async fn get_item_format_for_uri(
         &self,
         item: i64,
@@ -153,11 +234,7 @@ impl PlatformDataReader {
             let types = unsafe { pasteboard_item.types() };
             for format in types {
                 let format = format.to_string();
-                push(&mut res, format.clone());
-                // Put synthesized PNG right after tiff
-                if format == "public.tiff" && self.needs_to_synthesize_png(item) {
-                    res.push("public.png".to_string());
-                }
+                push(&mut res, format);
             }
 
             Ok(res)
@@ -166,30 +243,12 @@ impl PlatformDataReader {
         }
     }
 
-    fn needs_to_synthesize_png(&self, item: i64) -> bool {
-        let Ok(items) = self.get_pasteboard_items() else {
-            return false;
-        };
-        let mut has_tiff = false;
-        let mut has_png = false;
-        if item < items.count() as i64 {
-            let item = unsafe { items.objectAtIndex(item as usize) };
-            let types = unsafe { item.types() };
-            for format in types {
-                let format = format.to_string();
-                has_tiff |= format == "public.tiff";
-                has_png |= format == "public.png";
-            }
-        }
-        has_tiff && !has_png
-    }
-
     pub fn item_format_is_synthesized(
         &self,
-        item: i64,
-        format: &str,
+        _item: i64,
+        _format: &str,
     ) -> NativeExtensionsResult<bool> {
-        Ok(format == "public.png" && self.needs_to_synthesize_png(item))
+        Ok(false)
     }
 
     fn item_has_virtual_file(&self, item: i64) -> bool {
@@ -288,48 +347,13 @@ impl PlatformDataReader {
         Ok(None)
     }
 
-    pub async fn convert_to_png(&self, data: Vec<u8>) -> NativeExtensionsResult<Value> {
-        let (future, completer) = FutureCompleter::new();
-        let mut completer = Capsule::new(completer);
-        let sender = RunLoop::current().new_sender();
-        thread::spawn(move || {
-            autoreleasepool(|_| unsafe {
-                let data = NSData::from_vec(data);
-                let rep = NSBitmapImageRep::imageRepWithData(&data).unwrap();
-                let png = rep.representationUsingType_properties(
-                    NSBitmapImageFileType::PNG,
-                    &NSDictionary::dictionary(),
-                );
-
-                let res = Value::from_objc(png.map(|png| Id::cast(png)))
-                    .ok_log()
-                    .unwrap_or_default();
-                sender.send(move || {
-                    let completer = completer.take().unwrap();
-                    completer.complete(Ok(res));
-                });
-            });
-        });
-        future.await
-    }
-
     pub async fn get_data_for_item(
         &self,
         item: i64,
         data_type: String,
         _progress: Option<Arc<ReadProgress>>,
     ) -> NativeExtensionsResult<Value> {
-        if data_type == "public.png" && self.needs_to_synthesize_png(item) {
-            let tiff = self
-                .do_get_data_for_item(item, "public.tiff".to_owned())
-                .await?;
-            match tiff {
-                Value::U8List(data) => self.convert_to_png(data).await,
-                other => Ok(other),
-            }
-        } else {
-            self.do_get_data_for_item(item, data_type).await
-        }
+        self.do_get_data_for_item(item, data_type).await
     }
 
     fn schedule_do_get_data_for_item(
@@ -483,7 +507,8 @@ impl PlatformDataReader {
                             .expect("Callback invoked more than once");
                         if let Some(error) = error {
                             if let Some(url) = url {
-                                fs::remove_file(path_from_url(&url)).ok_log();
+                                // Partially received file may not exist.
+                                fs::remove_file(path_from_url(&url)).ok_log_at(Level::Debug);
                             }
 
                             completer.complete(Err(NativeExtensionsError::VirtualFileReceiveError(
@@ -9,7 +9,7 @@ use objc2::{
 };
 
 use crate::{
-    api_model::{ImageData, Point, Rect, Size},
+    api_model::{ImageData, PixelFormat, Point, Rect, Size},
     platform_impl::platform::common::cg_image_from_image_data,
 };
 
@@ -115,6 +115,7 @@ fn is_grayscale(image: &ImageData) -> bool {
 }
 
 pub fn ns_image_for_menu_item(image: ImageData) -> Id<NSImage> {
+    let image = image.into_format(PixelFormat::Rgba);
     let is_grayscale = is_grayscale(&image);
     let size = NSSize::new(image.point_width(), image.point_height());
     let image = ns_image_from_image_data(vec![image]);
@@ -17,7 +17,9 @@ use irondash_run_loop::spawn;
 use crate::{
     api_model::{DataProvider, DataProviderId, DataProviderValueId},
     context::Context,
+    diagnostics::{ResourceKind, ResourceOrigin, ResourceVisitor},
     error::{NativeExtensionsError, NativeExtensionsResult},
+    invoker::AsyncInvoker,
     log::OkLog,
     platform_impl::platform::{platform_stream_close, platform_stream_write, PlatformDataProvider},
     util::{DropNotifier, NextId},
@@ -40,6 +42,12 @@ impl VirtualSessionHandle {
     }
 }
 
+impl From<DropNotifier> for VirtualSessionHandle {
+    fn from(notifier: DropNotifier) -> Self {
+        VirtualSessionHandle(notifier)
+    }
+}
+
 /// Keeps the data provider alive
 #[allow(unused)] // DropNotifier is not read but needs to be retained.
 pub struct DataProviderHandle(DropNotifier);
@@ -78,7 +86,7 @@ pub trait PlatformDataProviderDelegate {
 
 pub struct DataProviderManager {
     weak_self: Late<Weak<Self>>,
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
     next_id: Cell<i64>,
     providers: RefCell<HashMap<DataProviderId, DataProviderEntry>>,
     virtual_sessions: RefCell<HashMap<VirtualSessionId, VirtualFileSession>>,
@@ -96,6 +104,7 @@ impl GetDataProviderManager for Context {
 
 struct DataProviderEntry {
     isolate_id: IsolateId,
+    origin: ResourceOrigin,
     platform_data_provider: Rc<PlatformDataProvider>,
 }
 
@@ -110,6 +119,7 @@ impl From<i64> for VirtualSessionId {
 
 struct VirtualFileSession {
     isolate_id: IsolateId,
+    origin: ResourceOrigin,
     size_known: Cell<bool>,
     on_size_known: Box<dyn Fn(Option<i64>)>,
     on_progress: Box<dyn Fn(f64 /* 0.0 - 1.0 */)>,
@@ -141,13 +151,13 @@ impl DataProviderManager {
 
     fn register_provider(
         &self,
-        source: DataProvider,
+        request: RegisterDataProviderRequest,
         isolate_id: IsolateId,
     ) -> NativeExtensionsResult<DataProviderId> {
         let platform_data_source = Rc::new(PlatformDataProvider::new(
             self.weak_self.clone(),
             isolate_id,
-            source,
+            request.provider,
         ));
         let id = self.next_id.next_id().into();
         platform_data_source.assign_weak_self(Rc::downgrade(&platform_data_source));
@@ -155,6 +165,7 @@ impl DataProviderManager {
             id,
             DataProviderEntry {
                 isolate_id,
+                origin: ResourceOrigin::new(request.creation_site, "registerDataProvider"),
                 platform_data_provider: platform_data_source,
             },
         );
@@ -231,6 +242,30 @@ impl DataProviderManager {
         (session.on_done)(VirtualFileResult::Cancelled);
         Ok(())
     }
+
+    pub fn visit_resources(&self, visitor: &mut dyn ResourceVisitor) {
+        for entry in self.providers.borrow().values() {
+            visitor.visit(entry.isolate_id, ResourceKind::Provider, &entry.origin);
+        }
+        for session in self.virtual_sessions.borrow().values() {
+            visitor.visit(
+                session.isolate_id,
+                ResourceKind::VirtualSession,
+                &session.origin,
+            );
+        }
+    }
+}
+
+impl Drop for DataProviderManager {
+    fn drop(&mut self) {
+        for (_, session) in self.virtual_sessions.take() {
+            if !session.size_known.get() {
+                (session.on_size_known)(None);
+            }
+            (session.on_done)(VirtualFileResult::Cancelled);
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -293,6 +328,10 @@ impl PlatformDataProviderDelegate for DataProviderManager {
         let session_id: VirtualSessionId = self.next_id.next_id().into();
         let sesion = VirtualFileSession {
             isolate_id,
+            // Session is requested by the platform (drop target or clipboard
+            // consumer); the Dart side only learns about it through
+            // "getVirtualFile", so only the tag is recorded.
+            origin: ResourceOrigin::new(None, "getVirtualFile"),
             size_known: Cell::new(false),
             on_size_known,
             on_progress,
@@ -331,6 +370,15 @@ impl PlatformDataProviderDelegate for DataProviderManager {
     }
 }
 
+#[derive(Debug, TryFromValue, IntoValue)]
+#[irondash(rename_all = "camelCase")]
+pub(crate) struct RegisterDataProviderRequest {
+    pub provider: DataProvider,
+    /// Dart code location registering the provider, reported by leak
+    /// diagnostics.
+    pub creation_site: Option<String>,
+}
+
 #[derive(Debug, TryFromValue)]
 #[irondash(rename_all = "camelCase")]
 struct VirtualFileUpdateProgress {
@@ -402,7 +450,7 @@ impl AsyncMethodHandler for DataProviderManager {
     }
 
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     // Called when engine is about to be destroyed.
@@ -458,3 +506,151 @@ pub extern "C" fn super_native_extensions_stream_write(
 pub extern "C" fn super_native_extensions_stream_close(handle: i32, delete: bool) {
     platform_stream_close(handle, delete);
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    use irondash_message_channel::{IntoValue, Value};
+
+    use super::{
+        GetDataProviderManager, PlatformDataProviderDelegate, VirtualFileResult,
+        VirtualSessionHandle,
+    };
+    use crate::{
+        context::Context,
+        platform::{run_test, value_at, MockIsolate},
+    };
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct SizeKnownRequest {
+        session_id: Value,
+        file_size: i64,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ProgressRequest {
+        session_id: Value,
+        progress: f64,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct CompleteRequest {
+        session_id: Value,
+    }
+
+    /// Callbacks of virtual file session in order of invocation.
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        SizeKnown(Option<i64>),
+        Progress(f64),
+        Done,
+        Error(String),
+        Cancelled,
+    }
+
+    fn get_virtual_file(
+        isolate: &MockIsolate,
+        events: &Rc<RefCell<Vec<Event>>>,
+    ) -> Arc<VirtualSessionHandle> {
+        let (size_events, progress_events, done_events) =
+            (events.clone(), events.clone(), events.clone());
+        Context::get().data_provider_manager().get_virtual_file(
+            isolate.id(),
+            3.into(),
+            10,
+            Box::new(move |size| size_events.borrow_mut().push(Event::SizeKnown(size))),
+            Box::new(move |progress| progress_events.borrow_mut().push(Event::Progress(progress))),
+            Box::new(move |result| {
+                done_events.borrow_mut().push(match result {
+                    VirtualFileResult::Done => Event::Done,
+                    VirtualFileResult::Error { message } => Event::Error(message),
+                    VirtualFileResult::Cancelled => Event::Cancelled,
+                })
+            }),
+        )
+    }
+
+    #[test]
+    fn test_get_virtual_file() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let events = Rc::new(RefCell::new(Vec::new()));
+            let _session = get_virtual_file(&isolate, &events);
+
+            let requests = isolate.calls_to("getVirtualFile");
+            assert_eq!(requests.len(), 1);
+            assert_eq!(value_at(&requests[0], &["virtualFileId"]), &Value::I64(3));
+            assert_eq!(value_at(&requests[0], &["streamHandle"]), &Value::I64(10));
+            let session_id = value_at(&requests[0], &["sessionId"]).clone();
+
+            isolate
+                .call_method(
+                    "DataProviderManager",
+                    "virtualFileSizeKnown",
+                    SizeKnownRequest {
+                        session_id: session_id.clone(),
+                        file_size: 4,
+                    },
+                )
+                .await
+                .unwrap();
+            isolate
+                .call_method(
+                    "DataProviderManager",
+                    "virtualFileUpdateProgress",
+                    ProgressRequest {
+                        session_id: session_id.clone(),
+                        progress: 0.5,
+                    },
+                )
+                .await
+                .unwrap();
+            let complete = || CompleteRequest {
+                session_id: session_id.clone(),
+            };
+            isolate
+                .call_method("DataProviderManager", "virtualFileComplete", complete())
+                .await
+                .unwrap();
+            assert_eq!(
+                *events.borrow(),
+                vec![Event::SizeKnown(Some(4)), Event::Progress(0.5), Event::Done]
+            );
+
+            // Completed session is forgotten.
+            assert!(isolate
+                .call_method("DataProviderManager", "virtualFileComplete", complete())
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_cancel_virtual_file() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let events = Rc::new(RefCell::new(Vec::new()));
+
+            // Releasing the session handle asks Dart to stop producing the
+            // file.
+            let session = get_virtual_file(&isolate, &events);
+            drop(session);
+            let requests = isolate.calls_to("getVirtualFile");
+            assert_eq!(
+                isolate.calls_to("cancelVirtualFile"),
+                vec![value_at(&requests[0], &["sessionId"]).clone()]
+            );
+
+            // Pending sessions of destroyed isolate are cancelled.
+            drop(isolate);
+            assert_eq!(
+                *events.borrow(),
+                vec![Event::SizeKnown(None), Event::Cancelled]
+            );
+        });
+    }
+}
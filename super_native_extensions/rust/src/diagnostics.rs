@@ -0,0 +1,510 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::{Rc, Weak},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use irondash_message_channel::{
+    AsyncMethodHandler, AsyncMethodInvoker, IntoValue, IsolateId, Late, MethodCall, PlatformError,
+    PlatformResult, RegisteredAsyncMethodHandler, TryFromValue, Value,
+};
+use irondash_run_loop::RunLoop;
+use log::warn;
+
+#[cfg(feature = "hot-key")]
+use crate::hot_key_manager::GetHotKeyManager;
+#[cfg(feature = "menu")]
+use crate::menu_manager::GetMenuManager;
+use crate::{
+    context::Context, data_provider_manager::GetDataProviderManager, invoker::AsyncInvoker,
+    log::OkLog, platform_impl::platform::platform_provider_cache_bytes,
+    reader_manager::GetDataReaderManager, segmented_queue,
+};
+
+/// Records when and where a resource tracked by one of the managers was
+/// created.
+#[derive(Debug)]
+pub struct ResourceOrigin {
+    created: Instant,
+    creation_site: String,
+    reported: Cell<bool>,
+}
+
+impl ResourceOrigin {
+    /// `creation_site` is the Dart code location that requested the resource,
+    /// if Dart sent one (debug builds only). Otherwise `tag` describing how
+    /// the resource was created is reported.
+    pub fn new(creation_site: Option<String>, tag: &str) -> Self {
+        Self {
+            created: Instant::now(),
+            creation_site: creation_site.unwrap_or_else(|| tag.into()),
+            reported: Cell::new(false),
+        }
+    }
+
+    pub fn age(&self) -> Duration {
+        self.created.elapsed()
+    }
+
+    pub fn creation_site(&self) -> &str {
+        &self.creation_site
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Reader,
+    ReadProgress,
+    VirtualFileReader,
+    Provider,
+    VirtualSession,
+    Menu,
+    HotKey,
+}
+
+impl ResourceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResourceKind::Reader => "reader",
+            ResourceKind::ReadProgress => "readProgress",
+            ResourceKind::VirtualFileReader => "virtualFileReader",
+            ResourceKind::Provider => "provider",
+            ResourceKind::VirtualSession => "virtualSession",
+            ResourceKind::Menu => "menu",
+            ResourceKind::HotKey => "hotKey",
+        }
+    }
+}
+
+/// Implemented by consumers of resources reported by managers.
+pub trait ResourceVisitor {
+    fn visit(&mut self, isolate_id: IsolateId, kind: ResourceKind, origin: &ResourceOrigin);
+}
+
+#[derive(IntoValue, Clone, Debug, Default)]
+#[irondash(rename_all = "camelCase")]
+pub struct ResourceStats {
+    pub count: i64,
+    /// Age of each resource in milliseconds, oldest first.
+    pub ages_millis: Vec<i64>,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct IsolateDiagnostics {
+    pub isolate_id: i64,
+    pub readers: ResourceStats,
+    pub read_progresses: ResourceStats,
+    pub virtual_file_readers: ResourceStats,
+    pub providers: ResourceStats,
+    pub virtual_sessions: ResourceStats,
+    pub menus: ResourceStats,
+    pub hot_keys: ResourceStats,
+}
+
+impl IsolateDiagnostics {
+    fn new(isolate_id: IsolateId) -> Self {
+        Self {
+            isolate_id: isolate_id.0,
+            readers: Default::default(),
+            read_progresses: Default::default(),
+            virtual_file_readers: Default::default(),
+            providers: Default::default(),
+            virtual_sessions: Default::default(),
+            menus: Default::default(),
+            hot_keys: Default::default(),
+        }
+    }
+
+    fn stats_mut(&mut self, kind: ResourceKind) -> &mut ResourceStats {
+        match kind {
+            ResourceKind::Reader => &mut self.readers,
+            ResourceKind::ReadProgress => &mut self.read_progresses,
+            ResourceKind::VirtualFileReader => &mut self.virtual_file_readers,
+            ResourceKind::Provider => &mut self.providers,
+            ResourceKind::VirtualSession => &mut self.virtual_sessions,
+            ResourceKind::Menu => &mut self.menus,
+            ResourceKind::HotKey => &mut self.hot_keys,
+        }
+    }
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct Diagnostics {
+    pub isolates: Vec<IsolateDiagnostics>,
+    /// Bytes held in memory segments of segmented queues.
+    pub segmented_queue_memory_bytes: i64,
+    /// Bytes written to (not yet deleted) file segments of segmented queues.
+    pub segmented_queue_file_bytes: i64,
+    /// Bytes of lazy data cached by platform data providers. `None` on
+    /// platforms where providers don't cache data.
+    pub provider_cache_bytes: Option<i64>,
+}
+
+impl Diagnostics {
+    pub fn isolate(&self, isolate_id: IsolateId) -> Option<&IsolateDiagnostics> {
+        self.isolates.iter().find(|i| i.isolate_id == isolate_id.0)
+    }
+}
+
+#[derive(Default)]
+struct DiagnosticsCollector {
+    isolates: HashMap<IsolateId, IsolateDiagnostics>,
+}
+
+impl ResourceVisitor for DiagnosticsCollector {
+    fn visit(&mut self, isolate_id: IsolateId, kind: ResourceKind, origin: &ResourceOrigin) {
+        let stats = self
+            .isolates
+            .entry(isolate_id)
+            .or_insert_with(|| IsolateDiagnostics::new(isolate_id))
+            .stats_mut(kind);
+        stats.count += 1;
+        stats.ages_millis.push(origin.age().as_millis() as i64);
+    }
+}
+
+impl DiagnosticsCollector {
+    fn finish(self) -> Diagnostics {
+        let mut isolates: Vec<_> = self.isolates.into_values().collect();
+        isolates.sort_by_key(|i| i.isolate_id);
+        for isolate in &mut isolates {
+            for kind in [
+                ResourceKind::Reader,
+                ResourceKind::ReadProgress,
+                ResourceKind::VirtualFileReader,
+                ResourceKind::Provider,
+                ResourceKind::VirtualSession,
+                ResourceKind::Menu,
+                ResourceKind::HotKey,
+            ] {
+                let ages = &mut isolate.stats_mut(kind).ages_millis;
+                ages.sort_unstable_by(|a, b| b.cmp(a));
+            }
+        }
+        let queue_bytes = segmented_queue::buffered_bytes();
+        Diagnostics {
+            isolates,
+            segmented_queue_memory_bytes: queue_bytes.memory as i64,
+            segmented_queue_file_bytes: queue_bytes.file as i64,
+            provider_cache_bytes: platform_provider_cache_bytes().map(|b| b as i64),
+        }
+    }
+}
+
+#[derive(TryFromValue, Debug, Clone)]
+#[irondash(rename_all = "camelCase")]
+pub struct LeakWatchdogConfig {
+    /// Readers, providers and virtual file sessions older than this are
+    /// reported. `None` disables the watchdog.
+    pub threshold_millis: Option<i64>,
+    /// Whether to invoke `onLeakSuspected` in the isolate owning the resource
+    /// in addition to logging a warning.
+    pub notify_dart: bool,
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct LeakReport {
+    pub kind: String,
+    pub age_millis: i64,
+    pub creation_site: String,
+}
+
+/// Finds watched resources older than threshold that were not reported yet.
+struct LeakScan {
+    threshold: Duration,
+    found: Vec<(IsolateId, LeakReport)>,
+}
+
+impl ResourceVisitor for LeakScan {
+    fn visit(&mut self, isolate_id: IsolateId, kind: ResourceKind, origin: &ResourceOrigin) {
+        let watched = matches!(
+            kind,
+            ResourceKind::Reader | ResourceKind::Provider | ResourceKind::VirtualSession
+        );
+        if !watched || origin.reported.get() || origin.age() < self.threshold {
+            return;
+        }
+        origin.reported.set(true);
+        self.found.push((
+            isolate_id,
+            LeakReport {
+                kind: kind.name().into(),
+                age_millis: origin.age().as_millis() as i64,
+                creation_site: origin.creation_site().into(),
+            },
+        ));
+    }
+}
+
+pub struct DiagnosticsManager {
+    weak_self: Late<Weak<Self>>,
+    invoker: Late<AsyncInvoker>,
+    watchdog_config: RefCell<Option<LeakWatchdogConfig>>,
+    // Incremented on reconfiguration so that already scheduled scans stop.
+    watchdog_generation: Cell<u64>,
+}
+
+impl DiagnosticsManager {
+    pub fn new() -> RegisteredAsyncMethodHandler<Self> {
+        Self {
+            weak_self: Late::new(),
+            invoker: Late::new(),
+            watchdog_config: RefCell::new(None),
+            watchdog_generation: Cell::new(0),
+        }
+        .register("DiagnosticsManager")
+    }
+
+    fn visit_resources(&self, visitor: &mut dyn ResourceVisitor) {
+        let context = Context::get();
+        context.data_reader_manager().visit_resources(visitor);
+        context.data_provider_manager().visit_resources(visitor);
+        #[cfg(feature = "menu")]
+        context.menu_manager().visit_resources(visitor);
+        #[cfg(feature = "hot-key")]
+        context.hot_key_manager().visit_resources(visitor);
+    }
+
+    pub fn get_diagnostics(&self) -> Diagnostics {
+        let mut collector = DiagnosticsCollector::default();
+        self.visit_resources(&mut collector);
+        collector.finish()
+    }
+
+    pub fn configure_leak_watchdog(&self, config: LeakWatchdogConfig) {
+        let generation = self.watchdog_generation.get() + 1;
+        self.watchdog_generation.set(generation);
+        let enabled = config.threshold_millis.is_some();
+        self.watchdog_config.replace(enabled.then_some(config));
+        if enabled {
+            self.schedule_leak_scan(generation);
+        }
+    }
+
+    fn watchdog_threshold(&self) -> Option<Duration> {
+        self.watchdog_config
+            .borrow()
+            .as_ref()
+            .and_then(|c| c.threshold_millis)
+            .map(|t| Duration::from_millis(t.max(0) as u64))
+    }
+
+    fn schedule_leak_scan(&self, generation: u64) {
+        let threshold = match self.watchdog_threshold() {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let interval = (threshold / 4).max(Duration::from_secs(1));
+        let weak_self = self.weak_self.clone();
+        RunLoop::current()
+            .schedule(interval, move || {
+                if let Some(this) = weak_self.upgrade() {
+                    if this.watchdog_generation.get() == generation {
+                        this.scan_for_leaks();
+                        this.schedule_leak_scan(generation);
+                    }
+                }
+            })
+            .detach();
+    }
+
+    /// Reports resources that outlived the watchdog threshold. Each resource
+    /// is reported at most once.
+    pub fn scan_for_leaks(&self) -> Vec<LeakReport> {
+        let threshold = match self.watchdog_threshold() {
+            Some(threshold) => threshold,
+            None => return Vec::new(),
+        };
+        let mut scan = LeakScan {
+            threshold,
+            found: Vec::new(),
+        };
+        self.visit_resources(&mut scan);
+        let notify_dart = self
+            .watchdog_config
+            .borrow()
+            .as_ref()
+            .map(|c| c.notify_dart)
+            .unwrap_or(false);
+        let mut res = Vec::new();
+        for (isolate_id, report) in scan.found {
+            warn!(
+                "Possible leak: {} created at {} alive for {} ms",
+                report.kind, report.creation_site, report.age_millis
+            );
+            if notify_dart {
+                self.invoker
+                    .call_method_sync(isolate_id, "onLeakSuspected", report.clone(), |r| {
+                        r.ok_log();
+                    });
+            }
+            res.push(report);
+        }
+        res
+    }
+}
+
+pub trait GetDiagnosticsManager {
+    fn diagnostics_manager(&self) -> Rc<DiagnosticsManager>;
+}
+
+impl GetDiagnosticsManager for Context {
+    fn diagnostics_manager(&self) -> Rc<DiagnosticsManager> {
+        self.get_attachment(DiagnosticsManager::new).handler()
+    }
+}
+
+#[async_trait(?Send)]
+impl AsyncMethodHandler for DiagnosticsManager {
+    fn assign_weak_self(&self, weak_self: Weak<Self>) {
+        self.weak_self.set(weak_self);
+    }
+
+    fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
+        self.invoker.set(invoker.into());
+    }
+
+    async fn on_method_call(&self, call: MethodCall) -> PlatformResult {
+        match call.method.as_str() {
+            "getDiagnostics" => Ok(self.get_diagnostics().into()),
+            "configureLeakWatchdog" => {
+                self.configure_leak_watchdog(call.args.try_into()?);
+                Ok(Value::Null)
+            }
+            _ => Err(PlatformError {
+                code: "invalid_method".into(),
+                message: Some(format!("Unknown Method: {}", call.method)),
+                detail: Value::Null,
+            }),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::Value;
+
+    use super::{GetDiagnosticsManager, LeakWatchdogConfig};
+    use crate::{
+        api_model::{DataProvider, DataRepresentation},
+        context::Context,
+        data_provider_manager::RegisterDataProviderRequest,
+        platform::{next_turn, run_test, MockIsolate},
+    };
+
+    fn text_provider(creation_site: Option<&str>) -> RegisterDataProviderRequest {
+        RegisterDataProviderRequest {
+            provider: DataProvider {
+                representations: vec![DataRepresentation::Simple {
+                    format: "text/plain".into(),
+                    data: Value::String("text".into()),
+                }],
+                suggested_name: None,
+                clipboard_intent: None,
+            },
+            creation_site: creation_site.map(|s| s.into()),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "clipboard")]
+    fn test_diagnostics() {
+        use crate::{
+            api_model::DataProviderId, clipboard_reader::NewClipboardReaderRequest,
+            reader_manager::RegisteredDataReader,
+        };
+
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let diagnostics = || Context::get().diagnostics_manager().get_diagnostics();
+            assert!(diagnostics().isolate(isolate.id()).is_none());
+
+            let provider_id: DataProviderId = isolate
+                .call_method(
+                    "DataProviderManager",
+                    "registerDataProvider",
+                    text_provider(None),
+                )
+                .await
+                .unwrap()
+                .try_into()
+                .unwrap();
+            let request = NewClipboardReaderRequest {
+                creation_site: None,
+            };
+            let reader: RegisteredDataReader = isolate
+                .call_method("ClipboardReader", "newClipboardReader", request)
+                .await
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+            let snapshot = diagnostics();
+            let stats = snapshot.isolate(isolate.id()).unwrap();
+            assert_eq!(stats.providers.count, 1);
+            assert_eq!(stats.readers.count, 1);
+            assert_eq!(stats.readers.ages_millis.len(), 1);
+
+            isolate
+                .call_method("DataProviderManager", "unregisterDataProvider", provider_id)
+                .await
+                .unwrap();
+            isolate
+                .call_method("DataReaderManager", "disposeReader", reader.handle())
+                .await
+                .unwrap();
+            assert!(diagnostics().isolate(isolate.id()).is_none());
+        });
+    }
+
+    #[test]
+    fn test_leak_watchdog() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let manager = Context::get().diagnostics_manager();
+            for creation_site in [Some("package:app/main.dart 12:5"), None] {
+                isolate
+                    .call_method(
+                        "DataProviderManager",
+                        "registerDataProvider",
+                        text_provider(creation_site),
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            assert!(manager.scan_for_leaks().is_empty());
+            manager.configure_leak_watchdog(LeakWatchdogConfig {
+                threshold_millis: Some(0),
+                notify_dart: true,
+            });
+            let reports = manager.scan_for_leaks();
+            assert_eq!(reports.len(), 2);
+            assert!(reports.iter().all(|r| r.kind == "provider"));
+            let mut sites: Vec<_> = reports.iter().map(|r| r.creation_site.as_str()).collect();
+            sites.sort_unstable();
+            // Providers registered without Dart creation site are reported
+            // with the method that created them.
+            assert_eq!(
+                sites,
+                ["package:app/main.dart 12:5", "registerDataProvider"]
+            );
+
+            // Already reported resources are not reported again.
+            assert!(manager.scan_for_leaks().is_empty());
+
+            next_turn().await;
+            assert_eq!(isolate.calls_to("onLeakSuspected").len(), 2);
+
+            manager.configure_leak_watchdog(LeakWatchdogConfig {
+                threshold_millis: None,
+                notify_dart: false,
+            });
+        });
+    }
+}
@@ -0,0 +1,362 @@
+//! Combined drag image for platforms that only support single image per drag
+//! session. Item images are stacked behind the first item and a badge with
+//! number of items is drawn at the top right corner of the first item.
+
+use crate::api_model::{DragItem, ImageData, PixelFormat, Rect, TargettedImage};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CombinedImageStyle {
+    /// Maximum number of items visible in the stack.
+    pub max_stacked_items: usize,
+    /// Offset between stacked items in logical points.
+    pub stack_offset: f64,
+    /// Opacity of items stacked behind the first one.
+    pub stacked_item_opacity: f64,
+    /// Whether to draw the item count badge for multiple items.
+    pub badge: bool,
+}
+
+impl Default for CombinedImageStyle {
+    fn default() -> Self {
+        Self {
+            max_stacked_items: 4,
+            stack_offset: 6.0,
+            stacked_item_opacity: 0.7,
+            badge: true,
+        }
+    }
+}
+
+const BADGE_HEIGHT: f64 = 20.0;
+const BADGE_PADDING: f64 = 5.0;
+const BADGE_COLOR: [u8; 3] = [255, 59, 48];
+/// Size of single font pixel in logical points.
+const FONT_PIXEL: f64 = 1.6;
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+/// 5x7 glyphs for badge text; Each byte is a row with the leftmost pixel in
+/// bit 4.
+const GLYPHS: [(char, [u8; GLYPH_HEIGHT]); 11] = [
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+];
+
+fn badge_text(count: usize) -> String {
+    if count > 99 {
+        "99+".into()
+    } else {
+        count.to_string()
+    }
+}
+
+fn glyph_pixel(c: char, x: usize, y: usize) -> bool {
+    GLYPHS
+        .iter()
+        .find(|g| g.0 == c)
+        .map(|g| x < GLYPH_WIDTH && y < GLYPH_HEIGHT && g.1[y] & (0x10 >> x) != 0)
+        .unwrap_or(false)
+}
+
+/// Badge geometry in logical points.
+struct Badge {
+    text: Vec<char>,
+    rect: Rect,
+}
+
+impl Badge {
+    fn new(count: usize, center_x: f64, center_y: f64) -> Self {
+        let text: Vec<char> = badge_text(count).chars().collect();
+        let width = (Self::text_width(text.len()) + 2.0 * BADGE_PADDING).max(BADGE_HEIGHT);
+        Self {
+            text,
+            rect: Rect::xywh(
+                center_x - width / 2.0,
+                center_y - BADGE_HEIGHT / 2.0,
+                width,
+                BADGE_HEIGHT,
+            ),
+        }
+    }
+
+    fn text_width(len: usize) -> f64 {
+        ((GLYPH_WIDTH + 1) * len - 1) as f64 * FONT_PIXEL
+    }
+
+    /// Coverage of the capsule shaped background at given point; `pixel` is
+    /// size of one pixel in points, used for antialiasing.
+    fn background_coverage(&self, x: f64, y: f64, pixel: f64) -> f64 {
+        let radius = self.rect.height / 2.0;
+        let center_y = self.rect.y + radius;
+        let x0 = self.rect.x + radius;
+        let x1 = self.rect.x + self.rect.width - radius;
+        let dx = x - x.clamp(x0, x1);
+        let dy = y - center_y;
+        let distance = (dx * dx + dy * dy).sqrt() - radius;
+        (0.5 - distance / pixel).clamp(0.0, 1.0)
+    }
+
+    /// Coverage of the text within pixel at given point using 4x4
+    /// supersampling.
+    fn text_coverage(&self, x: f64, y: f64, pixel: f64) -> f64 {
+        let left = self.rect.x + (self.rect.width - Self::text_width(self.text.len())) / 2.0;
+        let top = self.rect.y + (self.rect.height - GLYPH_HEIGHT as f64 * FONT_PIXEL) / 2.0;
+        let mut hits = 0;
+        for sy in 0..4 {
+            for sx in 0..4 {
+                let fx = (x + (sx as f64 - 1.5) / 4.0 * pixel - left) / FONT_PIXEL;
+                let fy = (y + (sy as f64 - 1.5) / 4.0 * pixel - top) / FONT_PIXEL;
+                if fx < 0.0 || fy < 0.0 {
+                    continue;
+                }
+                let (fx, fy) = (fx as usize, fy as usize);
+                let index = fx / (GLYPH_WIDTH + 1);
+                if let Some(c) = self.text.get(index) {
+                    if glyph_pixel(*c, fx % (GLYPH_WIDTH + 1), fy) {
+                        hits += 1;
+                    }
+                }
+            }
+        }
+        hits as f64 / 16.0
+    }
+}
+
+fn union(a: &Rect, b: &Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    Rect::xywh(
+        x,
+        y,
+        (a.x + a.width).max(b.x + b.width) - x,
+        (a.y + a.height).max(b.y + b.height) - y,
+    )
+}
+
+/// Draws premultiplied pixel over premultiplied pixel.
+fn blend_pixel(target: &mut [u8], source: [u8; 4]) {
+    let inverse_alpha = 255 - source[3] as u32;
+    for (t, s) in target.iter_mut().zip(source) {
+        *t = (s as u32 + (*t as u32 * inverse_alpha + 127) / 255) as u8;
+    }
+}
+
+/// Draws image over canvas at given pixel position. Both images must be
+/// premultiplied RGBA.
+fn draw_image(canvas: &mut ImageData, image: &ImageData, x: i32, y: i32, opacity: f64) {
+    let opacity = (opacity.clamp(0.0, 1.0) * 256.0) as u32;
+    let canvas_width = canvas.width;
+    for row in 0..image.height {
+        let target_y = y + row;
+        if !(0..canvas.height).contains(&target_y) {
+            continue;
+        }
+        let source = image.row(row);
+        let target = canvas.row_mut(target_y);
+        for column in 0..image.width {
+            let target_x = x + column;
+            if !(0..canvas_width).contains(&target_x) {
+                continue;
+            }
+            let s = &source[column as usize * 4..column as usize * 4 + 4];
+            let s = [s[0], s[1], s[2], s[3]].map(|c| ((c as u32 * opacity) >> 8) as u8);
+            let t = target_x as usize * 4;
+            blend_pixel(&mut target[t..t + 4], s);
+        }
+    }
+}
+
+fn draw_badge(canvas: &mut ImageData, canvas_rect: &Rect, badge: &Badge) {
+    let scale = canvas.device_pixel_ratio.unwrap_or(1.0);
+    let pixel = 1.0 / scale;
+    let x0 = (((badge.rect.x - canvas_rect.x) * scale).floor() as i32).max(0);
+    let y0 = (((badge.rect.y - canvas_rect.y) * scale).floor() as i32).max(0);
+    let x1 = (((badge.rect.x + badge.rect.width - canvas_rect.x) * scale).ceil() as i32)
+        .min(canvas.width);
+    let y1 = (((badge.rect.y + badge.rect.height - canvas_rect.y) * scale).ceil() as i32)
+        .min(canvas.height);
+    for y in y0..y1 {
+        let point_y = canvas_rect.y + (y as f64 + 0.5) * pixel;
+        let row = canvas.row_mut(y);
+        for x in x0..x1 {
+            let point_x = canvas_rect.x + (x as f64 + 0.5) * pixel;
+            let alpha = badge.background_coverage(point_x, point_y, pixel);
+            if alpha == 0.0 {
+                continue;
+            }
+            let text = badge.text_coverage(point_x, point_y, pixel);
+            let color = BADGE_COLOR.map(|c| c as f64 + (255.0 - c as f64) * text);
+            let source = [
+                (color[0] * alpha).round() as u8,
+                (color[1] * alpha).round() as u8,
+                (color[2] * alpha).round() as u8,
+                (255.0 * alpha).round() as u8,
+            ];
+            let t = x as usize * 4;
+            blend_pixel(&mut row[t..t + 4], source);
+        }
+    }
+}
+
+/// Creates single drag image from images of all items. The first item is
+/// drawn on top at its original position.
+pub fn combine_drag_images(items: &[DragItem], style: &CombinedImageStyle) -> TargettedImage {
+    let images: Vec<&TargettedImage> = items.iter().map(|i| &i.image).collect();
+    combine_images(&images, style)
+}
+
+fn combine_images(images: &[&TargettedImage], style: &CombinedImageStyle) -> TargettedImage {
+    let Some(first) = images.first() else {
+        return TargettedImage {
+            image_data: ImageData::new(0, 0, PixelFormat::Rgba, None),
+            rect: Rect::default(),
+        };
+    };
+    let badge = (style.badge && images.len() > 1)
+        .then(|| Badge::new(images.len(), first.rect.x + first.rect.width, first.rect.y));
+    let stacked = images.len().clamp(1, style.max_stacked_items.max(1));
+    if stacked == 1 && badge.is_none() {
+        return TargettedImage {
+            image_data: first.image_data.clone(),
+            rect: first.rect.clone(),
+        };
+    }
+
+    // Items behind the first one keep their size and are centered on the
+    // first item, shifted by stack offset.
+    let center = first.rect.center();
+    let placed: Vec<(&TargettedImage, Rect)> = images[..stacked]
+        .iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let offset = i as f64 * style.stack_offset;
+            let rect = if i == 0 {
+                image.rect.clone()
+            } else {
+                Rect::xywh(
+                    center.x - image.rect.width / 2.0 + offset,
+                    center.y - image.rect.height / 2.0 + offset,
+                    image.rect.width,
+                    image.rect.height,
+                )
+            };
+            (*image, rect)
+        })
+        .collect();
+
+    let mut canvas_rect = placed
+        .iter()
+        .skip(1)
+        .fold(first.rect.clone(), |a, (_, rect)| union(&a, rect));
+    if let Some(badge) = &badge {
+        canvas_rect = union(&canvas_rect, &badge.rect);
+    }
+
+    let scale = first.image_data.device_pixel_ratio.unwrap_or(1.0);
+    let mut canvas = ImageData::new(
+        (canvas_rect.width * scale).ceil() as i32,
+        (canvas_rect.height * scale).ceil() as i32,
+        PixelFormat::RgbaPremultiplied,
+        Some(scale),
+    );
+    for (i, (image, rect)) in placed.iter().enumerate().rev() {
+        let mut image_data = image.image_data.clone();
+        if image_data.device_pixel_ratio.unwrap_or(1.0) != scale {
+            image_data = image_data.scaled_to_device_pixel_ratio(scale);
+        }
+        let image_data = image_data.into_format(PixelFormat::RgbaPremultiplied);
+        let opacity = if i == 0 {
+            1.0
+        } else {
+            style.stacked_item_opacity
+        };
+        draw_image(
+            &mut canvas,
+            &image_data,
+            ((rect.x - canvas_rect.x) * scale).round() as i32,
+            ((rect.y - canvas_rect.y) * scale).round() as i32,
+            opacity,
+        );
+    }
+    if let Some(badge) = &badge {
+        draw_badge(&mut canvas, &canvas_rect, badge);
+    }
+    TargettedImage {
+        rect: Rect::xywh(
+            canvas_rect.x,
+            canvas_rect.y,
+            canvas.width as f64 / scale,
+            canvas.height as f64 / scale,
+        ),
+        image_data: canvas,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{badge_text, combine_images, CombinedImageStyle};
+    use crate::api_model::{ImageData, PixelFormat, Rect, TargettedImage};
+
+    fn solid(rect: Rect, color: [u8; 4]) -> TargettedImage {
+        let mut image_data = ImageData::new(
+            (rect.width * 2.0) as i32,
+            (rect.height * 2.0) as i32,
+            PixelFormat::Rgba,
+            Some(2.0),
+        );
+        image_data.data = color.repeat((image_data.width * image_data.height) as usize);
+        TargettedImage { image_data, rect }
+    }
+
+    #[test]
+    fn test_single_item() {
+        let image = solid(Rect::xywh(10.0, 10.0, 20.0, 10.0), [0, 0, 255, 255]);
+        let res = combine_images(&[&image], &CombinedImageStyle::default());
+        assert_eq!(res.rect, image.rect);
+        assert_eq!(res.image_data, image.image_data);
+    }
+
+    #[test]
+    fn test_stack_and_badge() {
+        let first = solid(Rect::xywh(100.0, 100.0, 40.0, 40.0), [0, 0, 255, 255]);
+        let second = solid(Rect::xywh(0.0, 0.0, 40.0, 40.0), [0, 255, 0, 255]);
+        let style = CombinedImageStyle::default();
+        let res = combine_images(&[&first, &second], &style);
+        // Second item is centered on the first one and shifted by 6 points,
+        // badge (20 x 20 points) is centered on top right corner of the
+        // first item.
+        assert_eq!(res.rect, Rect::xywh(100.0, 90.0, 50.0, 56.0));
+        let image = &res.image_data;
+        assert_eq!((image.width, image.height), (100, 112));
+        assert_eq!(image.device_pixel_ratio, Some(2.0));
+        // First item is on top.
+        assert_eq!(image.pixel(20, 40), [0, 0, 255, 255]);
+        // Second item is translucent.
+        let [r, g, b, a] = image.pixel(86, 106);
+        assert_eq!((r, g, b), (0, 255, 0));
+        assert!((178..=180).contains(&a), "{a}");
+        // Badge background and text.
+        let badge_pixels: Vec<[u8; 4]> = (0..40)
+            .flat_map(|y| (60..100).map(move |x| (x, y)))
+            .map(|(x, y)| image.pixel(x, y))
+            .collect();
+        assert!(badge_pixels.contains(&[255, 59, 48, 255]));
+        assert!(badge_pixels.contains(&[255, 255, 255, 255]));
+        assert_eq!(image.pixel(99, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_badge_text() {
+        assert_eq!(badge_text(2), "2");
+        assert_eq!(badge_text(99), "99");
+        assert_eq!(badge_text(150), "99+");
+    }
+}
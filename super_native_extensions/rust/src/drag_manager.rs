@@ -20,14 +20,14 @@ use crate::{
     data_provider_manager::{DataProviderHandle, GetDataProviderManager},
     drop_manager::GetDropManager,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    invoker::AsyncInvoker,
     log::{OkLog, OkLogUnexpected},
-    menu_manager::GetMenuManager,
-    platform_impl::platform::{
-        PlatformDataProvider, PlatformDragContext, PlatformDropContext, PlatformMenuContext,
-    },
+    platform_impl::platform::{PlatformDataProvider, PlatformDragContext, PlatformDropContext},
     util::{DropNotifier, NextId},
     value_promise::{Promise, PromiseResult},
 };
+#[cfg(feature = "menu")]
+use crate::{menu_manager::GetMenuManager, platform_impl::platform::PlatformMenuContext};
 
 // Each isolate has its own DragContext.
 pub type PlatformDragContextId = IsolateId;
@@ -51,6 +51,7 @@ pub struct GetAdditionalItemsResult {
 pub trait PlatformDragContextDelegate {
     fn get_platform_drop_contexts(&self) -> Vec<Rc<PlatformDropContext>>;
 
+    #[cfg(feature = "menu")]
     fn get_platform_menu_contexts(&self) -> Vec<Rc<PlatformMenuContext>>;
 
     fn get_drag_configuration_for_location(
@@ -104,7 +105,7 @@ impl From<DragSessionId> for i64 {
 
 pub struct DragManager {
     weak_self: Late<Weak<Self>>,
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
     contexts: RefCell<HashMap<PlatformDragContextId, Rc<PlatformDragContext>>>,
     next_session_id: Cell<i64>,
 }
@@ -355,7 +356,7 @@ impl AsyncMethodHandler for DragManager {
     }
 
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     async fn on_method_call(&self, call: MethodCall) -> PlatformResult {
@@ -377,7 +378,10 @@ impl AsyncMethodHandler for DragManager {
     }
 
     fn on_isolate_destroyed(&self, isolate: IsolateId) {
-        self.contexts.borrow_mut().remove(&isolate);
+        // Dropping the context releases data providers of its sessions, which
+        // borrows contexts again; Must not happen while the map is borrowed.
+        let context = self.contexts.borrow_mut().remove(&isolate);
+        drop(context);
     }
 }
 
@@ -386,6 +390,7 @@ impl PlatformDragContextDelegate for DragManager {
         Context::get().drop_manager().get_platform_drop_contexts()
     }
 
+    #[cfg(feature = "menu")]
     fn get_platform_menu_contexts(&self) -> Vec<Rc<PlatformMenuContext>> {
         Context::get().menu_manager().get_platform_menu_contexts()
     }
@@ -531,3 +536,264 @@ impl PlatformDragContextDelegate for DragManager {
         );
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::{IntoValue, Value};
+
+    use super::{DragSessionId, GetDragManager};
+    use crate::{
+        api_model::{
+            DataProvider, DataProviderId, DataRepresentation, DropOperation, ImageData, Point, Rect,
+        },
+        context::Context,
+        data_provider_manager::RegisterDataProviderRequest,
+        platform::{next_turn, run_test, value_at, MockIsolate},
+    };
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ContextInitRequest {
+        engine_handle: i64,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct TargettedImage {
+        image_data: ImageData,
+        rect: Rect,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct DragItem {
+        data_provider_id: DataProviderId,
+        lift_image: Value,
+        image: TargettedImage,
+        local_data: Value,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct DragConfiguration {
+        items: Vec<DragItem>,
+        allowed_operations: Vec<DropOperation>,
+        animates_to_starting_position_on_cancel_or_fail: bool,
+        prefers_full_size_previews: bool,
+        shadow_style: Value,
+        preview_size_policy: Value,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct DragConfigurationResponse {
+        configuration: Value,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct DragRequest {
+        configuration: DragConfiguration,
+        combined_drag_image: Value,
+        position: Point,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct LocalDataRequest {
+        session_id: DragSessionId,
+    }
+
+    async fn new_drag_context(isolate: &MockIsolate) {
+        isolate
+            .call_method(
+                "DragManager",
+                "newContext",
+                ContextInitRequest { engine_handle: 0 },
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn register_provider(isolate: &MockIsolate) -> DataProviderId {
+        let provider = DataProvider {
+            representations: vec![DataRepresentation::Simple {
+                format: "text/plain".into(),
+                data: Value::String("hello".into()),
+            }],
+            suggested_name: None,
+            clipboard_intent: None,
+        };
+        isolate
+            .call_method(
+                "DataProviderManager",
+                "registerDataProvider",
+                RegisterDataProviderRequest {
+                    provider,
+                    creation_site: None,
+                },
+            )
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    fn configuration(provider_id: DataProviderId) -> DragConfiguration {
+        DragConfiguration {
+            items: vec![DragItem {
+                data_provider_id: provider_id,
+                lift_image: Value::Null,
+                image: TargettedImage {
+                    image_data: ImageData {
+                        width: 1,
+                        height: 1,
+                        bytes_per_row: 4,
+                        data: vec![0; 4],
+                        ..Default::default()
+                    },
+                    rect: Rect::xywh(0.0, 0.0, 1.0, 1.0),
+                },
+                local_data: Value::String("local".into()),
+            }],
+            allowed_operations: vec![DropOperation::Copy],
+            animates_to_starting_position_on_cancel_or_fail: true,
+            prefers_full_size_previews: false,
+            shadow_style: Value::Null,
+            preview_size_policy: Value::Null,
+        }
+    }
+
+    /// Starts drag session through "startDrag" with single text item.
+    async fn start_drag(isolate: &MockIsolate) -> DragSessionId {
+        let provider_id = register_provider(isolate).await;
+        isolate
+            .call_method(
+                "DragManager",
+                "startDrag",
+                DragRequest {
+                    configuration: configuration(provider_id),
+                    combined_drag_image: Value::Null,
+                    position: Point { x: 1.0, y: 2.0 },
+                },
+            )
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    async fn local_data(isolate: &MockIsolate, session_id: DragSessionId) -> Value {
+        isolate
+            .call_method(
+                "DragManager",
+                "getLocalData",
+                LocalDataRequest { session_id },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_drag_request() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            new_drag_context(&isolate).await;
+            let context = Context::get().drag_manager().get_platform_drag_contexts()[0].clone();
+
+            // Dart declines the drag.
+            isolate.on_call("getConfigurationForDragRequest", |_| {
+                Ok(DragConfigurationResponse {
+                    configuration: Value::Null,
+                }
+                .into())
+            });
+            let location = Point { x: 10.0, y: 20.0 };
+            let session_id = context
+                .simulate_drag_request(location.clone())
+                .await
+                .unwrap();
+            assert_eq!(session_id, None);
+
+            let provider_id = register_provider(&isolate).await;
+            isolate.on_call("getConfigurationForDragRequest", move |_| {
+                Ok(DragConfigurationResponse {
+                    configuration: configuration(provider_id).into(),
+                }
+                .into())
+            });
+            let session_id = context
+                .simulate_drag_request(location.clone())
+                .await
+                .unwrap()
+                .unwrap();
+            let requests = isolate.calls_to("getConfigurationForDragRequest");
+            assert_eq!(requests.len(), 2);
+            assert_eq!(
+                value_at(&requests[1], &["sessionId"]),
+                &Value::from(session_id)
+            );
+            assert_eq!(
+                value_at(&requests[1], &["location"]),
+                &Value::from(location)
+            );
+            assert_eq!(context.active_sessions(), vec![session_id]);
+
+            let reader = context.reader_for_session(session_id).unwrap();
+            let data = reader
+                .get_data_for_item(0, "text/plain".into(), None)
+                .await
+                .unwrap();
+            assert_eq!(data, Value::String("hello".into()));
+            assert_eq!(
+                local_data(&isolate, session_id).await,
+                Value::List(vec![Value::String("local".into())])
+            );
+
+            context
+                .simulate_drag_move(session_id, Point { x: 5.0, y: 5.0 })
+                .unwrap();
+            assert_eq!(isolate.calls_to("dragSessionDidMove").len(), 1);
+
+            // Ending the session releases its data provider.
+            context
+                .simulate_drag_end(session_id, DropOperation::Copy)
+                .unwrap();
+            next_turn().await;
+            let ended = isolate.calls_to("dragSessionDidEnd");
+            assert_eq!(ended.len(), 1);
+            assert_eq!(
+                value_at(&ended[0], &["dropOperation"]),
+                &Value::from(DropOperation::Copy)
+            );
+            assert_eq!(
+                isolate.calls_to("releaseDataProvider"),
+                vec![Value::from(provider_id)]
+            );
+            assert_eq!(local_data(&isolate, session_id).await, Value::Null);
+        });
+    }
+
+    #[test]
+    fn test_start_drag() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            new_drag_context(&isolate).await;
+            let session_id = start_drag(&isolate).await;
+
+            let manager = Context::get().drag_manager();
+            let context = manager.get_platform_drag_contexts()[0].clone();
+            assert_eq!(context.active_sessions(), vec![session_id]);
+            assert_eq!(
+                local_data(&isolate, session_id).await,
+                Value::List(vec![Value::String("local".into())])
+            );
+            drop(context);
+
+            // Context and sessions are torn down with the isolate, without
+            // calling back into destroyed isolate.
+            drop(isolate);
+            assert!(manager.get_platform_drag_contexts().is_empty());
+        });
+    }
+}
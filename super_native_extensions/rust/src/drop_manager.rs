@@ -16,8 +16,10 @@ use log::warn;
 use crate::{
     api_model::{DropOperation, ImageData, Point, Rect, Size},
     context::Context,
+    diagnostics::ResourceOrigin,
     drag_manager::{GetDragManager, PlatformDragContextId},
     error::{NativeExtensionsError, NativeExtensionsResult},
+    invoker::AsyncInvoker,
     log::{OkLog, OkLogUnexpected},
     platform_impl::platform::{PlatformDataReader, PlatformDragContext, PlatformDropContext},
     reader_manager::{GetDataReaderManager, RegisteredDataReader},
@@ -29,7 +31,7 @@ pub type PlatformDropContextId = IsolateId;
 
 pub struct DropManager {
     weak_self: Late<Weak<Self>>,
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
     contexts: RefCell<HashMap<PlatformDropContextId, Rc<PlatformDropContext>>>,
 }
 
@@ -231,7 +233,7 @@ impl AsyncMethodHandler for DropManager {
     }
 
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     async fn on_method_call(&self, call: MethodCall) -> PlatformResult {
@@ -305,9 +307,11 @@ impl PlatformDropContextDelegate for DropManager {
         id: PlatformDropContextId,
         platform_reader: Rc<PlatformDataReader>,
     ) -> RegisteredDataReader {
+        // Drop readers are created by the platform drop session rather than by
+        // a Dart call, so there is no Dart creation site to report.
         Context::get()
             .data_reader_manager()
-            .register_platform_reader(platform_reader, id)
+            .register_platform_reader(platform_reader, id, ResourceOrigin::new(None, "drop"))
     }
 
     fn get_preview_for_item(
@@ -336,3 +340,123 @@ impl PlatformDropContextDelegate for DropManager {
         res
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::{IntoValue, Value};
+
+    use super::GetDropManager;
+    use crate::{
+        api_model::{DataProvider, DataRepresentation, DropOperation, Point},
+        context::Context,
+        platform::{run_test, value_at, MockIsolate, PlatformDataReader},
+    };
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ContextInitRequest {
+        engine_handle: i64,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct RegisterDropFormatsRequest {
+        formats: Vec<String>,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ItemDataRequest {
+        item_handle: i64,
+        reader_handle: Value,
+        format: String,
+        progress_id: i64,
+    }
+
+    #[test]
+    fn test_drop() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            isolate
+                .call_method(
+                    "DropManager",
+                    "newContext",
+                    ContextInitRequest { engine_handle: 0 },
+                )
+                .await
+                .unwrap();
+            isolate
+                .call_method(
+                    "DropManager",
+                    "registerDropFormats",
+                    RegisterDropFormatsRequest {
+                        formats: vec!["text/plain".into()],
+                    },
+                )
+                .await
+                .unwrap();
+            let manager = Context::get().drop_manager();
+            let context = manager.get_platform_drop_contexts()[0].clone();
+            assert_eq!(context.registered_drop_formats(), ["text/plain"]);
+
+            isolate.on_call("onDropUpdate", |_| Ok(DropOperation::Copy.into()));
+            isolate.on_call("onPerformDrop", |_| Ok(Value::Null));
+            let reader = PlatformDataReader::new_with_data(vec![DataProvider {
+                representations: vec![DataRepresentation::Simple {
+                    format: "text/plain".into(),
+                    data: Value::String("dropped".into()),
+                }],
+                suggested_name: None,
+                clipboard_intent: None,
+            }]);
+            let location = Point { x: 5.0, y: 5.0 };
+            let operation = context
+                .simulate_drop_update(
+                    reader,
+                    location.clone(),
+                    vec![DropOperation::Copy, DropOperation::Move],
+                )
+                .await
+                .unwrap();
+            assert_eq!(operation, DropOperation::Copy);
+
+            let updates = isolate.calls_to("onDropUpdate");
+            assert_eq!(updates.len(), 1);
+            let items = match value_at(&updates[0], &["items"]) {
+                Value::List(items) => items.clone(),
+                other => panic!("unexpected items: {other:?}"),
+            };
+            assert_eq!(items.len(), 1);
+            assert_eq!(
+                value_at(&items[0], &["formats"]),
+                &Value::List(vec![Value::String("text/plain".into())])
+            );
+
+            // Dart reads dropped data through the reader sent with the event.
+            let data = isolate
+                .call_method(
+                    "DataReaderManager",
+                    "getItemData",
+                    ItemDataRequest {
+                        item_handle: 0,
+                        reader_handle: value_at(&updates[0], &["reader", "handle"]).clone(),
+                        format: "text/plain".into(),
+                        progress_id: 1,
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(data, Value::String("dropped".into()));
+
+            let operation = context.simulate_perform_drop(location).await.unwrap();
+            assert_eq!(operation, DropOperation::Copy);
+            assert_eq!(isolate.calls_to("onPerformDrop").len(), 1);
+            assert_eq!(isolate.calls_to("onDropEnded").len(), 1);
+            assert!(isolate.calls_to("onDropLeave").is_empty());
+
+            drop(context);
+            drop(isolate);
+            assert!(manager.get_platform_drop_contexts().is_empty());
+        });
+    }
+}
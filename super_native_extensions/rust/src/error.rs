@@ -1,6 +1,6 @@
-use std::{fmt::Display, io};
+use std::{error::Error, fmt::Display, io};
 
-use irondash_message_channel::{MethodCallError, PlatformError, Value};
+use irondash_message_channel::{IntoValue, MethodCallError, PlatformError, Value};
 
 #[derive(Debug)]
 pub enum NativeExtensionsError {
@@ -21,6 +21,19 @@ pub enum NativeExtensionsError {
     PlatformMenuNotFound,
     InvalidMenuElement,
     InvalidMenuConfigurationId,
+    /// Error reported by the operating system or platform framework.
+    SystemError {
+        domain: &'static str,
+        code: Option<i64>,
+        message: String,
+    },
+    /// Error that occurred while processing specific item; Adds item handle
+    /// and format to the detail of `source`.
+    ItemError {
+        item_handle: i64,
+        format: Option<String>,
+        source: Box<NativeExtensionsError>,
+    },
 }
 
 pub type NativeExtensionsResult<T> = Result<T, NativeExtensionsError>;
@@ -55,46 +68,154 @@ impl Display for NativeExtensionsError {
             NativeExtensionsError::InvalidMenuConfigurationId => {
                 write!(f, "invalid menu configuration id")
             }
+            NativeExtensionsError::SystemError {
+                domain,
+                code,
+                message,
+            } => match code {
+                Some(code) => write!(f, "{domain} error {code}: {message}"),
+                None => write!(f, "{domain} error: {message}"),
+            },
+            NativeExtensionsError::ItemError { source, .. } => source.fmt(f),
         }
     }
 }
 
-impl std::error::Error for NativeExtensionsError {}
+impl std::error::Error for NativeExtensionsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NativeExtensionsError::IOError(e) => Some(e),
+            NativeExtensionsError::ItemError { source, .. } => source.source(),
+            _ => None,
+        }
+    }
+}
+
+/// Machine readable error description sent to Dart as `PlatformError.detail`.
+#[derive(IntoValue, Debug)]
+#[irondash(rename_all = "camelCase")]
+struct ErrorDetail {
+    kind: String,
+    message: String,
+    domain: Option<String>,
+    io_error_kind: Option<String>,
+    os_error_code: Option<i64>,
+    format: Option<String>,
+    item_handle: Option<i64>,
+    cause: Value,
+}
+
+impl ErrorDetail {
+    fn new(kind: &str, message: String) -> Self {
+        Self {
+            kind: kind.into(),
+            message,
+            domain: None,
+            io_error_kind: None,
+            os_error_code: None,
+            format: None,
+            item_handle: None,
+            cause: Value::Null,
+        }
+    }
+
+    fn from_error(error: &(dyn Error + 'static)) -> Self {
+        Self {
+            cause: error
+                .source()
+                .map(|s| Self::from_error(s).into())
+                .unwrap_or(Value::Null),
+            ..Self::new("error", error.to_string())
+        }
+    }
+}
 
 impl NativeExtensionsError {
-    fn get_detail(&self) -> Value {
+    /// Wraps the error with item handle and format it relates to.
+    pub fn for_item(self, item_handle: i64, format: Option<&str>) -> Self {
+        NativeExtensionsError::ItemError {
+            item_handle,
+            format: format.map(|f| f.to_owned()),
+            source: Box::new(self),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
         match self {
-            NativeExtensionsError::UnknownError => "unknownError".into(),
-            NativeExtensionsError::MethodCallError(_) => "methodCallError".into(),
-            NativeExtensionsError::OtherError(_) => "otherError".into(),
-            NativeExtensionsError::DataSourceNotFound => "dataSourceNotFound".into(),
-            NativeExtensionsError::ReaderNotFound => "readerNotFound".into(),
-            NativeExtensionsError::PlatformContextNotFound => "platformContextNotFound".into(),
-            NativeExtensionsError::UnsupportedOperation => "unsupportedOperation".into(),
-            NativeExtensionsError::VirtualFileSessionNotFound => {
-                "virtualFileSessionNotFound".into()
+            NativeExtensionsError::UnknownError => "unknownError",
+            NativeExtensionsError::MethodCallError(_) => "methodCallError",
+            NativeExtensionsError::OtherError(_) => "otherError",
+            NativeExtensionsError::DataSourceNotFound => "dataSourceNotFound",
+            NativeExtensionsError::ReaderNotFound => "readerNotFound",
+            NativeExtensionsError::PlatformContextNotFound => "platformContextNotFound",
+            NativeExtensionsError::UnsupportedOperation => "unsupportedOperation",
+            NativeExtensionsError::VirtualFileSessionNotFound => "virtualFileSessionNotFound",
+            NativeExtensionsError::VirtualFileReceiveError(_) => "virtualFileReceiveError",
+            NativeExtensionsError::IOError(_) => "ioError",
+            NativeExtensionsError::InvalidData => "invalidData",
+            NativeExtensionsError::DragSessionNotFound => "dragSessionNotFound",
+            NativeExtensionsError::MouseEventNotFound => "mouseEventNotFound",
+            NativeExtensionsError::EngineContextError(_) => "engineContextError",
+            NativeExtensionsError::PlatformMenuNotFound => "platformMenuNotFound",
+            NativeExtensionsError::InvalidMenuElement => "invalidMenuElement",
+            NativeExtensionsError::InvalidMenuConfigurationId => "invalidMenuConfigurationId",
+            NativeExtensionsError::SystemError { .. } => "systemError",
+            NativeExtensionsError::ItemError { source, .. } => source.kind(),
+        }
+    }
+
+    fn get_detail(&self) -> ErrorDetail {
+        let mut detail = ErrorDetail::new(self.kind(), self.to_string());
+        match self {
+            NativeExtensionsError::MethodCallError(MethodCallError::PlatformError(e)) => {
+                detail.cause = PlatformErrorDetail {
+                    code: e.code.clone(),
+                    message: e.message.clone(),
+                    detail: e.detail.clone(),
+                }
+                .into();
             }
-            NativeExtensionsError::VirtualFileReceiveError(_) => "virtualFileReceiveError".into(),
-            NativeExtensionsError::IOError(_) => "ioError".into(),
-            NativeExtensionsError::InvalidData => "invalidData".into(),
-            NativeExtensionsError::DragSessionNotFound => "dragSessionNotFound".into(),
-            NativeExtensionsError::MouseEventNotFound => "mouseEventNotFound".into(),
-            NativeExtensionsError::EngineContextError(_) => "engineContextError".into(),
-            NativeExtensionsError::PlatformMenuNotFound => "platformMenuNotFound".into(),
-            NativeExtensionsError::InvalidMenuElement => "invalidMenuElement".into(),
-            NativeExtensionsError::InvalidMenuConfigurationId => {
-                "invalidMenuConfigurationId".into()
+            NativeExtensionsError::IOError(e) => {
+                detail.io_error_kind = Some(format!("{:?}", e.kind()));
+                detail.os_error_code = e.raw_os_error().map(|c| c as i64);
+                if let Some(inner) = e.get_ref() {
+                    detail.cause = ErrorDetail::from_error(inner).into();
+                }
+            }
+            NativeExtensionsError::SystemError { domain, code, .. } => {
+                detail.domain = Some((*domain).into());
+                detail.os_error_code = *code;
+            }
+            NativeExtensionsError::ItemError {
+                item_handle,
+                format,
+                source,
+            } => {
+                detail = source.get_detail();
+                detail.item_handle = Some(*item_handle);
+                detail.format = format.clone();
             }
+            _ => {}
         }
+        detail
     }
 }
 
+/// Dart error received in response to method call.
+#[derive(IntoValue, Debug)]
+#[irondash(rename_all = "camelCase")]
+struct PlatformErrorDetail {
+    code: String,
+    message: Option<String>,
+    detail: Value,
+}
+
 impl From<NativeExtensionsError> for PlatformError {
     fn from(err: NativeExtensionsError) -> Self {
         PlatformError {
             code: "super_native_extensions_error".into(),
             message: Some(err.to_string()),
-            detail: err.get_detail(),
+            detail: err.get_detail().into(),
         }
     }
 }
@@ -116,3 +237,73 @@ impl From<irondash_engine_context::Error> for NativeExtensionsError {
         NativeExtensionsError::EngineContextError(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use irondash_message_channel::{PlatformError, Value, ValueTupleList};
+
+    use super::NativeExtensionsError;
+
+    fn detail(error: NativeExtensionsError) -> ValueTupleList {
+        let error: PlatformError = error.into();
+        assert_eq!(error.code, "super_native_extensions_error");
+        match error.detail {
+            Value::Map(detail) => detail,
+            other => panic!("Unexpected detail: {:?}", other),
+        }
+    }
+
+    fn get<'a>(map: &'a ValueTupleList, key: &str) -> &'a Value {
+        map.iter()
+            .find_map(|(k, v)| (*k == Value::String(key.into())).then_some(v))
+            .unwrap_or_else(|| panic!("Missing key {key}"))
+    }
+
+    #[test]
+    fn test_simple_error() {
+        let detail = detail(NativeExtensionsError::ReaderNotFound);
+        assert_eq!(
+            get(&detail, "kind"),
+            &Value::String("readerNotFound".into())
+        );
+        assert_eq!(
+            get(&detail, "message"),
+            &Value::String("platform reader not found".into())
+        );
+        assert_eq!(get(&detail, "domain"), &Value::Null);
+        assert_eq!(get(&detail, "itemHandle"), &Value::Null);
+        assert_eq!(get(&detail, "cause"), &Value::Null);
+    }
+
+    #[test]
+    fn test_system_error() {
+        let detail = detail(NativeExtensionsError::SystemError {
+            domain: "windows",
+            code: Some(-2147221404),
+            message: "Invalid FORMATETC structure".into(),
+        });
+        assert_eq!(get(&detail, "kind"), &Value::String("systemError".into()));
+        assert_eq!(get(&detail, "domain"), &Value::String("windows".into()));
+        assert_eq!(get(&detail, "osErrorCode"), &Value::I64(-2147221404));
+    }
+
+    #[test]
+    fn test_item_io_error() {
+        let error = io::Error::new(io::ErrorKind::InvalidData, "bad header");
+        let detail = detail(NativeExtensionsError::from(error).for_item(3, Some("image/png")));
+        assert_eq!(get(&detail, "kind"), &Value::String("ioError".into()));
+        assert_eq!(
+            get(&detail, "ioErrorKind"),
+            &Value::String("InvalidData".into())
+        );
+        assert_eq!(get(&detail, "itemHandle"), &Value::I64(3));
+        assert_eq!(get(&detail, "format"), &Value::String("image/png".into()));
+        let Value::Map(cause) = get(&detail, "cause") else {
+            panic!("Missing cause");
+        };
+        assert_eq!(get(cause, "kind"), &Value::String("error".into()));
+        assert_eq!(get(cause, "message"), &Value::String("bad header".into()));
+    }
+}
@@ -0,0 +1,541 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use async_trait::async_trait;
+use irondash_message_channel::Value;
+
+use crate::{
+    context::Context,
+    error::NativeExtensionsResult,
+    html_codec::{decode_html, html_fragment},
+    platform_impl::platform::{platform_format_converters, PlatformDataReader},
+    reader_manager::ReadProgress,
+    value_cbor::value_from_format,
+    value_coerce::{CoerceToData, CoerceToString, StringFormat},
+};
+
+/// Produces data in target format from data in one of the source formats.
+/// Registered converters are used to synthesize formats that the item does
+/// not provide itself.
+#[async_trait(?Send)]
+pub trait FormatConverter {
+    /// Format produced by this converter.
+    fn target_format(&self) -> &str;
+
+    /// Formats this converter can read, in order of preference.
+    fn source_formats(&self) -> &[String];
+
+    async fn convert(&self, source_format: &str, data: Value) -> NativeExtensionsResult<Value>;
+}
+
+pub struct FormatConverterRegistry {
+    converters: RefCell<Vec<Rc<dyn FormatConverter>>>,
+}
+
+pub trait GetFormatConverterRegistry {
+    fn format_converter_registry(&self) -> Rc<FormatConverterRegistry>;
+}
+
+impl GetFormatConverterRegistry for Context {
+    fn format_converter_registry(&self) -> Rc<FormatConverterRegistry> {
+        self.get_attachment(|| Rc::new(FormatConverterRegistry::new()))
+            .clone()
+    }
+}
+
+impl FormatConverterRegistry {
+    fn new() -> Self {
+        Self {
+            converters: RefCell::new(platform_format_converters()),
+        }
+    }
+
+    /// Registers converter. Converters registered later take precedence over
+    /// earlier ones and over the built-in converters.
+    pub fn register(&self, converter: Rc<dyn FormatConverter>) {
+        self.converters.borrow_mut().insert(0, converter);
+    }
+
+    /// Returns converter and source format for producing `target` from
+    /// `formats`. Returns `None` if `formats` already contain `target`.
+    fn converter_for(
+        &self,
+        formats: &[String],
+        target: &str,
+    ) -> Option<(Rc<dyn FormatConverter>, String)> {
+        if formats.iter().any(|f| f == target) {
+            return None;
+        }
+        self.converters
+            .borrow()
+            .iter()
+            .filter(|c| c.target_format() == target)
+            .find_map(|c| {
+                c.source_formats()
+                    .iter()
+                    .find(|s| formats.contains(s))
+                    .map(|s| (c.clone(), s.clone()))
+            })
+    }
+
+    /// Returns formats that can be synthesized from `formats`.
+    pub fn synthesized_formats(&self, formats: &[String]) -> Vec<String> {
+        let mut res = Vec::<String>::new();
+        for converter in self.converters.borrow().iter() {
+            let target = converter.target_format();
+            if res.iter().any(|f| f == target) {
+                continue;
+            }
+            if self.converter_for(formats, target).is_some() {
+                res.push(target.into());
+            }
+        }
+        res
+    }
+}
+
+/// Returns formats for item, including formats synthesized by registered
+/// converters.
+pub async fn item_formats(
+    reader: &PlatformDataReader,
+    item: i64,
+) -> NativeExtensionsResult<Vec<String>> {
+    let mut formats = reader.get_formats_for_item(item).await?;
+    let synthesized = Context::get()
+        .format_converter_registry()
+        .synthesized_formats(&formats);
+    formats.extend(synthesized);
+    Ok(formats)
+}
+
+/// Returns whether the format is synthesized either by the platform or by
+/// a registered converter.
+pub async fn item_format_is_synthesized(
+    reader: &PlatformDataReader,
+    item: i64,
+    format: &str,
+) -> NativeExtensionsResult<bool> {
+    if reader.item_format_is_synthesized(item, format)? {
+        return Ok(true);
+    }
+    let formats = reader.get_formats_for_item(item).await?;
+    Ok(Context::get()
+        .format_converter_registry()
+        .converter_for(&formats, format)
+        .is_some())
+}
+
+/// Reads data for item, running format conversion if the item does not
+/// provide the format itself.
+pub async fn item_data(
+    reader: &PlatformDataReader,
+    item: i64,
+    format: String,
+    progress: Option<Arc<ReadProgress>>,
+) -> NativeExtensionsResult<Value> {
+    let formats = reader.get_formats_for_item(item).await?;
+    let converter = Context::get()
+        .format_converter_registry()
+        .converter_for(&formats, &format);
+    match converter {
+        Some((converter, source_format)) => {
+            let data = reader
+                .get_data_for_item(item, source_format.clone(), progress)
+                .await?;
+            if data == Value::Null {
+                return Ok(Value::Null);
+            }
+            let data = value_from_format(data, &source_format);
+            let res = converter.convert(&source_format, data).await?;
+            Ok(value_from_format(res, &format))
+        }
+        None => {
+            let res = reader
+                .get_data_for_item(item, format.clone(), progress)
+                .await?;
+            Ok(value_from_format(res, &format))
+        }
+    }
+}
+
+/// Built-in converter producing plain text from HTML.
+pub struct HtmlToText {
+    source_formats: Vec<String>,
+    target_format: String,
+}
+
+impl HtmlToText {
+    pub fn new(source_formats: &[&str], target_format: &str) -> Self {
+        Self {
+            source_formats: source_formats.iter().map(|f| f.to_string()).collect(),
+            target_format: target_format.into(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl FormatConverter for HtmlToText {
+    fn target_format(&self) -> &str {
+        &self.target_format
+    }
+
+    fn source_formats(&self) -> &[String] {
+        &self.source_formats
+    }
+
+    async fn convert(&self, _source_format: &str, data: Value) -> NativeExtensionsResult<Value> {
+        let html = match data {
+            Value::String(html) => Some(html),
+            data => data
+                .coerce_to_data(StringFormat::Utf8)
+                .map(|d| decode_html(&d)),
+        };
+        Ok(html
+            .map(|html| html_to_text(html_fragment(&html)).into())
+            .unwrap_or(Value::Null))
+    }
+}
+
+/// Built-in converter producing plain text from RTF.
+pub struct RtfToText {
+    source_formats: Vec<String>,
+    target_format: String,
+}
+
+impl RtfToText {
+    pub fn new(source_formats: &[&str], target_format: &str) -> Self {
+        Self {
+            source_formats: source_formats.iter().map(|f| f.to_string()).collect(),
+            target_format: target_format.into(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl FormatConverter for RtfToText {
+    fn target_format(&self) -> &str {
+        &self.target_format
+    }
+
+    fn source_formats(&self) -> &[String] {
+        &self.source_formats
+    }
+
+    async fn convert(&self, _source_format: &str, data: Value) -> NativeExtensionsResult<Value> {
+        Ok(data
+            .coerce_to_string(None)
+            .map(|rtf| rtf_to_text(&rtf).into())
+            .unwrap_or(Value::Null))
+    }
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Extracts text content from HTML. Block level elements and `<br>` are
+/// turned into line breaks, content of `<script>` and `<style>` is dropped.
+pub fn html_to_text(html: &str) -> String {
+    const BLOCK_ELEMENTS: &[&str] = &[
+        "p",
+        "div",
+        "li",
+        "tr",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "blockquote",
+        "pre",
+        "ul",
+        "ol",
+        "table",
+        "br",
+    ];
+    let mut res = String::new();
+    let mut skip_until: Option<&str> = None;
+    let mut pending_space = false;
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest.find('>').unwrap_or(rest.len());
+            let tag = &rest[1..end];
+            rest = &rest[(end + 1).min(rest.len())..];
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            if let Some(skip) = skip_until {
+                if closing && name == skip {
+                    skip_until = None;
+                }
+                continue;
+            }
+            if !closing && (name == "script" || name == "style") {
+                skip_until = Some(if name == "script" { "script" } else { "style" });
+            } else if BLOCK_ELEMENTS.contains(&name.as_str())
+                && (closing || name == "br")
+                && !res.is_empty()
+            {
+                res.push('\n');
+                pending_space = false;
+            }
+            continue;
+        }
+        if skip_until.is_some() {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let (c, len) = if c == '&' {
+            match rest[1..].find(';').filter(|e| *e < 10) {
+                Some(end) => match decode_entity(&rest[1..end + 1]) {
+                    Some(c) => (c, end + 2),
+                    None => (c, 1),
+                },
+                None => (c, 1),
+            }
+        } else {
+            (c, c.len_utf8())
+        };
+        rest = &rest[len..];
+        if c.is_whitespace() && c != '\u{a0}' {
+            pending_space = true;
+        } else {
+            if pending_space && !res.is_empty() && !res.ends_with('\n') {
+                res.push(' ');
+            }
+            pending_space = false;
+            res.push(if c == '\u{a0}' { ' ' } else { c });
+        }
+    }
+    res.trim_end().to_string()
+}
+
+/// Extracts text content from RTF. Formatting is ignored, as are
+/// destinations that do not contain document text (font table, pictures,
+/// document info, ...).
+pub fn rtf_to_text(rtf: &str) -> String {
+    const IGNORED_DESTINATIONS: &[&str] = &[
+        "fonttbl",
+        "colortbl",
+        "stylesheet",
+        "info",
+        "pict",
+        "header",
+        "footer",
+        "listtable",
+        "listoverridetable",
+        "generator",
+        "themedata",
+        "latentstyles",
+        "datastore",
+        "xmlnstbl",
+    ];
+
+    struct Group {
+        ignored: bool,
+        unicode_skip: usize,
+    }
+
+    let mut res = String::new();
+    let mut stack = vec![Group {
+        ignored: false,
+        unicode_skip: 1,
+    }];
+    // Number of characters to skip after \uN control word.
+    let mut skip = 0;
+    let bytes = rtf.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'{' => {
+                let current = stack.last().unwrap();
+                let group = Group {
+                    ignored: current.ignored,
+                    unicode_skip: current.unicode_skip,
+                };
+                stack.push(group);
+                skip = 0;
+                i += 1;
+            }
+            b'}' => {
+                if stack.len() > 1 {
+                    stack.pop();
+                }
+                skip = 0;
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&next) = bytes.get(i) else {
+                    break;
+                };
+                let group = stack.last_mut().unwrap();
+                if next.is_ascii_alphabetic() {
+                    let start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word = &rtf[start..i];
+                    let param_start = i;
+                    if i < bytes.len() && bytes[i] == b'-' {
+                        i += 1;
+                    }
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let param = rtf[param_start..i].parse::<i32>().ok();
+                    if i < bytes.len() && bytes[i] == b' ' {
+                        i += 1;
+                    }
+                    if IGNORED_DESTINATIONS.contains(&word) {
+                        group.ignored = true;
+                        continue;
+                    }
+                    if group.ignored {
+                        continue;
+                    }
+                    match word {
+                        "par" | "line" | "row" => res.push('\n'),
+                        "tab" | "cell" => res.push('\t'),
+                        "uc" => group.unicode_skip = param.unwrap_or(1).max(0) as usize,
+                        "u" => {
+                            if let Some(param) = param {
+                                // Values over 32767 are written as negative numbers.
+                                let code = if param < 0 { param + 65536 } else { param };
+                                res.extend(char::from_u32(code as u32));
+                            }
+                            skip = group.unicode_skip;
+                        }
+                        _ => {}
+                    }
+                } else {
+                    // Control symbol may be followed by non-ASCII character.
+                    i += rtf[i..].chars().next().map_or(1, |c| c.len_utf8());
+                    match next {
+                        b'*' => group.ignored = true,
+                        b'\'' => {
+                            let hex = rtf.get(i..i + 2).unwrap_or_default();
+                            i += hex.len();
+                            if skip > 0 {
+                                skip -= 1;
+                            } else if !group.ignored {
+                                // Assume Windows-1252, which matches Latin-1 for
+                                // printable characters outside of 0x80-0x9F.
+                                if let Ok(b) = u8::from_str_radix(hex, 16) {
+                                    res.push(b as char);
+                                }
+                            }
+                        }
+                        b'~' if !group.ignored => res.push('\u{a0}'),
+                        b'\\' | b'{' | b'}' if !group.ignored => res.push(next as char),
+                        b'\n' | b'\r' if !group.ignored => res.push('\n'),
+                        _ => {}
+                    }
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            _ => {
+                let ch = rtf[i..].chars().next().unwrap();
+                i += ch.len_utf8();
+                if skip > 0 {
+                    skip -= 1;
+                } else if !stack.last().unwrap().ignored {
+                    res.push(ch);
+                }
+            }
+        }
+    }
+    res.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{html_to_text, rtf_to_text};
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_synthesized_formats() {
+        use irondash_message_channel::Value;
+
+        use super::{item_data, item_format_is_synthesized, item_formats};
+        use crate::{
+            api_model::{DataProvider, DataRepresentation},
+            platform::{run_test, PlatformDataReader},
+        };
+
+        run_test(async {
+            let reader = PlatformDataReader::new_with_data(vec![DataProvider {
+                representations: vec![DataRepresentation::Simple {
+                    format: "text/html".into(),
+                    data: Value::U8List(b"<p>Hello</p>".to_vec()),
+                }],
+                suggested_name: None,
+                clipboard_intent: None,
+            }]);
+            let formats = item_formats(&reader, 0).await.unwrap();
+            assert_eq!(formats, vec!["text/html", "text/plain"]);
+            assert!(item_format_is_synthesized(&reader, 0, "text/plain")
+                .await
+                .unwrap());
+            assert!(!item_format_is_synthesized(&reader, 0, "text/html")
+                .await
+                .unwrap());
+            let text = item_data(&reader, 0, "text/plain".into(), None)
+                .await
+                .unwrap();
+            assert_eq!(text, Value::String("Hello".into()));
+        });
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(
+            html_to_text(
+                "<html><head><style>p { color: red; }</style></head>\
+                 <body><p>Hello&nbsp;<b>world</b>!</p><p>A &amp; B&#x21;<br>x</p>\
+                 <script>alert('hi')</script></body></html>"
+            ),
+            "Hello world!\nA & B!\nx"
+        );
+        assert_eq!(html_to_text("a \n  b"), "a b");
+        assert_eq!(html_to_text("&unknown; &"), "&unknown; &");
+    }
+
+    #[test]
+    fn test_rtf_to_text() {
+        let rtf = r"{\rtf1\ansi\ansicpg1252{\fonttbl\f0\fswiss Helvetica;}
+{\colortbl;\red255\green255\blue255;}
+{\*\expandedcolortbl;;}
+\f0\fs24 Hello \b world\b0 !\par
+caf\'e9 \{x\}\tab\u8364?end}";
+        assert_eq!(rtf_to_text(rtf), "Hello world!\ncafé {x}\t€end");
+    }
+
+    #[test]
+    fn test_rtf_to_text_non_ascii_control_symbol() {
+        // Unknown control symbols are dropped, including non-ASCII ones.
+        assert_eq!(rtf_to_text(r"{\é x\中y}"), " xy");
+        assert_eq!(rtf_to_text("\\é"), "");
+    }
+}
@@ -12,7 +12,9 @@ use irondash_message_channel::{
 
 use crate::{
     context::Context,
+    diagnostics::{ResourceKind, ResourceOrigin, ResourceVisitor},
     error::{NativeExtensionsError, NativeExtensionsResult},
+    invoker::Invoker,
     log::OkLog,
     platform_impl::platform::PlatformHotKeyManager,
     util::NextId,
@@ -37,10 +39,10 @@ struct HotKeyDestroyRequest {
 pub struct HotKeyHandle(i64);
 
 pub struct HotKeyManager {
-    invoker: Late<MethodInvoker>,
-    handle_to_isolate: RefCell<HashMap<HotKeyHandle, IsolateId>>,
+    invoker: Late<Invoker>,
+    handle_to_isolate: RefCell<HashMap<HotKeyHandle, (IsolateId, ResourceOrigin)>>,
     next_id: Cell<i64>,
-    platform_manager: Late<Rc<PlatformHotKeyManager>>,
+    pub(crate) platform_manager: Late<Rc<PlatformHotKeyManager>>,
 }
 
 pub trait HotKeyManagerDelegate {
@@ -80,9 +82,10 @@ impl HotKeyManager {
             return Ok(None);
         }
         res?;
-        self.handle_to_isolate
-            .borrow_mut()
-            .insert(handle, isolate_id);
+        self.handle_to_isolate.borrow_mut().insert(
+            handle,
+            (isolate_id, ResourceOrigin::new(None, "createHotKey")),
+        );
         Ok(Some(handle))
     }
 
@@ -91,7 +94,13 @@ impl HotKeyManager {
         self.platform_manager.destroy_hot_key(request.handle)
     }
 
-    fn on_method_call(&self, call: MethodCall) -> PlatformResult {
+    pub fn visit_resources(&self, visitor: &mut dyn ResourceVisitor) {
+        for (isolate_id, origin) in self.handle_to_isolate.borrow().values() {
+            visitor.visit(*isolate_id, ResourceKind::HotKey, origin);
+        }
+    }
+
+    pub(crate) fn on_method_call(&self, call: MethodCall) -> PlatformResult {
         match call.method.as_str() {
             "createHotKey" => self
                 .create_hot_key(call.isolate, call.args.try_into()?)
@@ -110,7 +119,7 @@ impl MethodHandler for HotKeyManager {
     }
 
     fn assign_invoker(&self, invoker: MethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     fn assign_weak_self(&self, weak_self: std::rc::Weak<Self>) {
@@ -124,7 +133,7 @@ impl MethodHandler for HotKeyManager {
             .handle_to_isolate
             .borrow()
             .iter()
-            .filter_map(|(handle, id)| if *id == isolate { Some(*handle) } else { None })
+            .filter_map(|(handle, (id, _))| if *id == isolate { Some(*handle) } else { None })
             .collect::<Vec<_>>();
         for handle in handles {
             self.handle_to_isolate.borrow_mut().remove(&handle);
@@ -137,7 +146,7 @@ impl HotKeyManagerDelegate for HotKeyManager {
     fn on_hot_key_pressed(&self, handle: HotKeyHandle) {
         let handle_to_isolate = self.handle_to_isolate.borrow();
         let isolate = handle_to_isolate.get(&handle);
-        if let Some(isolate) = isolate {
+        if let Some((isolate, _)) = isolate {
             self.invoker
                 .call_method(*isolate, "onHotKeyPressed", handle, |r| {
                     r.ok_log();
@@ -147,7 +156,7 @@ impl HotKeyManagerDelegate for HotKeyManager {
     fn on_hot_key_released(&self, handle: HotKeyHandle) {
         let handle_to_isolate = self.handle_to_isolate.borrow();
         let isolate = handle_to_isolate.get(&handle);
-        if let Some(isolate) = isolate {
+        if let Some((isolate, _)) = isolate {
             self.invoker
                 .call_method(*isolate, "onHotKeyReleased", handle, |r| {
                     r.ok_log();
@@ -155,3 +164,90 @@ impl HotKeyManagerDelegate for HotKeyManager {
         }
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::rc::Rc;
+
+    use irondash_message_channel::{IntoValue, Value};
+
+    use super::{GetHotKeyManager, HotKeyHandle};
+    use crate::{
+        context::Context,
+        platform::{run_test, MockIsolate},
+    };
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct HotKeyCreateRequest {
+        alt: bool,
+        shift: bool,
+        meta: bool,
+        control: bool,
+        platform_code: i64,
+    }
+
+    #[derive(IntoValue)]
+    struct HotKeyDestroyRequest {
+        handle: HotKeyHandle,
+    }
+
+    async fn create_hot_key(isolate: &MockIsolate, platform_code: i64) -> HotKeyHandle {
+        let request = HotKeyCreateRequest {
+            alt: false,
+            shift: true,
+            meta: false,
+            control: true,
+            platform_code,
+        };
+        isolate
+            .call_method("HotKeyManager", "createHotKey", request)
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hot_key() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let platform_manager = Rc::clone(&Context::get().hot_key_manager().platform_manager);
+
+            let handle = create_hot_key(&isolate, 65).await;
+            let hot_keys = platform_manager.hot_keys();
+            assert_eq!(hot_keys.len(), 1);
+            assert_eq!(hot_keys[0].0, handle);
+            assert!(hot_keys[0].1.shift && hot_keys[0].1.control);
+            assert_eq!(hot_keys[0].1.platform_code, 65);
+
+            platform_manager.simulate_hot_key_pressed(handle).unwrap();
+            platform_manager.simulate_hot_key_released(handle).unwrap();
+            assert_eq!(
+                isolate.calls_to("onHotKeyPressed"),
+                vec![Value::from(handle)]
+            );
+            assert_eq!(
+                isolate.calls_to("onHotKeyReleased"),
+                vec![Value::from(handle)]
+            );
+
+            isolate
+                .call_method(
+                    "HotKeyManager",
+                    "destroyHotKey",
+                    HotKeyDestroyRequest { handle },
+                )
+                .await
+                .unwrap();
+            assert!(platform_manager.hot_keys().is_empty());
+            assert!(platform_manager.simulate_hot_key_pressed(handle).is_err());
+
+            // Hot keys are unregistered with the isolate that created them.
+            create_hot_key(&isolate, 66).await;
+            assert_eq!(platform_manager.hot_keys().len(), 1);
+            drop(isolate);
+            assert!(platform_manager.hot_keys().is_empty());
+        });
+    }
+}
@@ -0,0 +1,366 @@
+//! Encoding and decoding of HTML clipboard data. Applications disagree on
+//! how HTML should be stored: Windows uses CF_HTML (UTF-8 with header
+//! containing byte offsets), Firefox on Linux writes UTF-16 with byte order
+//! mark and some applications rely on `<meta charset>` declaration.
+
+use std::ops::Range;
+
+use crate::value_coerce::{decode_text, Charset};
+
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+const START_FRAGMENT: &str = "<!--StartFragment-->";
+const END_FRAGMENT: &str = "<!--EndFragment-->";
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    // ASCII lowercase preserves byte offsets.
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+/// Detects UTF-16 without byte order mark by looking for zero bytes in
+/// ASCII range characters.
+fn detect_utf16(data: &[u8]) -> Option<Charset> {
+    if let Some(bom) = data.get(..2) {
+        if bom == UTF16_LE_BOM {
+            return Some(Charset::Utf16Le);
+        } else if bom == UTF16_BE_BOM {
+            return Some(Charset::Utf16Be);
+        }
+    }
+    let sample = &data[..data.len().min(64) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zeros_at = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if odd > pairs / 2 && even == 0 {
+        Some(Charset::Utf16Le)
+    } else if even > pairs / 2 && odd == 0 {
+        Some(Charset::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Returns byte range of charset value in `<meta charset>` or
+/// `<meta http-equiv="Content-Type">` declaration.
+fn find_charset(html: &[u8]) -> Option<Range<usize>> {
+    let head = html[..html.len().min(1024)].to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(meta) = find_bytes(&head[offset..], b"<meta") {
+        let meta_start = offset + meta;
+        let meta_end = head[meta_start..]
+            .iter()
+            .position(|b| *b == b'>')
+            .map(|e| meta_start + e)
+            .unwrap_or(head.len());
+        if let Some(charset) = find_bytes(&head[meta_start..meta_end], b"charset=") {
+            let mut start = meta_start + charset + b"charset=".len();
+            while start < meta_end && matches!(head[start], b'"' | b'\'') {
+                start += 1;
+            }
+            let end = head[start..meta_end]
+                .iter()
+                .position(|b| matches!(b, b'"' | b'\'' | b';') || b.is_ascii_whitespace())
+                .map(|e| start + e)
+                .unwrap_or(meta_end);
+            return Some(start..end);
+        }
+        offset = meta_end;
+    }
+    None
+}
+
+fn declared_charset(html: &[u8]) -> Option<Charset> {
+    find_charset(html).and_then(|r| Charset::from_name(&String::from_utf8_lossy(html.get(r)?)))
+}
+
+/// Replaces charset declared in HTML with UTF-8 since the decoded HTML is
+/// always UTF-8.
+fn normalize_charset(html: String) -> String {
+    match find_charset(html.as_bytes()) {
+        Some(range)
+            if html.is_char_boundary(range.start)
+                && html.is_char_boundary(range.end)
+                && !html[range.clone()].eq_ignore_ascii_case("utf-8") =>
+        {
+            let mut html = html;
+            html.replace_range(range, "utf-8");
+            html
+        }
+        _ => html,
+    }
+}
+
+fn cf_html_header_value(data: &[u8], key: &str) -> Option<i64> {
+    let header = &data[..data.len().min(512)];
+    let header = String::from_utf8_lossy(header);
+    header
+        .lines()
+        .take_while(|l| !l.starts_with('<'))
+        .find_map(|l| l.strip_prefix(key)?.strip_prefix(':')?.trim().parse().ok())
+}
+
+/// Returns HTML from CF_HTML data, or `None` if the data has no CF_HTML
+/// header. Returns the whole document if available, fragment otherwise.
+pub fn decode_cf_html(data: &[u8]) -> Option<String> {
+    if !data.starts_with(b"Version:") {
+        return None;
+    }
+    let range = |start: &str, end: &str| -> Option<Range<usize>> {
+        let start = cf_html_header_value(data, start)?;
+        let end = cf_html_header_value(data, end)?;
+        if start < 0 || end < start || end as usize > data.len() {
+            return None;
+        }
+        Some(start as usize..end as usize)
+    };
+    let range = range("StartHTML", "EndHTML").or_else(|| range("StartFragment", "EndFragment"))?;
+    let html = String::from_utf8_lossy(&data[range]);
+    Some(html.trim_end_matches('\0').to_owned())
+}
+
+/// Decodes HTML clipboard data to UTF-8 string. Handles byte order marks,
+/// UTF-16 without byte order mark, CF_HTML and single byte charsets
+/// declared in `<meta>`. Declared charset is replaced with UTF-8.
+pub fn decode_html(data: &[u8]) -> String {
+    let html = if let Some(charset) = detect_utf16(data) {
+        decode_text(data, Some(charset))
+    } else if let Some(html) = decode_cf_html(data) {
+        html
+    } else if std::str::from_utf8(data).is_ok() {
+        decode_text(data, None)
+    } else {
+        match declared_charset(data) {
+            // Browsers decode ISO-8859-1 as Windows-1252.
+            Some(Charset::Iso8859_1) => decode_text(data, Some(Charset::Windows1252)),
+            charset => decode_text(data, charset),
+        }
+    };
+    normalize_charset(html)
+}
+
+/// Returns fragment part of the HTML; Content between fragment markers if
+/// present, content of `<body>` for full documents, or the HTML itself.
+pub fn html_fragment(html: &str) -> &str {
+    if let (Some(start), Some(end)) = (html.find(START_FRAGMENT), html.find(END_FRAGMENT)) {
+        let start = start + START_FRAGMENT.len();
+        if start <= end {
+            return &html[start..end];
+        }
+    }
+    if let Some(body) = find_ignore_case(html, "<body") {
+        let start = html[body..].find('>').map(|e| body + e + 1);
+        let end = find_ignore_case(html, "</body");
+        if let (Some(start), Some(end)) = (start, end) {
+            if start <= end {
+                return &html[start..end];
+            }
+        }
+    }
+    html
+}
+
+/// Returns full HTML document. Fragments are wrapped in document with UTF-8
+/// charset declaration.
+pub fn html_document(html: &str) -> String {
+    if find_ignore_case(html, "<html").is_some() {
+        html.to_owned()
+    } else {
+        format!("<html><head><meta charset=\"utf-8\"></head><body>{html}</body></html>")
+    }
+}
+
+/// Encodes HTML as CF_HTML. Fragment markers are added unless already
+/// present.
+pub fn encode_cf_html(html: &str) -> Vec<u8> {
+    let document = if html.contains(START_FRAGMENT) && html.contains(END_FRAGMENT) {
+        html_document(html)
+    } else {
+        let fragment = html_fragment(html);
+        let marked = format!("{START_FRAGMENT}{fragment}{END_FRAGMENT}");
+        if fragment.len() == html.len() {
+            html_document(&marked)
+        } else {
+            // Replace body content, keeping the rest of the document.
+            let start = fragment.as_ptr() as usize - html.as_ptr() as usize;
+            let end = start + fragment.len();
+            html_document(&format!("{}{marked}{}", &html[..start], &html[end..]))
+        }
+    };
+    let header =
+        |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+            format!(
+                "Version:0.9\r\nStartHTML:{start_html:010}\r\nEndHTML:{end_html:010}\r\n\
+             StartFragment:{start_fragment:010}\r\nEndFragment:{end_fragment:010}\r\n"
+            )
+        };
+    let header_len = header(0, 0, 0, 0).len();
+    let start_fragment = header_len + document.find(START_FRAGMENT).unwrap() + START_FRAGMENT.len();
+    let end_fragment = header_len + document.find(END_FRAGMENT).unwrap();
+    let mut res = header(
+        header_len,
+        header_len + document.len(),
+        start_fragment,
+        end_fragment,
+    )
+    .into_bytes();
+    res.extend_from_slice(document.as_bytes());
+    res
+}
+
+/// Encodes HTML provided by Dart for writing as `text/html`. The result is
+/// UTF-8, with charset declaration added if the HTML has none so that
+/// readers that do not assume UTF-8 decode it correctly.
+pub fn encode_html(data: &[u8]) -> Vec<u8> {
+    let html = decode_html(data);
+    if find_charset(html.as_bytes()).is_some() {
+        html.into_bytes()
+    } else {
+        html_document(&html).into_bytes()
+    }
+}
+
+/// Encodes HTML provided by Dart as CF_HTML. Data that already has CF_HTML
+/// header is returned unchanged.
+pub fn encode_cf_html_data(data: Vec<u8>) -> Vec<u8> {
+    if decode_cf_html(&data).is_some() {
+        data
+    } else {
+        encode_cf_html(&decode_html(&data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_cf_html, decode_html, encode_cf_html, encode_cf_html_data, encode_html,
+        html_document, html_fragment, UTF16_LE_BOM,
+    };
+    use crate::value_coerce::Charset;
+
+    fn encode_utf16(html: &str, bom: bool) -> Vec<u8> {
+        let mut res = Vec::new();
+        if bom {
+            res.extend_from_slice(UTF16_LE_BOM);
+        }
+        res.extend(Charset::Utf16Le.encode(html));
+        res
+    }
+
+    #[test]
+    fn test_cf_html() {
+        let encoded = encode_cf_html("<b>Příliš</b>");
+        let text = String::from_utf8(encoded.clone()).unwrap();
+        assert!(text.starts_with("Version:0.9\r\nStartHTML:0000000"));
+        let offset = |key: &str| -> usize {
+            let start = text.find(key).unwrap() + key.len() + 1;
+            text[start..start + 10].parse().unwrap()
+        };
+        assert_eq!(
+            &encoded[offset("StartFragment")..offset("EndFragment")],
+            "<b>Příliš</b>".as_bytes()
+        );
+        assert_eq!(offset("EndHTML"), encoded.len());
+        let decoded = decode_cf_html(&encoded).unwrap();
+        assert!(decoded.starts_with("<html>"));
+        assert_eq!(html_fragment(&decoded), "<b>Příliš</b>");
+        assert_eq!(decode_html(&encoded), decoded);
+
+        // Existing body content becomes fragment.
+        let encoded = encode_cf_html("<html><body class=\"a\"><i>x</i></body></html>");
+        let decoded = decode_cf_html(&encoded).unwrap();
+        assert_eq!(
+            decoded,
+            "<html><body class=\"a\"><!--StartFragment--><i>x</i><!--EndFragment--></body></html>"
+        );
+        assert_eq!(decode_cf_html(b"<b>x</b>"), None);
+    }
+
+    #[test]
+    fn test_encode_cf_html_data() {
+        let encoded = encode_cf_html("<b>x</b>");
+        assert_eq!(encode_cf_html_data(encoded.clone()), encoded);
+        assert_eq!(encode_cf_html_data(b"<b>x</b>".to_vec()), encoded);
+        assert_eq!(
+            decode_cf_html(&encode_cf_html_data(encode_utf16("<b>é</b>", true))).unwrap(),
+            decode_cf_html(&encode_cf_html("<b>é</b>")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encode_html() {
+        assert_eq!(
+            encode_html(b"<p>x</p>"),
+            b"<html><head><meta charset=\"utf-8\"></head><body><p>x</p></body></html>"
+        );
+        assert_eq!(
+            encode_html(b"<meta charset='utf-8'><p>x</p>"),
+            b"<meta charset='utf-8'><p>x</p>"
+        );
+        assert_eq!(
+            encode_html(&encode_utf16("<meta charset=\"utf-16\">é", true)),
+            "<meta charset=\"utf-8\">é".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let html = "<meta charset=\"utf-16\"><b>Ünïcödé</b>";
+        let expected = "<meta charset=\"utf-8\"><b>Ünïcödé</b>";
+        assert_eq!(decode_html(&encode_utf16(html, true)), expected);
+        assert_eq!(decode_html(&encode_utf16(html, false)), expected);
+        let mut be = vec![0xFE, 0xFF];
+        be.extend(html.encode_utf16().flat_map(|u| u.to_be_bytes()));
+        assert_eq!(decode_html(&be), expected);
+    }
+
+    #[test]
+    fn test_decode_charset() {
+        let mut html =
+            b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">"
+                .to_vec();
+        html.extend_from_slice(&[0x80, b' ', 0xE9]);
+        assert_eq!(
+            decode_html(&html),
+            "<meta http-equiv=\"Content-Type\" content=\"text/html; charset=utf-8\">€ é"
+        );
+        // Valid UTF-8 wins over declared charset.
+        assert_eq!(
+            decode_html("<meta charset='latin1'>é".as_bytes()),
+            "<meta charset='utf-8'>é"
+        );
+        let mut bom = vec![0xEF, 0xBB, 0xBF];
+        bom.extend_from_slice(b"<p>x</p>");
+        assert_eq!(decode_html(&bom), "<p>x</p>");
+    }
+
+    #[test]
+    fn test_fragment_and_document() {
+        assert_eq!(html_fragment("<p>x</p>"), "<p>x</p>");
+        assert_eq!(
+            html_fragment("<HTML><BODY bgcolor=white><p>x</p></BODY></HTML>"),
+            "<p>x</p>"
+        );
+        assert_eq!(
+            html_document("<p>x</p>"),
+            "<html><head><meta charset=\"utf-8\"></head><body><p>x</p></body></html>"
+        );
+        assert_eq!(html_document("<html></html>"), "<html></html>");
+    }
+}
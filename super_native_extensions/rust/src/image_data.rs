@@ -0,0 +1,244 @@
+//! Pixel format conversion, cropping and scaling of [`ImageData`]. Platform
+//! code converts images to the format expected by the platform API using
+//! [`ImageData::to_format`] instead of swizzling pixels itself.
+
+use std::borrow::Cow;
+
+use crate::{
+    api_model::{ImageData, PixelFormat},
+    image_resample::{resample, ResampleFilter},
+};
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Gray => 1,
+            _ => 4,
+        }
+    }
+
+    pub fn is_premultiplied(&self) -> bool {
+        matches!(
+            self,
+            PixelFormat::RgbaPremultiplied | PixelFormat::BgraPremultiplied
+        )
+    }
+
+    /// Returns pixel as straight RGBA.
+    fn read(&self, p: &[u8]) -> [u8; 4] {
+        match self {
+            PixelFormat::Rgba => [p[0], p[1], p[2], p[3]],
+            PixelFormat::Bgra => [p[2], p[1], p[0], p[3]],
+            PixelFormat::RgbaPremultiplied => unpremultiply([p[0], p[1], p[2], p[3]]),
+            PixelFormat::BgraPremultiplied => unpremultiply([p[2], p[1], p[0], p[3]]),
+            PixelFormat::Gray => [p[0], p[0], p[0], 255],
+        }
+    }
+
+    /// Writes straight RGBA pixel.
+    fn write(&self, rgba: [u8; 4], p: &mut [u8]) {
+        let [r, g, b, a] = match self {
+            PixelFormat::RgbaPremultiplied | PixelFormat::BgraPremultiplied => premultiply(rgba),
+            _ => rgba,
+        };
+        match self {
+            PixelFormat::Rgba | PixelFormat::RgbaPremultiplied => p.copy_from_slice(&[r, g, b, a]),
+            PixelFormat::Bgra | PixelFormat::BgraPremultiplied => p.copy_from_slice(&[b, g, r, a]),
+            // Alpha is dropped.
+            PixelFormat::Gray => p[0] = luma(rgba),
+        }
+    }
+}
+
+pub fn premultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    let m = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+    [m(r), m(g), m(b), a]
+}
+
+pub fn unpremultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    if a == 0 {
+        return [0, 0, 0, 0];
+    }
+    let u = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    [u(r), u(g), u(b), a]
+}
+
+fn luma([r, g, b, _]: [u8; 4]) -> u8 {
+    ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114 + 500) / 1000) as u8
+}
+
+impl ImageData {
+    /// Creates transparent image with tightly packed rows.
+    pub fn new(
+        width: i32,
+        height: i32,
+        pixel_format: PixelFormat,
+        device_pixel_ratio: Option<f64>,
+    ) -> Self {
+        let bytes_per_row = width * pixel_format.bytes_per_pixel() as i32;
+        Self {
+            width,
+            height,
+            bytes_per_row,
+            data: vec![0; (bytes_per_row * height) as usize],
+            device_pixel_ratio,
+            pixel_format: Some(pixel_format),
+        }
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.pixel_format.unwrap_or_default()
+    }
+
+    pub fn row(&self, y: i32) -> &[u8] {
+        let start = (y * self.bytes_per_row) as usize;
+        &self.data[start..start + self.width as usize * self.format().bytes_per_pixel()]
+    }
+
+    pub fn row_mut(&mut self, y: i32) -> &mut [u8] {
+        let start = (y * self.bytes_per_row) as usize;
+        let len = self.width as usize * self.format().bytes_per_pixel();
+        &mut self.data[start..start + len]
+    }
+
+    /// Returns pixel at given position as straight RGBA.
+    pub fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+        let format = self.format();
+        let bpp = format.bytes_per_pixel();
+        format.read(&self.row(y)[x as usize * bpp..])
+    }
+
+    /// Returns image in given format. Images already in that format are
+    /// borrowed, converted images have tightly packed rows.
+    pub fn to_format(&self, format: PixelFormat) -> Cow<'_, ImageData> {
+        if self.format() == format {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.convert(format))
+        }
+    }
+
+    pub fn into_format(self, format: PixelFormat) -> ImageData {
+        if self.format() == format {
+            self
+        } else {
+            self.convert(format)
+        }
+    }
+
+    fn convert(&self, format: PixelFormat) -> ImageData {
+        let source_format = self.format();
+        let source_bpp = source_format.bytes_per_pixel();
+        let mut res = ImageData::new(self.width, self.height, format, self.device_pixel_ratio);
+        for y in 0..self.height {
+            let source = self.row(y);
+            let target = res.row_mut(y);
+            for (s, t) in source
+                .chunks_exact(source_bpp)
+                .zip(target.chunks_exact_mut(format.bytes_per_pixel()))
+            {
+                format.write(source_format.read(s), t);
+            }
+        }
+        res
+    }
+
+    /// Returns part of the image. Rectangle is in pixels and is clamped to
+    /// image bounds.
+    pub fn cropped(&self, x: i32, y: i32, width: i32, height: i32) -> ImageData {
+        let x0 = x.clamp(0, self.width);
+        let y0 = y.clamp(0, self.height);
+        let x1 = (x + width).clamp(x0, self.width);
+        let y1 = (y + height).clamp(y0, self.height);
+        let format = self.format();
+        let bpp = format.bytes_per_pixel();
+        let mut res = ImageData::new(x1 - x0, y1 - y0, format, self.device_pixel_ratio);
+        for row in y0..y1 {
+            let source = &self.row(row)[x0 as usize * bpp..x1 as usize * bpp];
+            res.row_mut(row - y0).copy_from_slice(source);
+        }
+        res
+    }
+
+    /// Returns image resized to given dimensions using bilinear filtering.
+    pub fn resized(&self, width: i32, height: i32) -> ImageData {
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        resample(self, width, height, ResampleFilter::Bilinear)
+    }
+
+    /// Returns image rendered for given device pixel ratio. Size of the image
+    /// in points is preserved.
+    pub fn scaled_to_device_pixel_ratio(&self, device_pixel_ratio: f64) -> ImageData {
+        let width = (self.point_width() * device_pixel_ratio).round().max(1.0) as i32;
+        let height = (self.point_height() * device_pixel_ratio).round().max(1.0) as i32;
+        let mut res = self.resized(width, height);
+        res.device_pixel_ratio = Some(device_pixel_ratio);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{premultiply, unpremultiply};
+    use crate::api_model::{ImageData, PixelFormat};
+
+    fn test_image() -> ImageData {
+        let mut image = ImageData::new(3, 2, PixelFormat::Rgba, Some(2.0));
+        image.data = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 128],
+            [0, 0, 255, 0],
+            [10, 20, 30, 40],
+            [255, 255, 255, 255],
+            [0, 0, 0, 255],
+        ]
+        .concat();
+        image
+    }
+
+    #[test]
+    fn test_premultiply() {
+        assert_eq!(premultiply([255, 128, 0, 128]), [128, 64, 0, 128]);
+        assert_eq!(unpremultiply([128, 64, 0, 128]), [255, 128, 0, 128]);
+        assert_eq!(unpremultiply([10, 10, 10, 0]), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_convert() {
+        let image = test_image();
+        let bgra = image.to_format(PixelFormat::Bgra);
+        assert_eq!(&bgra.data[..8], &[0, 0, 255, 255, 0, 255, 0, 128]);
+        assert_eq!(bgra.into_owned().into_format(PixelFormat::Rgba), image);
+
+        let premultiplied = image.to_format(PixelFormat::BgraPremultiplied);
+        assert_eq!(&premultiplied.data[4..8], &[0, 128, 0, 128]);
+        assert_eq!(premultiplied.pixel(1, 0), [0, 255, 0, 128]);
+        // Fully transparent pixels lose color.
+        assert_eq!(premultiplied.pixel(2, 0), [0, 0, 0, 0]);
+
+        let gray = image.to_format(PixelFormat::Gray);
+        assert_eq!(gray.bytes_per_row, 3);
+        assert_eq!(gray.row(1), &[18, 255, 0]);
+        assert_eq!(gray.pixel(1, 1), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_crop_and_scale() {
+        let mut image = test_image();
+        // Padded rows.
+        image.bytes_per_row = 16;
+        image.data = [&image.data[..12], &[0; 4], &image.data[12..], &[0; 4]].concat();
+        let cropped = image.cropped(1, -1, 5, 2);
+        assert_eq!((cropped.width, cropped.height), (2, 1));
+        assert_eq!(cropped.data, vec![0, 255, 0, 128, 0, 0, 255, 0]);
+
+        let scaled = image.scaled_to_device_pixel_ratio(4.0);
+        assert_eq!((scaled.width, scaled.height), (6, 4));
+        assert_eq!(scaled.device_pixel_ratio, Some(4.0));
+        assert_eq!(scaled.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(scaled.pixel(5, 3), [0, 0, 0, 255]);
+        assert_eq!(image.resized(3, 2), image);
+    }
+}
@@ -0,0 +1,307 @@
+//! Image resampling and size limit for drag and menu previews. Dart renders
+//! previews at device pixel ratio of the widget, which for large widgets
+//! results in images much bigger than platforms expect for drag icons.
+//! [`PreviewSizePolicy`] downscales such images while keeping their size in
+//! logical points by lowering the device pixel ratio.
+
+use std::f64::consts::PI;
+
+use irondash_message_channel::TryFromValue;
+
+use crate::api_model::{ImageData, PixelFormat, TargettedImage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromValue)]
+#[irondash(rename_all = "camelCase")]
+pub enum ResampleFilter {
+    /// Averages source pixels covered by target pixel. Nearest neighbour
+    /// when upscaling.
+    Box,
+    Bilinear,
+    /// Sharpest of the filters, suitable for downscaling previews.
+    Lanczos3,
+}
+
+impl ResampleFilter {
+    fn support(&self) -> f64 {
+        match self {
+            ResampleFilter::Box => 0.5,
+            ResampleFilter::Bilinear => 1.0,
+            ResampleFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            ResampleFilter::Box => {
+                if x <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ResampleFilter::Bilinear => (1.0 - x).max(0.0),
+            ResampleFilter::Lanczos3 => {
+                if x < 1e-8 {
+                    1.0
+                } else if x < 3.0 {
+                    let px = PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Source pixels contributing to single target pixel.
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+fn contributions(
+    source_len: usize,
+    target_len: usize,
+    filter: ResampleFilter,
+) -> Vec<Contribution> {
+    let scale = source_len as f64 / target_len as f64;
+    // When downscaling the filter is stretched to cover all source pixels.
+    let filter_scale = scale.max(1.0);
+    let support = filter.support() * filter_scale;
+    (0..target_len)
+        .map(|i| {
+            let center = (i as f64 + 0.5) * scale;
+            let start = ((center - support).floor().max(0.0) as usize).min(source_len - 1);
+            let end = ((center + support).ceil() as usize).clamp(start + 1, source_len);
+            let mut weights: Vec<f64> = (start..end)
+                .map(|j| filter.weight((j as f64 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f64 = weights.iter().sum();
+            if sum.abs() < 1e-8 {
+                // Can only happen for box filter exactly between pixels.
+                weights.iter_mut().for_each(|w| *w = 0.0);
+                let nearest = (center as usize).clamp(start, end - 1);
+                weights[nearest - start] = 1.0;
+            } else {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+            Contribution {
+                start,
+                weights: weights.into_iter().map(|w| w as f32).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Resamples image to given size. Filtering is done on premultiplied pixels
+/// so that transparent pixels don't bleed color into their neighbours.
+/// Resulting image has the same pixel format and device pixel ratio as the
+/// source.
+pub fn resample(image: &ImageData, width: i32, height: i32, filter: ResampleFilter) -> ImageData {
+    let format = image.format();
+    let mut res = ImageData::new(
+        width.max(0),
+        height.max(0),
+        PixelFormat::RgbaPremultiplied,
+        image.device_pixel_ratio,
+    );
+    if image.width <= 0 || image.height <= 0 || res.width == 0 || res.height == 0 {
+        return res.into_format(format);
+    }
+    let source = image.to_format(PixelFormat::RgbaPremultiplied);
+    let (source_width, source_height) = (source.width as usize, source.height as usize);
+    let (target_width, target_height) = (res.width as usize, res.height as usize);
+
+    // Horizontal pass; Source height, target width.
+    let horizontal = contributions(source_width, target_width, filter);
+    let mut tmp = vec![0f32; source_height * target_width * 4];
+    for y in 0..source_height {
+        let row = source.row(y as i32);
+        let tmp_row = &mut tmp[y * target_width * 4..(y + 1) * target_width * 4];
+        for (target, c) in tmp_row.chunks_exact_mut(4).zip(horizontal.iter()) {
+            for (i, weight) in c.weights.iter().enumerate() {
+                let pixel = &row[(c.start + i) * 4..(c.start + i) * 4 + 4];
+                for (t, p) in target.iter_mut().zip(pixel) {
+                    *t += *p as f32 * weight;
+                }
+            }
+        }
+    }
+
+    // Vertical pass.
+    let vertical = contributions(source_height, target_height, filter);
+    let mut accumulator = vec![0f32; target_width * 4];
+    for (y, c) in vertical.iter().enumerate() {
+        accumulator.iter_mut().for_each(|a| *a = 0.0);
+        for (i, weight) in c.weights.iter().enumerate() {
+            let start = (c.start + i) * target_width * 4;
+            let tmp_row = &tmp[start..start + target_width * 4];
+            for (a, t) in accumulator.iter_mut().zip(tmp_row) {
+                *a += t * weight;
+            }
+        }
+        for (target, pixel) in res
+            .row_mut(y as i32)
+            .chunks_exact_mut(4)
+            .zip(accumulator.chunks_exact(4))
+        {
+            // Lanczos may overshoot; Color can't exceed alpha in premultiplied
+            // pixel.
+            let alpha = pixel[3].round().clamp(0.0, 255.0);
+            target[3] = alpha as u8;
+            for (t, p) in target[..3].iter_mut().zip(pixel) {
+                *t = p.round().clamp(0.0, alpha) as u8;
+            }
+        }
+    }
+    res.into_format(format)
+}
+
+/// Maximum size of preview images in physical pixels. Larger images are
+/// downscaled; Their size in logical points stays the same.
+#[derive(Clone, Copy, Debug, PartialEq, TryFromValue)]
+#[irondash(rename_all = "camelCase")]
+pub struct PreviewSizePolicy {
+    pub max_width: i32,
+    pub max_height: i32,
+    pub filter: ResampleFilter,
+}
+
+impl Default for PreviewSizePolicy {
+    fn default() -> Self {
+        Self {
+            max_width: 1024,
+            max_height: 1024,
+            filter: ResampleFilter::Lanczos3,
+        }
+    }
+}
+
+impl PreviewSizePolicy {
+    /// Returns scale by which the image needs to be downscaled, or `None`
+    /// if the image fits.
+    fn scale_for(&self, image: &ImageData) -> Option<f64> {
+        if image.width <= self.max_width && image.height <= self.max_height {
+            return None;
+        }
+        let scale_x = self.max_width as f64 / image.width as f64;
+        let scale_y = self.max_height as f64 / image.height as f64;
+        Some(scale_x.min(scale_y))
+    }
+
+    pub fn apply(&self, image: ImageData) -> ImageData {
+        let Some(scale) = self.scale_for(&image) else {
+            return image;
+        };
+        let width = ((image.width as f64 * scale).round() as i32).clamp(1, self.max_width);
+        let height = ((image.height as f64 * scale).round() as i32).clamp(1, self.max_height);
+        let mut res = resample(&image, width, height, self.filter);
+        // Keep size in points; Use the actual scale after rounding of the
+        // larger dimension to minimize error.
+        let actual_scale = if image.width >= image.height {
+            width as f64 / image.width as f64
+        } else {
+            height as f64 / image.height as f64
+        };
+        res.device_pixel_ratio = Some(image.device_pixel_ratio.unwrap_or(1.0) * actual_scale);
+        res
+    }
+
+    pub fn apply_to_targetted_image(&self, image: TargettedImage) -> TargettedImage {
+        TargettedImage {
+            image_data: self.apply(image.image_data),
+            rect: image.rect,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resample, PreviewSizePolicy, ResampleFilter};
+    use crate::api_model::{ImageData, PixelFormat, Rect, TargettedImage};
+
+    fn checkerboard(width: i32, height: i32) -> ImageData {
+        let mut image = ImageData::new(width, height, PixelFormat::Rgba, Some(2.0));
+        for y in 0..height {
+            for x in 0..width {
+                let value = if (x + y) % 2 == 0 { 255 } else { 0 };
+                image.row_mut(y)[x as usize * 4..x as usize * 4 + 4]
+                    .copy_from_slice(&[value, value, value, 255]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_resample() {
+        let mut uniform = ImageData::new(7, 5, PixelFormat::Bgra, None);
+        uniform.data = [10, 20, 30, 255].repeat(35);
+        for filter in [
+            ResampleFilter::Box,
+            ResampleFilter::Bilinear,
+            ResampleFilter::Lanczos3,
+        ] {
+            for (width, height) in [(3, 2), (7, 5), (20, 11)] {
+                let res = resample(&uniform, width, height, filter);
+                assert_eq!((res.width, res.height), (width, height));
+                assert_eq!(res.format(), PixelFormat::Bgra);
+                assert!(res.data.chunks(4).all(|p| p == [10, 20, 30, 255]));
+            }
+        }
+
+        // Box filter averages source pixels when downscaling.
+        let image = checkerboard(8, 6);
+        let res = resample(&image, 4, 3, ResampleFilter::Box);
+        assert!(res.data.chunks(4).all(|p| p == [128, 128, 128, 255]));
+        // Bilinear filter covers all source pixels as well.
+        let res = resample(&image, 4, 3, ResampleFilter::Bilinear);
+        assert_eq!(res.pixel(1, 1), [128, 128, 128, 255]);
+
+        // Box filter upscales as nearest neighbour.
+        let res = resample(&image, 16, 12, ResampleFilter::Box);
+        assert_eq!(res.pixel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(res.pixel(1, 1), [255, 255, 255, 255]);
+        assert_eq!(res.pixel(2, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_resample_transparent() {
+        let mut image = ImageData::new(4, 1, PixelFormat::Rgba, None);
+        image.data = [
+            [255, 0, 0, 255],
+            [255, 0, 0, 255],
+            [0, 255, 0, 0],
+            [0, 255, 0, 0],
+        ]
+        .concat();
+        let res = resample(&image, 2, 1, ResampleFilter::Lanczos3);
+        // Color of transparent pixels must not leak.
+        let [r, g, _, a] = res.pixel(0, 0);
+        assert!(r > 250 && g == 0 && a > 200, "{:?}", res.pixel(0, 0));
+        assert_eq!(res.pixel(1, 0)[1], 0);
+    }
+
+    #[test]
+    fn test_preview_size_policy() {
+        let policy = PreviewSizePolicy {
+            max_width: 100,
+            max_height: 50,
+            filter: ResampleFilter::Bilinear,
+        };
+        let image = TargettedImage {
+            image_data: checkerboard(400, 100),
+            rect: Rect::xywh(10.0, 20.0, 200.0, 50.0),
+        };
+        let res = policy.apply_to_targetted_image(image);
+        assert_eq!((res.image_data.width, res.image_data.height), (100, 25));
+        assert_eq!(res.image_data.device_pixel_ratio, Some(0.5));
+        assert_eq!(res.image_data.point_width(), 200.0);
+        assert_eq!(res.image_data.point_height(), 50.0);
+        assert_eq!(res.rect, Rect::xywh(10.0, 20.0, 200.0, 50.0));
+
+        let small = checkerboard(10, 10);
+        assert_eq!(policy.apply(small.clone()), small);
+    }
+}
@@ -0,0 +1,112 @@
+use irondash_message_channel::{
+    AsyncMethodInvoker, IsolateId, MethodCallError, MethodInvoker, Value,
+};
+
+/// Wrapper around [`AsyncMethodInvoker`] used by managers to call into Dart.
+/// With the `mock` feature enabled calls targeting mock isolates are answered
+/// by the isolate instead of being sent through the message channel.
+pub struct AsyncInvoker {
+    inner: AsyncMethodInvoker,
+}
+
+impl From<AsyncMethodInvoker> for AsyncInvoker {
+    fn from(inner: AsyncMethodInvoker) -> Self {
+        Self { inner }
+    }
+}
+
+impl AsyncInvoker {
+    pub async fn call_method_cv<V, T>(
+        &self,
+        target_isolate: IsolateId,
+        method: &str,
+        args: V,
+    ) -> Result<T, MethodCallError>
+    where
+        V: Into<Value>,
+        T: TryFrom<Value>,
+        T::Error: Into<MethodCallError>,
+    {
+        let args = args.into();
+        #[cfg(feature = "mock")]
+        if let Some(res) = crate::platform::mock_isolate_call(target_isolate, method, &args) {
+            return T::try_from(res?).map_err(Into::into);
+        }
+        self.inner
+            .call_method_cv(target_isolate, method, args)
+            .await
+    }
+
+    pub fn call_method_sync<V, F>(&self, target_isolate: IsolateId, method: &str, args: V, reply: F)
+    where
+        V: Into<Value>,
+        F: FnOnce(Result<Value, MethodCallError>) + 'static,
+    {
+        let args = args.into();
+        #[cfg(feature = "mock")]
+        if let Some(res) = crate::platform::mock_isolate_call(target_isolate, method, &args) {
+            reply_later(move || reply(res));
+            return;
+        }
+        self.inner
+            .call_method_sync(target_isolate, method, args, reply)
+    }
+
+    pub fn call_method_sync_cv<V, T, F>(
+        &self,
+        target_isolate: IsolateId,
+        method: &str,
+        args: V,
+        reply: F,
+    ) where
+        V: Into<Value>,
+        T: TryFrom<Value>,
+        T::Error: Into<MethodCallError>,
+        F: FnOnce(Result<T, MethodCallError>) + 'static,
+    {
+        let args = args.into();
+        #[cfg(feature = "mock")]
+        if let Some(res) = crate::platform::mock_isolate_call(target_isolate, method, &args) {
+            reply_later(move || reply(res.and_then(|v| T::try_from(v).map_err(Into::into))));
+            return;
+        }
+        self.inner
+            .call_method_sync_cv(target_isolate, method, args, reply)
+    }
+}
+
+/// Wrapper around [`MethodInvoker`], see [`AsyncInvoker`].
+pub struct Invoker {
+    inner: MethodInvoker,
+}
+
+impl From<MethodInvoker> for Invoker {
+    fn from(inner: MethodInvoker) -> Self {
+        Self { inner }
+    }
+}
+
+impl Invoker {
+    pub fn call_method<V, F>(&self, target_isolate: IsolateId, method: &str, args: V, reply: F)
+    where
+        V: Into<Value>,
+        F: FnOnce(Result<Value, MethodCallError>) + 'static,
+    {
+        let args = args.into();
+        #[cfg(feature = "mock")]
+        if let Some(res) = crate::platform::mock_isolate_call(target_isolate, method, &args) {
+            reply_later(move || reply(res));
+            return;
+        }
+        self.inner.call_method(target_isolate, method, args, reply)
+    }
+}
+
+// Replies from Dart always arrive asynchronously; Mock replies must not be
+// delivered while the caller is still on the stack.
+#[cfg(feature = "mock")]
+fn reply_later<F: FnOnce() + 'static>(f: F) {
+    irondash_run_loop::RunLoop::current()
+        .schedule_next(f)
+        .detach();
+}
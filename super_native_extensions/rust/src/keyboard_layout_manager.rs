@@ -6,10 +6,12 @@ use std::{
 
 use irondash_message_channel::{
     IntoValue, IsolateId, Late, MethodCall, MethodCallReply, MethodHandler, MethodInvoker,
-    RegisteredMethodHandler, Value,
+    PlatformResult, RegisteredMethodHandler, Value,
 };
 
-use crate::{context::Context, log::OkLog, platform_impl::platform::PlatformKeyboardLayout};
+use crate::{
+    context::Context, invoker::Invoker, log::OkLog, platform_impl::platform::PlatformKeyboardLayout,
+};
 
 #[derive(IntoValue, Clone)]
 #[irondash(rename_all = "camelCase")]
@@ -31,7 +33,7 @@ pub struct KeyboardLayout {
 
 pub struct KeyboardLayoutManager {
     pub(crate) platform_layout: Late<Rc<PlatformKeyboardLayout>>,
-    invoker: Late<MethodInvoker>,
+    invoker: Late<Invoker>,
     isolates: RefCell<HashSet<IsolateId>>,
 }
 
@@ -58,20 +60,23 @@ impl KeyboardLayoutManager {
         }
         .register("KeyboardLayoutManager")
     }
-}
 
-impl MethodHandler for KeyboardLayoutManager {
-    fn on_method_call(&self, call: MethodCall, reply: MethodCallReply) {
-        #[allow(clippy::single_match)]
+    pub(crate) fn on_method_call(&self, call: MethodCall) -> PlatformResult {
         match call.method.as_str() {
             "getKeyboardLayout" => {
                 self.isolates.borrow_mut().insert(call.isolate);
                 let layout = self.platform_layout.get_current_layout();
-                reply.send_ok(layout);
+                Ok(layout.into())
             }
-            _ => {}
+            _ => Ok(Value::Null),
         }
     }
+}
+
+impl MethodHandler for KeyboardLayoutManager {
+    fn on_method_call(&self, call: MethodCall, reply: MethodCallReply) {
+        reply.send(self.on_method_call(call))
+    }
 
     fn assign_weak_self(&self, weak_self: Weak<Self>) {
         let delegate: Weak<dyn KeyboardLayoutDelegate> = weak_self;
@@ -82,7 +87,7 @@ impl MethodHandler for KeyboardLayoutManager {
     }
 
     fn assign_invoker(&self, invoker: MethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     /// Called when isolate is about to be destroyed.
@@ -104,3 +109,53 @@ impl KeyboardLayoutDelegate for KeyboardLayoutManager {
         }
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::Value;
+
+    use super::{GetKeyboardLayoutDelegate, Key, KeyboardLayout};
+    use crate::{
+        context::Context,
+        platform::{run_test, value_at, MockIsolate},
+    };
+
+    #[test]
+    fn test_layout_change() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let layout = isolate
+                .call_method("KeyboardLayoutManager", "getKeyboardLayout", Value::Null)
+                .await
+                .unwrap();
+            assert_eq!(value_at(&layout, &["keys"]), &Value::List(Vec::new()));
+
+            // Isolates that asked for layout are notified about changes.
+            let manager = Context::get().keyboard_map_manager();
+            manager
+                .platform_layout
+                .simulate_layout_change(KeyboardLayout {
+                    keys: vec![Key {
+                        platform: 38,
+                        physical: 0x70004,
+                        logical: Some(0x61),
+                        logical_shift: Some(0x41),
+                        logical_alt: None,
+                        logical_alt_shift: None,
+                        logical_meta: None,
+                    }],
+                });
+            let changes = isolate.calls_to("onLayoutChanged");
+            assert_eq!(changes.len(), 1);
+            match value_at(&changes[0], &["keys"]) {
+                Value::List(keys) => {
+                    assert_eq!(value_at(&keys[0], &["logical"]), &Value::I64(0x61));
+                }
+                other => panic!("unexpected keys: {other:?}"),
+            }
+
+            drop(isolate);
+            assert!(manager.isolates.borrow().is_empty());
+        });
+    }
+}
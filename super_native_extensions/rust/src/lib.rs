@@ -12,47 +12,81 @@
 // TODO(knopp): False positive in 1.83.0.
 #![allow(clippy::missing_const_for_thread_local)]
 
-use std::ffi::c_void;
+use std::{cell::RefCell, ffi::c_void};
 
 use ::log::debug;
+use capabilities::GetCapabilitiesManager;
+#[cfg(feature = "clipboard-events")]
 use clipboard_events_manager::GetClipboardEventManager;
+#[cfg(feature = "clipboard")]
 use clipboard_reader::GetClipboardReader;
+#[cfg(feature = "clipboard")]
 use clipboard_writer::GetClipboardWriter;
 use context::Context;
 use data_provider_manager::GetDataProviderManager;
+use diagnostics::GetDiagnosticsManager;
+#[cfg(feature = "drag-drop")]
 use drag_manager::GetDragManager;
+#[cfg(feature = "drag-drop")]
 use drop_manager::GetDropManager;
+#[cfg(feature = "hot-key")]
 use hot_key_manager::GetHotKeyManager;
+#[cfg(feature = "keyboard-layout")]
 use keyboard_layout_manager::GetKeyboardLayoutDelegate;
+use log_config_manager::GetLogConfigManager;
+#[cfg(feature = "menu")]
 use menu_manager::GetMenuManager;
 
 use irondash_message_channel::{irondash_init_message_channel_context, FunctionResult};
+use irondash_run_loop::RunLoop;
 use reader_manager::GetDataReaderManager;
 
+mod alpha_outline;
+#[cfg(feature = "clipboard")]
+pub mod api;
 mod api_model;
 mod blur;
+mod capabilities;
+#[cfg(feature = "clipboard-events")]
 mod clipboard_events_manager;
+#[cfg(feature = "clipboard")]
 mod clipboard_reader;
+#[cfg(feature = "clipboard")]
 mod clipboard_writer;
 mod context;
 mod data_provider_manager;
+mod diagnostics;
+mod drag_image;
+#[cfg(feature = "drag-drop")]
 mod drag_manager;
+#[cfg(feature = "drag-drop")]
 mod drop_manager;
 mod error;
+mod format_converter;
+#[cfg(feature = "hot-key")]
 mod hot_key_manager;
+mod html_codec;
+mod image_data;
+mod image_resample;
+mod invoker;
+#[cfg(feature = "keyboard-layout")]
 mod keyboard_layout_manager;
 mod log;
+mod log_config_manager;
+#[cfg(feature = "menu")]
 mod menu_manager;
 mod reader_manager;
 mod shadow;
+mod uri_list;
 mod util;
+mod value_cbor;
 mod value_coerce;
 mod value_promise;
 
 #[allow(dead_code)]
 mod segmented_queue;
 
-// #[cfg(not(test))]
+#[cfg(not(feature = "mock"))]
 #[path = "."]
 mod platform_impl {
     #[cfg(any(target_os = "ios", target_os = "macos"))]
@@ -72,12 +106,12 @@ mod platform_impl {
     pub mod platform;
 }
 
-// #[cfg(test)]
-// #[path = "."]
-// mod platform_impl {
-//     #[path = "mock/mod.rs"]
-//     pub mod platform;
-// }
+#[cfg(feature = "mock")]
+#[path = "."]
+mod platform_impl {
+    #[path = "mock/mod.rs"]
+    pub mod platform;
+}
 
 mod platform {
     pub(crate) use super::platform_impl::platform::*;
@@ -93,38 +127,61 @@ impl DataTransferPlugin {
         // eagerly initialize
         context.data_provider_manager();
         context.data_reader_manager();
+        #[cfg(feature = "clipboard")]
         context.clipboard_writer();
+        #[cfg(feature = "clipboard")]
         context.clipboard_reader();
+        #[cfg(feature = "drag-drop")]
         context.drag_manager();
+        #[cfg(feature = "drag-drop")]
         context.drop_manager();
+        #[cfg(feature = "keyboard-layout")]
         context.keyboard_map_manager();
+        #[cfg(feature = "hot-key")]
         context.hot_key_manager();
+        #[cfg(feature = "menu")]
         context.menu_manager();
+        #[cfg(feature = "clipboard-events")]
         context.clipboard_event_manager();
+        context.capabilities_manager();
+        context.log_config_manager();
+        context.diagnostics_manager();
         DataTransferPlugin { _context: context }
     }
 }
 
+impl Drop for DataTransferPlugin {
+    fn drop(&mut self) {
+        Context::shutdown();
+    }
+}
+
 thread_local! {
-    static PLUGIN: DataTransferPlugin = DataTransferPlugin::new();
+    static PLUGIN: RefCell<Option<DataTransferPlugin>> = const { RefCell::new(None) };
 }
 
 fn init(init_loger: bool) {
     if init_loger {
+        // Filtering is done by plugin logger, inner logger accepts everything.
         #[cfg(not(target_os = "ios"))]
-        {
-            simple_logger::init_with_level(::log::Level::Info).ok();
-        }
+        let inner = simple_logger::SimpleLogger::new().with_level(::log::LevelFilter::Trace);
         #[cfg(target_os = "ios")]
-        {
-            oslog::OsLogger::new("supernativeextensions")
-                .level_filter(::log::LevelFilter::Debug)
-                .init()
-                .ok();
-        }
+        let inner =
+            oslog::OsLogger::new("supernativeextensions").level_filter(::log::LevelFilter::Trace);
+        crate::log::init_logger(Box::new(inner), ::log::LevelFilter::Info);
     }
-    // Lazily initialize the thread local
-    PLUGIN.with(|_| {});
+    PLUGIN.with(|plugin| {
+        if plugin.borrow().is_none() {
+            plugin.replace(Some(DataTransferPlugin::new()));
+        }
+    });
+}
+
+fn shutdown() {
+    // Take the plugin out first so that it is not borrowed while managers
+    // are being dropped.
+    let plugin = PLUGIN.with(|plugin| plugin.take());
+    drop(plugin);
 }
 
 #[no_mangle]
@@ -134,6 +191,20 @@ pub extern "C" fn super_native_extensions_init() {
     init(true);
 }
 
+#[no_mangle]
+/// Releases all resources held by the plugin. Hot keys are unregistered,
+/// clipboard content provided by the plugin is released and pending sessions
+/// are cancelled. Plugin can be initialized again afterwards.
+///
+/// Can be called from any thread, in which case shutdown is scheduled on the
+/// platform thread.
+pub extern "C" fn super_native_extensions_shutdown() {
+    match RunLoop::sender_for_main_thread() {
+        Some(sender) if !sender.is_same_thread() => sender.send(shutdown),
+        _ => shutdown(),
+    }
+}
+
 #[cfg(target_os = "android")]
 mod android {
 
@@ -159,8 +230,8 @@ mod android {
         clip_data_helper: jni::objects::JObject,
         drag_drop_helper: jni::objects::JObject,
     ) {
-        use ::log::Level;
-        use android_logger::Config;
+        use ::log::{Level, LevelFilter};
+        use android_logger::{AndroidLogger, Config};
 
         // This is to ensure that engine context is not used for sending things
         // to main thread. EngineContext main thread sender does not work properly
@@ -168,10 +239,13 @@ mod android {
         // Without this clipboard access may deadlock.
         RunLoop::set_main_thread();
 
-        android_logger::init_once(
-            Config::default()
-                .with_min_level(Level::Info)
-                .with_tag("flutter"),
+        crate::log::init_logger(
+            Box::new(AndroidLogger::new(
+                Config::default()
+                    .with_min_level(Level::Trace)
+                    .with_tag("flutter"),
+            )),
+            LevelFilter::Info,
         );
         JAVA_VM.get_or_init(|| {
             env.get_java_vm()
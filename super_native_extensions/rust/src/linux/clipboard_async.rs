@@ -11,12 +11,14 @@ use glib_sys::{gpointer, GFALSE};
 use gtk::Clipboard;
 use gtk_sys::{
     gtk_clipboard_request_contents, gtk_clipboard_request_targets, gtk_clipboard_request_text,
-    gtk_clipboard_request_uris, gtk_selection_data_get_data, gtk_selection_data_get_length,
-    gtk_targets_include_text, GtkClipboard, GtkSelectionData,
+    gtk_selection_data_get_data, gtk_selection_data_get_length, gtk_targets_include_text,
+    GtkClipboard, GtkSelectionData,
 };
 use irondash_run_loop::util::FutureCompleter;
 
-use super::common::{AtomExt, TYPE_TEXT};
+use crate::uri_list::parse_uri_list;
+
+use super::common::{AtomExt, TYPE_TEXT, TYPE_URI};
 
 #[async_trait(?Send)]
 pub trait ClipboardAsync {
@@ -53,15 +55,10 @@ impl ClipboardAsync for Clipboard {
     }
 
     async fn get_uri_list(&self) -> Vec<String> {
-        let (future, completer) = FutureCompleter::new();
-        unsafe {
-            gtk_clipboard_request_uris(
-                self.to_glib_none().0,
-                Some(on_uri_list),
-                Box::into_raw(Box::new(completer)) as *mut _,
-            )
-        }
-        future.await
+        self.get_data(TYPE_URI)
+            .await
+            .map(|data| parse_uri_list(&data))
+            .unwrap_or_default()
     }
 
     async fn get_data(&self, ty: &str) -> Option<Vec<u8>> {
@@ -111,26 +108,6 @@ extern "C" fn on_text(_: *mut GtkClipboard, text: *const c_char, completer: gpoi
     }
 }
 
-extern "C" fn on_uri_list(_: *mut GtkClipboard, list: *mut *mut c_char, completer: gpointer) {
-    let completer = completer as *mut FutureCompleter<Vec<String>>;
-    let completer = unsafe { Box::from_raw(completer) };
-    let mut res = Vec::new();
-    if !list.is_null() {
-        let mut index = 0usize;
-        loop {
-            let uri = unsafe { *list.add(index) };
-            if uri.is_null() {
-                break;
-            } else {
-                let uri = unsafe { CStr::from_ptr(uri) }.to_string_lossy();
-                res.push(uri.into());
-            }
-            index += 1;
-        }
-    }
-    completer.complete(res)
-}
-
 extern "C" fn on_contents(
     _: *mut GtkClipboard,
     selection_data: *mut GtkSelectionData,
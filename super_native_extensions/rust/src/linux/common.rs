@@ -3,15 +3,18 @@ use std::ffi::{CStr, CString};
 use gdk::{
     cairo::{Format, ImageSurface},
     glib::translate::{FromGlibPtrNone, ToGlibPtr, ToGlibPtrMut},
-    Atom, Event, EventType,
+    Atom, Display, Event, EventType,
 };
 use gdk_sys::{gdk_atom_intern, gdk_atom_name, GdkAtom};
 use glib_sys::GFALSE;
-use gtk::{TargetEntry, TargetList};
+use gtk::{Clipboard, TargetEntry, TargetList};
 use gtk_sys::{gtk_target_table_new_from_list, gtk_targets_include_text};
 
-use crate::api_model::ImageData;
-use crate::error::{NativeExtensionsError::OtherError, NativeExtensionsResult};
+use crate::api_model::{ImageData, PixelFormat};
+use crate::error::{
+    NativeExtensionsError::{self, OtherError},
+    NativeExtensionsResult,
+};
 
 // Use gtk function to set/retrieve text (there are multiple possible format,
 // we don't want to mess with that)
@@ -21,6 +24,16 @@ pub const TYPE_TEXT: &str = "text/plain";
 // URI list, when reading URI list is split into multiple items.
 pub const TYPE_URI: &str = "text/uri-list";
 
+// HTML is normalized to UTF-8 both when reading and writing; Some applications
+// (i.e. Firefox) use UTF-16.
+pub const TYPE_HTML: &str = "text/html";
+
+// File manager clipboard formats telling whether the files were copied or
+// cut. GNOME format contains "copy" or "cut" followed by URIs, KDE format
+// contains "1" for cut files.
+pub const TYPE_GNOME_COPIED_FILES: &str = "x-special/gnome-copied-files";
+pub const TYPE_KDE_CUT_SELECTION: &str = "application/x-kde-cutselection";
+
 pub trait AtomExt {
     fn from_string(s: &str) -> GdkAtom;
     fn to_string(&self) -> String;
@@ -65,26 +78,14 @@ impl TargetListExt for TargetList {
 pub fn surface_from_image_data(image: ImageData, opacity: f64) -> ImageSurface {
     let factor: i32 = (opacity * 255.0) as i32;
 
+    // Cairo ARGB32 is premultiplied native endian ARGB.
+    let image = image.into_format(PixelFormat::BgraPremultiplied);
     let mut data = image.data;
-    for offset in (0..data.len()).step_by(4) {
-        let (r, g, b, a) = (
-            data[offset],
-            data[offset + 1],
-            data[offset + 2],
-            data[offset + 3],
-        );
-
-        let a = if factor == 255 {
-            a
-        } else {
-            (a as i32 * factor / 255) as u8
-        };
-
-        // Premultiply alpha
-        data[offset] = (b as i32 * a as i32 / 255) as u8;
-        data[offset + 1] = (g as i32 * a as i32 / 255) as u8;
-        data[offset + 2] = (r as i32 * a as i32 / 255) as u8;
-        data[offset + 3] = a;
+    if factor != 255 {
+        // Opacity applies to all channels of premultiplied pixel.
+        for c in data.iter_mut() {
+            *c = (*c as i32 * factor / 255) as u8;
+        }
     }
     let surface = ImageSurface::create_for_data(
         data,
@@ -101,6 +102,18 @@ pub fn surface_from_image_data(image: ImageData, opacity: f64) -> ImageSurface {
     res
 }
 
+/// Returns clipboard for default display. Fails when there is no default
+/// display, which usually means that GTK was not initialized.
+pub(super) fn default_clipboard() -> NativeExtensionsResult<Clipboard> {
+    let gdk_error = |message: &str| NativeExtensionsError::SystemError {
+        domain: "gdk",
+        code: None,
+        message: message.into(),
+    };
+    let display = Display::default().ok_or_else(|| gdk_error("default display not found"))?;
+    Clipboard::default(&display).ok_or_else(|| gdk_error("default clipboard not found"))
+}
+
 pub(super) fn synthesize_button_up(event: &Event) -> NativeExtensionsResult<Event> {
     if event.event_type() != EventType::ButtonPress
         && event.event_type() != EventType::DoubleButtonPress
@@ -2,24 +2,49 @@ use std::{
     cell::RefCell,
     collections::HashMap,
     rc::{Rc, Weak},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
-use gdk::{Atom, Display};
+use gdk::Atom;
 
-use gtk::{Clipboard, SelectionData, TargetList};
+use gtk::{SelectionData, TargetList};
 use irondash_message_channel::{IsolateId, Late};
 use irondash_run_loop::RunLoop;
 
 use crate::{
-    api_model::{DataProvider, DataProviderValueId, DataRepresentation},
+    api_model::{ClipboardIntent, DataProvider, DataProviderValueId, DataRepresentation},
     data_provider_manager::{DataProviderHandle, PlatformDataProviderDelegate},
-    error::{NativeExtensionsError, NativeExtensionsResult},
+    error::NativeExtensionsResult,
+    html_codec::encode_html,
     log::OkLog,
-    value_coerce::{CoerceToData, StringFormat},
+    uri_list::{is_file_uri, parse_uri_list, serialize_gnome_copied_files, serialize_uri_list},
+    value_cbor::value_for_format,
+    value_coerce::{decode_text, CoerceToData, StringFormat},
 };
 
-use super::common::{target_includes_text, TargetListExt, TYPE_TEXT, TYPE_URI};
+use super::{
+    common::{
+        default_clipboard, target_includes_text, TargetListExt, TYPE_GNOME_COPIED_FILES, TYPE_HTML,
+        TYPE_KDE_CUT_SELECTION, TYPE_TEXT, TYPE_URI,
+    },
+    image_transcode::{transcode_image, ImageFormat},
+    item_container::{encode_items, item_target, parse_item_target, ContainerItem, TYPE_ITEMS},
+};
+
+/// Image formats offered in addition to the image representation provided
+/// by Dart, for the benefit of applications that do not understand PNG.
+#[cfg(feature = "image")]
+const EXPORTED_IMAGE_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Png,
+    ImageFormat::Bmp,
+    ImageFormat::Jpeg,
+    ImageFormat::Tiff,
+];
+#[cfg(not(feature = "image"))]
+const EXPORTED_IMAGE_FORMATS: &[ImageFormat] = &[];
 
 pub fn platform_stream_write(_handle: i32, _data: &[u8]) -> i32 {
     0
@@ -27,6 +52,17 @@ pub fn platform_stream_write(_handle: i32, _data: &[u8]) -> i32 {
 
 pub fn platform_stream_close(_handle: i32, _delete: bool) {}
 
+static CACHED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Data object last set to clipboard. Alive while GTK keeps it.
+    static CLIPBOARD_OWNER: RefCell<Weak<DataObject>> = RefCell::new(Weak::new());
+}
+
+pub fn platform_provider_cache_bytes() -> Option<usize> {
+    Some(CACHED_BYTES.load(Ordering::Relaxed))
+}
+
 pub struct PlatformDataProvider {
     weak_self: Late<Weak<Self>>,
     delegate: Weak<dyn PlatformDataProviderDelegate>,
@@ -52,6 +88,18 @@ impl PlatformDataProvider {
         self.weak_self.set(weak_self);
     }
 
+    /// Clears clipboard if it still holds data written by this plugin,
+    /// releasing the data providers.
+    pub fn release_clipboard() {
+        let owner = CLIPBOARD_OWNER.with(|o| o.take());
+        if owner.upgrade().is_none() {
+            return;
+        }
+        if let Ok(clipboard) = default_clipboard() {
+            clipboard.clear();
+        }
+    }
+
     pub async fn write_to_clipboard(
         providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
     ) -> NativeExtensionsResult<()> {
@@ -60,6 +108,54 @@ impl PlatformDataProvider {
     }
 }
 
+/// Returns first representation with image format that can be decoded.
+fn image_source(data: &DataProvider) -> Option<(&DataRepresentation, ImageFormat)> {
+    data.representations
+        .iter()
+        .filter(|r| !r.is_virtual_file())
+        .find_map(|r| {
+            ImageFormat::from_mime_type(r.format())
+                .filter(|f| *f != ImageFormat::Xpm)
+                .map(|f| (r, f))
+        })
+}
+
+/// Image formats that are not provided by Dart but can be converted from
+/// the image representation.
+fn exported_image_formats(data: &DataProvider) -> Vec<ImageFormat> {
+    if image_source(data).is_none() {
+        return Vec::new();
+    }
+    EXPORTED_IMAGE_FORMATS
+        .iter()
+        .filter(|f| {
+            !data
+                .representations
+                .iter()
+                .any(|r| !r.is_virtual_file() && r.format() == f.mime_type())
+        })
+        .copied()
+        .collect()
+}
+
+impl PlatformDataProvider {
+    fn formats(&self) -> Vec<&str> {
+        self.data
+            .representations
+            .iter()
+            .filter(|r| !r.is_virtual_file())
+            .map(|r| r.format())
+            .collect()
+    }
+
+    fn has_format(&self, format: &str) -> bool {
+        self.data
+            .representations
+            .iter()
+            .any(|r| !r.is_virtual_file() && r.format() == format)
+    }
+}
+
 struct ProviderEntry {
     provider: Rc<PlatformDataProvider>,
     _handle: Arc<DataProviderHandle>,
@@ -68,6 +164,24 @@ struct ProviderEntry {
 pub struct DataObject {
     providers: Vec<ProviderEntry>,
     cache: RefCell<HashMap<DataProviderValueId, Option<Vec<u8>>>>,
+    /// Images converted on demand from lazy image representations, keyed by
+    /// the source value. Simple representations are already in memory and
+    /// are converted on every request.
+    exported_images: RefCell<HashMap<(DataProviderValueId, ImageFormat), Option<Vec<u8>>>>,
+}
+
+impl Drop for DataObject {
+    fn drop(&mut self) {
+        let cached: usize = self
+            .cache
+            .get_mut()
+            .values()
+            .chain(self.exported_images.get_mut().values())
+            .flatten()
+            .map(|d| d.len())
+            .sum();
+        CACHED_BYTES.fetch_sub(cached, Ordering::Relaxed);
+    }
 }
 
 impl DataObject {
@@ -81,28 +195,59 @@ impl DataObject {
                 })
                 .collect(),
             cache: RefCell::new(HashMap::new()),
+            exported_images: RefCell::new(HashMap::new()),
         })
     }
 
     fn set_data_(selection_data: &SelectionData, data: &[u8]) -> NativeExtensionsResult<()> {
         let target = selection_data.target();
         if target_includes_text(&target) {
-            selection_data.set_text(
-                std::str::from_utf8(data)
-                    .map_err(|e| NativeExtensionsError::OtherError(e.to_string()))?,
-            );
+            selection_data.set_text(&decode_text(data, None));
         } else {
             selection_data.set(&target, 8, data);
         }
         Ok(())
     }
 
-    fn get_data_for_item(&self, item: &PlatformDataProvider, ty: &str) -> Option<Vec<u8>> {
+    fn get_data_for_item(&self, item: &ProviderEntry, ty: &str) -> Option<Vec<u8>> {
+        let provider = &item.provider;
+        if provider.has_format(ty) {
+            return self.get_data_for_representation(provider, ty);
+        }
+        let target = ImageFormat::from_mime_type(ty)
+            .filter(|f| exported_image_formats(&provider.data).contains(f))?;
+        let (source, source_format) = image_source(&provider.data)?;
+        let key = match source {
+            DataRepresentation::Lazy { id, format: _ } => Some((*id, target)),
+            _ => None,
+        };
+        if let Some(cached) = key.and_then(|key| self.exported_images.borrow().get(&key).cloned()) {
+            return cached;
+        }
+        let data = self
+            .get_data_for_representation(provider, source.format())
+            .and_then(|data| transcode_image(&data, source_format, target).ok_log());
+        if let Some(key) = key {
+            CACHED_BYTES.fetch_add(
+                data.as_ref().map(|d| d.len()).unwrap_or(0),
+                Ordering::Relaxed,
+            );
+            self.exported_images.borrow_mut().insert(key, data.clone());
+        }
+        data
+    }
+
+    fn get_data_for_representation(
+        &self,
+        item: &PlatformDataProvider,
+        ty: &str,
+    ) -> Option<Vec<u8>> {
         for data in &item.data.representations {
             match data {
                 DataRepresentation::Simple { format, data } => {
                     if format == ty {
-                        return data.coerce_to_data(StringFormat::Utf8);
+                        return value_for_format(data, ty)
+                            .coerce_to_data(StringFormat::for_mime_type(ty));
                     }
                 }
                 DataRepresentation::Lazy { format, id } => {
@@ -116,7 +261,12 @@ impl DataObject {
                                 if let Some(result) = promise.try_take() {
                                     match result {
                                         crate::value_promise::ValuePromiseResult::Ok { value } => {
-                                            let data = value.coerce_to_data(StringFormat::Utf8);
+                                            let data = value_for_format(&value, ty)
+                                                .coerce_to_data(StringFormat::for_mime_type(ty));
+                                            CACHED_BYTES.fetch_add(
+                                                data.as_ref().map(|d| d.len()).unwrap_or(0),
+                                                Ordering::Relaxed,
+                                            );
                                             self.cache.borrow_mut().insert(*id, data.clone());
                                             return data;
                                         }
@@ -136,6 +286,73 @@ impl DataObject {
         None
     }
 
+    /// URIs from all items.
+    fn get_uris(&self) -> Vec<String> {
+        self.providers
+            .iter()
+            .filter_map(|item| self.get_data_for_item(item, TYPE_URI))
+            .flat_map(|data| parse_uri_list(&data))
+            .collect()
+    }
+
+    /// Whether file managers may be able to paste the items. Only URI lists
+    /// provided upfront are inspected; Lazy URI lists are not resolved when
+    /// advertising targets, non-file URIs are filtered out when the data
+    /// is requested.
+    fn may_have_file_uris(&self) -> bool {
+        self.providers.iter().any(|item| {
+            item.provider
+                .data
+                .representations
+                .iter()
+                .any(|repr| match repr {
+                    DataRepresentation::Simple { format, data } if format == TYPE_URI => {
+                        value_for_format(data, TYPE_URI)
+                            .coerce_to_data(StringFormat::Utf8)
+                            .map(|data| parse_uri_list(&data).iter().any(|uri| is_file_uri(uri)))
+                            .unwrap_or(false)
+                    }
+                    DataRepresentation::Lazy { format, id: _ } => format == TYPE_URI,
+                    _ => false,
+                })
+        })
+    }
+
+    /// Intent of the first item that specifies one.
+    fn clipboard_intent(&self) -> ClipboardIntent {
+        self.providers
+            .iter()
+            .find_map(|item| item.provider.data.clipboard_intent)
+            .unwrap_or(ClipboardIntent::Copy)
+    }
+
+    /// Describes all items so that other instances of this plugin can read
+    /// them back as separate items. Data itself is served from item targets.
+    fn get_items_container(&self) -> Option<Vec<u8>> {
+        let items: Vec<_> = self
+            .providers
+            .iter()
+            .map(|item| ContainerItem {
+                suggested_name: item.provider.data.suggested_name.clone(),
+                formats: Self::item_formats(item),
+            })
+            .collect();
+        encode_items(&items).ok_log()
+    }
+
+    fn item_formats(item: &ProviderEntry) -> Vec<String> {
+        item.provider
+            .formats()
+            .into_iter()
+            .map(|f| f.to_owned())
+            .chain(
+                exported_image_formats(&item.provider.data)
+                    .into_iter()
+                    .map(|f| f.mime_type().to_owned()),
+            )
+            .collect()
+    }
+
     pub fn get_data(&self, selection_data: &SelectionData) -> NativeExtensionsResult<()> {
         let target = selection_data.target();
         let is_text = target_includes_text(&target);
@@ -147,19 +364,32 @@ impl DataObject {
         };
         if target == TYPE_URI {
             // merge URIs from all items
-            let mut data = Vec::<u8>::new();
-            for item in &self.providers {
-                if let Some(item_data) = self.get_data_for_item(&item.provider, &target) {
-                    data.extend_from_slice(&item_data);
-                    data.push(b'\r');
-                    data.push(b'\n');
-                }
-            }
+            Self::set_data_(selection_data, &serialize_uri_list(&self.get_uris()))?;
+        } else if target == TYPE_GNOME_COPIED_FILES {
+            let data = serialize_gnome_copied_files(self.clipboard_intent(), &self.get_uris());
             Self::set_data_(selection_data, &data)?;
-        } else if let Some(item) = self.providers.first() {
-            if let Some(data) = self.get_data_for_item(&item.provider, &target) {
+        } else if target == TYPE_KDE_CUT_SELECTION {
+            Self::set_data_(selection_data, b"1")?;
+        } else if target == TYPE_ITEMS {
+            if let Some(data) = self.get_items_container() {
+                Self::set_data_(selection_data, &data)?;
+            }
+        } else if let Some((index, format)) = parse_item_target(&target) {
+            let data = self
+                .providers
+                .get(index)
+                .and_then(|item| self.get_data_for_item(item, format));
+            if let Some(data) = data {
                 Self::set_data_(selection_data, &data)?;
             }
+        } else if let Some(item) = self.providers.first() {
+            if let Some(data) = self.get_data_for_item(item, &target) {
+                if target == TYPE_HTML {
+                    Self::set_data_(selection_data, &encode_html(&data))?;
+                } else {
+                    Self::set_data_(selection_data, &data)?;
+                }
+            }
         }
         Ok(())
     }
@@ -168,14 +398,12 @@ impl DataObject {
         unsafe { gtk::set_initialized() };
         let list = self.create_target_list();
         let targets = list.get_target_entries();
-        let display = Display::default()
-            .ok_or_else(|| NativeExtensionsError::OtherError("Display not found".into()))?;
-        let clipboard = Clipboard::default(&display)
-            .ok_or_else(|| NativeExtensionsError::OtherError("Clipboard not found".into()))?;
+        let clipboard = default_clipboard()?;
         let self_clone = self.clone();
         clipboard.set_with_data(&targets, move |_, selection_data, _| {
             self_clone.get_data(selection_data).ok_log();
         });
+        CLIPBOARD_OWNER.with(|o| o.replace(Rc::downgrade(self)));
         Ok(())
     }
 
@@ -200,7 +428,213 @@ impl DataObject {
                     _ => {}
                 }
             }
+            for format in exported_image_formats(&item.provider.data) {
+                add(&list, format.mime_type());
+            }
+        }
+        if self.providers.len() > 1 {
+            add(&list, TYPE_ITEMS);
+            for (index, item) in self.providers.iter().enumerate() {
+                for format in Self::item_formats(item) {
+                    add(&list, &item_target(index, &format));
+                }
+            }
+        }
+        if self.may_have_file_uris() {
+            // Lets file managers paste the files; URIs are merged from all
+            // items, same as for text/uri-list.
+            add(&list, TYPE_GNOME_COPIED_FILES);
+            if self.clipboard_intent() == ClipboardIntent::Cut {
+                add(&list, TYPE_KDE_CUT_SELECTION);
+            }
         }
         list
     }
 }
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::HashSet,
+        rc::{Rc, Weak},
+        sync::Arc,
+    };
+
+    use async_trait::async_trait;
+    use irondash_message_channel::{IsolateId, Value};
+    use irondash_run_loop::util::DropNotifier;
+
+    use super::{
+        exported_image_formats, image_source, DataObject, ImageFormat, PlatformDataProvider,
+    };
+    use crate::{
+        api_model::{DataProvider, DataProviderValueId, DataRepresentation},
+        data_provider_manager::{
+            DataProviderHandle, PlatformDataProviderDelegate, VirtualFileResult,
+            VirtualSessionHandle,
+        },
+        platform_impl::platform::image_transcode::{decode_image, encode_image},
+        value_promise::{ValuePromise, ValuePromiseResult, ValuePromiseSetCancel},
+    };
+
+    fn provider(representations: Vec<DataRepresentation>) -> DataProvider {
+        DataProvider {
+            representations,
+            suggested_name: None,
+            clipboard_intent: None,
+        }
+    }
+
+    fn simple(format: &str) -> DataRepresentation {
+        DataRepresentation::Simple {
+            format: format.into(),
+            data: Value::Null,
+        }
+    }
+
+    fn lazy(format: &str, id: i64) -> DataRepresentation {
+        DataRepresentation::Lazy {
+            format: format.into(),
+            id: id.into(),
+        }
+    }
+
+    fn png(width: u32) -> Vec<u8> {
+        let image = image::DynamicImage::new_rgba8(width, 1);
+        encode_image(&image, ImageFormat::Png).unwrap()
+    }
+
+    #[test]
+    fn test_image_source() {
+        assert_eq!(image_source(&provider(vec![simple("text/plain")])), None);
+        // XPM can be decoded but is not worth converting from.
+        let data = provider(vec![
+            simple("text/plain"),
+            simple("image/x-xpixmap"),
+            lazy("image/jpeg", 1),
+            simple("image/png"),
+        ]);
+        assert_eq!(
+            image_source(&data),
+            Some((&data.representations[2], ImageFormat::Jpeg))
+        );
+        let data = provider(vec![DataRepresentation::VirtualFile {
+            id: 1.into(),
+            format: "image/png".into(),
+            storage_suggestion: None,
+        }]);
+        assert_eq!(image_source(&data), None);
+    }
+
+    #[test]
+    fn test_exported_image_formats() {
+        assert_eq!(
+            exported_image_formats(&provider(vec![simple("text/plain")])),
+            vec![]
+        );
+        assert_eq!(
+            exported_image_formats(&provider(vec![lazy("image/png", 1)])),
+            vec![ImageFormat::Bmp, ImageFormat::Jpeg, ImageFormat::Tiff]
+        );
+        assert_eq!(
+            exported_image_formats(&provider(vec![simple("image/tiff"), simple("image/jpeg")])),
+            vec![ImageFormat::Png, ImageFormat::Bmp]
+        );
+    }
+
+    /// Serves PNG images of given width for lazy values and records requests.
+    struct TestDelegate {
+        widths: Vec<(DataProviderValueId, u32)>,
+        requests: RefCell<Vec<DataProviderValueId>>,
+    }
+
+    #[async_trait(?Send)]
+    impl PlatformDataProviderDelegate for TestDelegate {
+        fn get_lazy_data(
+            &self,
+            _isolate_id: IsolateId,
+            data_id: DataProviderValueId,
+            _on_done: Option<Box<dyn FnOnce()>>,
+        ) -> Arc<ValuePromise> {
+            self.requests.borrow_mut().push(data_id);
+            let promise = Arc::new(ValuePromise::new());
+            let width = self.widths.iter().find(|w| w.0 == data_id).unwrap().1;
+            promise.set_value(png(width).into());
+            promise
+        }
+
+        async fn get_lazy_data_async(
+            &self,
+            _isolate_id: IsolateId,
+            _data_id: DataProviderValueId,
+        ) -> ValuePromiseResult {
+            panic!("not used by this test")
+        }
+
+        fn get_virtual_file(
+            &self,
+            _isolate_id: IsolateId,
+            _virtual_file_id: DataProviderValueId,
+            _stream_handle: i32,
+            _on_size_known: Box<dyn Fn(Option<i64>)>,
+            _on_progress: Box<dyn Fn(f64)>,
+            _on_done: Box<dyn FnOnce(VirtualFileResult)>,
+        ) -> Arc<VirtualSessionHandle> {
+            panic!("not used by this test")
+        }
+    }
+
+    #[test]
+    fn test_exported_images_cached_per_value() {
+        let delegate = Rc::new(TestDelegate {
+            widths: vec![(1.into(), 2), (2.into(), 3)],
+            requests: RefCell::new(Vec::new()),
+        });
+        let weak_delegate: Weak<dyn PlatformDataProviderDelegate> = Rc::downgrade(&delegate);
+        // Third item shares the value with the first one.
+        let data_object = DataObject::new(
+            [1, 2, 1]
+                .into_iter()
+                .map(|id| {
+                    let provider = PlatformDataProvider::new(
+                        weak_delegate.clone(),
+                        IsolateId(1),
+                        provider(vec![lazy("image/png", id)]),
+                    );
+                    let handle: Arc<DataProviderHandle> = Arc::new(DropNotifier::new(|| {}).into());
+                    (Rc::new(provider), handle)
+                })
+                .collect(),
+        );
+        let width = |item: usize, format: ImageFormat| {
+            let data = data_object
+                .get_data_for_item(&data_object.providers[item], format.mime_type())
+                .unwrap();
+            decode_image(&data, format).unwrap().width()
+        };
+        assert_eq!(width(0, ImageFormat::Bmp), 2);
+        assert_eq!(width(1, ImageFormat::Bmp), 3);
+        assert_eq!(width(0, ImageFormat::Tiff), 2);
+        assert_eq!(width(2, ImageFormat::Bmp), 2);
+        assert_eq!(width(1, ImageFormat::Bmp), 3);
+
+        // Source value is requested once, conversions are done once per
+        // value and format.
+        assert_eq!(*delegate.requests.borrow(), vec![1.into(), 2.into()]);
+        let keys: HashSet<_> = data_object
+            .exported_images
+            .borrow()
+            .keys()
+            .copied()
+            .collect();
+        assert_eq!(
+            keys,
+            HashSet::from([
+                (1.into(), ImageFormat::Bmp),
+                (1.into(), ImageFormat::Tiff),
+                (2.into(), ImageFormat::Bmp),
+            ])
+        );
+    }
+}
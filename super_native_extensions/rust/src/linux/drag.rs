@@ -21,6 +21,7 @@ use irondash_run_loop::RunLoop;
 
 use crate::{
     api_model::{DataProviderId, DragConfiguration, DragRequest, DropOperation, Point},
+    drag_image::{combine_drag_images, CombinedImageStyle},
     drag_manager::{
         DataProviderEntry, DragSessionId, PlatformDragContextDelegate, PlatformDragContextId,
     },
@@ -217,7 +218,7 @@ impl PlatformDragContext {
             .borrow()
             .as_ref()
             .cloned()
-            .ok_or_else(|| NativeExtensionsError::OtherError("Missing mouse event".into()))?;
+            .ok_or(NativeExtensionsError::MouseEventNotFound)?;
 
         // release event will get eaten
         let mut release = synthesize_button_up(&event)?;
@@ -237,16 +238,23 @@ impl PlatformDragContext {
             request.position.y as i32,
         );
         if let Some(context) = context {
-            if let Some(image) = request.combined_drag_image {
-                let image = image.with_shadow(10);
-                let scale = image.image_data.device_pixel_ratio.unwrap_or(1.0);
-                let surface = surface_from_image_data(image.image_data, 0.8);
-                surface.set_device_offset(
-                    (image.rect.x - request.position.x) * scale,
-                    (image.rect.y - request.position.y) * scale,
-                );
-                context.drag_set_icon_surface(&surface)
-            }
+            let image = request.combined_drag_image.unwrap_or_else(|| {
+                combine_drag_images(&request.configuration.items, &CombinedImageStyle::default())
+            });
+            let shadow_style = request.configuration.shadow_style.clone();
+            let image = request
+                .configuration
+                .preview_size_policy
+                .unwrap_or_default()
+                .apply_to_targetted_image(image)
+                .with_shadow_or_default(shadow_style.as_ref());
+            let scale = image.image_data.device_pixel_ratio.unwrap_or(1.0);
+            let surface = surface_from_image_data(image.image_data, 0.8);
+            surface.set_device_offset(
+                (image.rect.x - request.position.x) * scale,
+                (image.rect.y - request.position.y) * scale,
+            );
+            context.drag_set_icon_surface(&surface);
             let session = Session::new(
                 session_id,
                 self.id,
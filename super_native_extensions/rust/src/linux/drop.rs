@@ -31,7 +31,7 @@ use crate::{
 };
 
 use super::{
-    common::{TargetListExt, TYPE_TEXT, TYPE_URI},
+    common::{TargetListExt, TYPE_TEXT},
     drag_common::DropOperationExt,
     PlatformDataReader, WidgetReader,
 };
@@ -173,12 +173,7 @@ impl PlatformDropContext {
             items: (0..number_of_items)
                 .map(|i| DropItem {
                     item_id: (i as i64).into(),
-                    formats: reader_info
-                        .targets
-                        .iter()
-                        .filter(|f| i == 0 || *f == TYPE_URI)
-                        .cloned()
-                        .collect(),
+                    formats: reader_info.item_formats.get(i).cloned().unwrap_or_default(),
                     local_data: local_data.get(i).cloned().unwrap_or(Value::Null),
                 })
                 .collect(),
@@ -0,0 +1,379 @@
+//! Conversion between image formats commonly offered on X11 clipboard.
+//! Many X11 applications only offer BMP, TIFF, JPEG or XPM images while Dart
+//! expects PNG.
+//!
+//! Image codecs are only compiled in with subsystems that transfer data
+//! (the `image` dependency is enabled by `clipboard` and `drag-drop`).
+
+use crate::error::{NativeExtensionsError, NativeExtensionsResult};
+
+#[cfg(feature = "image")]
+use {
+    crate::{
+        format_converter::FormatConverter,
+        value_coerce::{CoerceToData, StringFormat},
+    },
+    async_trait::async_trait,
+    image::{DynamicImage, ImageOutputFormat, RgbaImage},
+    irondash_message_channel::Value,
+    irondash_run_loop::{
+        util::{Capsule, FutureCompleter},
+        RunLoop,
+    },
+    std::{
+        collections::HashMap,
+        io::{self, Cursor},
+        thread,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Bmp,
+    Tiff,
+    Jpeg,
+    Xpm,
+}
+
+impl ImageFormat {
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "image/png" => Some(Self::Png),
+            "image/bmp" | "image/x-bmp" | "image/x-MS-bmp" => Some(Self::Bmp),
+            "image/tiff" => Some(Self::Tiff),
+            "image/jpeg" => Some(Self::Jpeg),
+            "image/x-xpixmap" => Some(Self::Xpm),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Bmp => "image/bmp",
+            Self::Tiff => "image/tiff",
+            Self::Jpeg => "image/jpeg",
+            Self::Xpm => "image/x-xpixmap",
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for NativeExtensionsError {
+    fn from(error: image::ImageError) -> Self {
+        if let image::ImageError::IoError(error) = error {
+            return error.into();
+        }
+        let kind = match &error {
+            image::ImageError::Unsupported(_) => io::ErrorKind::Unsupported,
+            image::ImageError::Limits(_) => io::ErrorKind::OutOfMemory,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, error).into()
+    }
+}
+
+#[cfg(feature = "image")]
+pub fn decode_image(data: &[u8], format: ImageFormat) -> NativeExtensionsResult<DynamicImage> {
+    let format = match format {
+        ImageFormat::Png => image::ImageFormat::Png,
+        ImageFormat::Bmp => image::ImageFormat::Bmp,
+        ImageFormat::Tiff => image::ImageFormat::Tiff,
+        ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+        ImageFormat::Xpm => return decode_xpm(data).map(DynamicImage::ImageRgba8),
+    };
+    Ok(image::load_from_memory_with_format(data, format)?)
+}
+
+#[cfg(feature = "image")]
+pub fn encode_image(image: &DynamicImage, format: ImageFormat) -> NativeExtensionsResult<Vec<u8>> {
+    let mut res = Vec::new();
+    let mut cursor = Cursor::new(&mut res);
+    match format {
+        ImageFormat::Png => image.write_to(&mut cursor, ImageOutputFormat::Png)?,
+        ImageFormat::Bmp => image.write_to(&mut cursor, ImageOutputFormat::Bmp)?,
+        ImageFormat::Tiff => image.write_to(&mut cursor, ImageOutputFormat::Tiff)?,
+        // JPEG has no alpha channel.
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut cursor, ImageOutputFormat::Jpeg(90))?,
+        ImageFormat::Xpm => return Err(NativeExtensionsError::UnsupportedOperation),
+    }
+    Ok(res)
+}
+
+#[cfg(feature = "image")]
+pub fn transcode_image(
+    data: &[u8],
+    from: ImageFormat,
+    to: ImageFormat,
+) -> NativeExtensionsResult<Vec<u8>> {
+    encode_image(&decode_image(data, from)?, to)
+}
+
+/// No formats are exported without image codecs, so this is only here to
+/// keep callers free of feature checks.
+#[cfg(not(feature = "image"))]
+pub fn transcode_image(
+    _data: &[u8],
+    _from: ImageFormat,
+    _to: ImageFormat,
+) -> NativeExtensionsResult<Vec<u8>> {
+    Err(NativeExtensionsError::UnsupportedOperation)
+}
+
+#[cfg(feature = "image")]
+/// Synthesizes PNG from other image formats.
+pub struct ImageToPng {
+    source_formats: Vec<String>,
+}
+
+#[cfg(feature = "image")]
+impl ImageToPng {
+    pub fn new() -> Self {
+        Self {
+            source_formats: [
+                "image/bmp",
+                "image/x-bmp",
+                "image/x-MS-bmp",
+                "image/tiff",
+                "image/jpeg",
+                "image/x-xpixmap",
+            ]
+            .iter()
+            .map(|f| f.to_string())
+            .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+#[async_trait(?Send)]
+impl FormatConverter for ImageToPng {
+    fn target_format(&self) -> &str {
+        "image/png"
+    }
+
+    fn source_formats(&self) -> &[String] {
+        &self.source_formats
+    }
+
+    async fn convert(&self, source_format: &str, data: Value) -> NativeExtensionsResult<Value> {
+        let format = ImageFormat::from_mime_type(source_format)
+            .ok_or(NativeExtensionsError::UnsupportedOperation)?;
+        let data = data
+            .coerce_to_data(StringFormat::Utf8)
+            .ok_or(NativeExtensionsError::InvalidData)?;
+
+        let (future, completer) = FutureCompleter::new();
+        let mut completer = Capsule::new(completer);
+        let sender = RunLoop::current().new_sender();
+
+        // Decoding large images takes a while; Keep it off the main thread.
+        thread::spawn(move || {
+            let res = transcode_image(&data, format, ImageFormat::Png);
+            sender.send(move || {
+                let completer = completer.take().unwrap();
+                completer.complete(res);
+            });
+        });
+
+        Ok(future.await?.into())
+    }
+}
+
+#[cfg(feature = "image")]
+fn parse_xpm_color(color: &str) -> Option<[u8; 4]> {
+    if color.eq_ignore_ascii_case("none") {
+        return Some([0, 0, 0, 0]);
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        // #RGB, #RRGGBB or #RRRRGGGGBBBB; Use most significant byte of each
+        // component.
+        let len = hex.len() / 3;
+        if len == 0 || hex.len() % 3 != 0 || !hex.is_ascii() {
+            return None;
+        }
+        let component = |i: usize| {
+            let c = u16::from_str_radix(&hex[i * len..(i + 1) * len], 16).ok()?;
+            Some(match len {
+                1 => (c * 17) as u8,
+                2 => c as u8,
+                n => (c >> ((n - 2) * 4)) as u8,
+            })
+        };
+        return Some([component(0)?, component(1)?, component(2)?, 255]);
+    }
+    let rgb = match color.to_ascii_lowercase().replace(' ', "").as_str() {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "gray" | "grey" => [190, 190, 190],
+        _ => return None,
+    };
+    Some([rgb[0], rgb[1], rgb[2], 255])
+}
+
+#[cfg(feature = "image")]
+/// Maximum width or height of decoded XPM image. XPM is text format so
+/// anything close to this would be megabytes of data anyway.
+const MAX_XPM_DIMENSION: usize = 16384;
+
+#[cfg(feature = "image")]
+/// Decodes XPM3 image. Only colors for color visual (`c` key) are used.
+pub fn decode_xpm(data: &[u8]) -> NativeExtensionsResult<RgbaImage> {
+    let text = String::from_utf8_lossy(data);
+    // All relevant content is inside C string literals.
+    let strings: Vec<&str> = text.split('"').skip(1).step_by(2).collect();
+    let invalid = || -> NativeExtensionsError {
+        io::Error::new(io::ErrorKind::InvalidData, "invalid XPM image").into()
+    };
+
+    let mut header = strings.first().ok_or_else(invalid)?.split_whitespace();
+    let mut next = || -> NativeExtensionsResult<usize> {
+        header
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let (width, height, num_colors, chars_per_pixel) = (next()?, next()?, next()?, next()?);
+    if chars_per_pixel == 0 || width > MAX_XPM_DIMENSION || height > MAX_XPM_DIMENSION {
+        return Err(invalid());
+    }
+    // Header, color definitions and pixel rows must all be present.
+    let colors_end = num_colors.checked_add(1).ok_or_else(invalid)?;
+    let rows_end = colors_end.checked_add(height).ok_or_else(invalid)?;
+    let row_len = width.checked_mul(chars_per_pixel).ok_or_else(invalid)?;
+    if strings.len() < rows_end {
+        return Err(invalid());
+    }
+
+    const KEYS: &[&str] = &["c", "m", "g", "g4", "s"];
+    let mut colors = HashMap::new();
+    for line in &strings[1..colors_end] {
+        let chars = line.get(..chars_per_pixel).ok_or_else(invalid)?;
+        // Color definition is list of key value pairs, where value may
+        // contain spaces.
+        let tokens: Vec<&str> = line[chars_per_pixel..].split_whitespace().collect();
+        let mut color = None;
+        let mut i = 0;
+        while i < tokens.len() {
+            let key = tokens[i];
+            let end = tokens[i + 1..]
+                .iter()
+                .position(|t| KEYS.contains(t))
+                .map(|p| i + 1 + p)
+                .unwrap_or(tokens.len());
+            if key == "c" || (color.is_none() && key != "s") {
+                color = parse_xpm_color(&tokens[i + 1..end].join(" "));
+            }
+            i = end;
+        }
+        colors.insert(chars, color.ok_or_else(invalid)?);
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for row in &strings[colors_end..rows_end] {
+        if row.len() < row_len {
+            return Err(invalid());
+        }
+        for x in 0..width {
+            let key = row
+                .get(x * chars_per_pixel..(x + 1) * chars_per_pixel)
+                .ok_or_else(invalid)?;
+            pixels.extend_from_slice(colors.get(key).ok_or_else(invalid)?);
+        }
+    }
+    RgbaImage::from_raw(width as u32, height as u32, pixels).ok_or_else(invalid)
+}
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use std::io;
+
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use crate::error::NativeExtensionsError;
+
+    use super::{decode_image, decode_xpm, encode_image, transcode_image, ImageFormat};
+
+    fn test_image() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 3, |x, y| {
+            Rgba([(x * 60) as u8, (y * 100) as u8, 200, 255])
+        }))
+    }
+
+    #[test]
+    fn test_transcode_to_png() {
+        for format in [ImageFormat::Bmp, ImageFormat::Tiff, ImageFormat::Jpeg] {
+            let data = encode_image(&test_image(), format).unwrap();
+            let png = transcode_image(&data, format, ImageFormat::Png).unwrap();
+            assert!(png.starts_with(b"\x89PNG"));
+            let image = decode_image(&png, ImageFormat::Png).unwrap();
+            assert_eq!((image.width(), image.height()), (4, 3));
+            if format != ImageFormat::Jpeg {
+                assert_eq!(image.to_rgba8(), test_image().to_rgba8());
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_xpm() {
+        let xpm = br#"/* XPM */
+static char * test_xpm[] = {
+"3 2 3 1",
+"  c None",
+". c #FF0000",
+"+ c #00000000FFFF",
+" .+",
+"+. "};"#;
+        let image = decode_xpm(xpm).unwrap();
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+        assert_eq!(image.get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(2, 0), &Rgba([0, 0, 255, 255]));
+        assert_eq!(image.get_pixel(0, 1), &Rgba([0, 0, 255, 255]));
+
+        let png = transcode_image(xpm, ImageFormat::Xpm, ImageFormat::Png).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert!(decode_xpm(b"\"1 1 1 1\"").is_err());
+    }
+
+    fn assert_invalid_data(xpm: &[u8]) {
+        match decode_xpm(xpm) {
+            Err(NativeExtensionsError::IOError(e)) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData)
+            }
+            other => panic!("unexpected result {:?}", other.map(|i| i.dimensions())),
+        }
+    }
+
+    #[test]
+    fn test_decode_xpm_malformed() {
+        assert_invalid_data(b"");
+        assert_invalid_data(b"\"3 2\"");
+        assert_invalid_data(b"\"a b c d\"");
+        assert_invalid_data(b"\"1 1 1 0\", \". c red\", \".\"");
+        // Missing pixel row.
+        assert_invalid_data(b"\"1 2 1 1\", \". c red\", \".\"");
+        // Short pixel row.
+        assert_invalid_data(b"\"2 1 1 1\", \". c red\", \".\"");
+        // Unknown color.
+        assert_invalid_data(b"\"1 1 1 1\", \". c red\", \"x\"");
+    }
+
+    #[test]
+    fn test_decode_xpm_huge_header() {
+        let max = usize::MAX.to_string();
+        assert_invalid_data(format!("\"1 1 {max} 1\"").as_bytes());
+        assert_invalid_data(format!("\"1 {max} 1 1\"").as_bytes());
+        assert_invalid_data(format!("\"{max} 1 1 {max}\"").as_bytes());
+        assert_invalid_data(format!("\"2 1 1 {max}\"").as_bytes());
+        assert_invalid_data(b"\"100000 100000 1 1\", \". c red\"");
+    }
+}
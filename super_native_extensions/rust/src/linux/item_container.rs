@@ -0,0 +1,152 @@
+//! Container format carrying all items of a clipboard or drag session.
+//! X11 selections only have a single set of targets, so with multiple items
+//! every target except the URI list is served from the first item. When
+//! writing more than one item the data object also offers [`TYPE_ITEMS`],
+//! which instances of this plugin read back as separate items.
+//!
+//! The container is CBOR encoded map with `version` and `items`. Each item
+//! is a map with `name` (suggested name or null) and `formats`. The
+//! container only describes the items so that it is cheap to read on every
+//! drag hover; Data for each item and format is served from separate target
+//! (see [`item_target`]) in the same encoding as the individual targets.
+
+use irondash_message_channel::{Value, ValueTupleList};
+
+use crate::{
+    error::NativeExtensionsResult,
+    log::OkLog,
+    value_cbor::{decode_value, encode_value},
+};
+
+pub const TYPE_ITEMS: &str = "application/x-super-native-extensions-items";
+
+const TYPE_ITEM_PREFIX: &str = "application/x-super-native-extensions-item-";
+
+const VERSION: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerItem {
+    pub suggested_name: Option<String>,
+    pub formats: Vec<String>,
+}
+
+/// Target serving data of given item and format.
+pub fn item_target(item: usize, format: &str) -> String {
+    format!("{TYPE_ITEM_PREFIX}{item}:{format}")
+}
+
+/// Returns item index and format for target created by [`item_target`].
+pub fn parse_item_target(target: &str) -> Option<(usize, &str)> {
+    let (item, format) = target.strip_prefix(TYPE_ITEM_PREFIX)?.split_once(':')?;
+    Some((item.parse().ok()?, format))
+}
+
+fn string_key(key: &str) -> Value {
+    Value::String(key.into())
+}
+
+fn get<'a>(map: &'a ValueTupleList, key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(k, _)| matches!(k, Value::String(k) if k == key))
+        .map(|(_, v)| v)
+}
+
+pub fn encode_items(items: &[ContainerItem]) -> NativeExtensionsResult<Vec<u8>> {
+    let items = items
+        .iter()
+        .map(|item| {
+            let formats = item.formats.iter().cloned().map(Value::String).collect();
+            Value::Map(ValueTupleList::new(vec![
+                (
+                    string_key("name"),
+                    item.suggested_name
+                        .clone()
+                        .map(Value::String)
+                        .unwrap_or(Value::Null),
+                ),
+                (string_key("formats"), Value::List(formats)),
+            ]))
+        })
+        .collect();
+    encode_value(&Value::Map(ValueTupleList::new(vec![
+        (string_key("version"), Value::I64(VERSION)),
+        (string_key("items"), Value::List(items)),
+    ])))
+}
+
+/// Returns items from container, or `None` if the data is not a container
+/// of supported version.
+pub fn decode_items(data: &[u8]) -> Option<Vec<ContainerItem>> {
+    let Value::Map(container) = decode_value(data).ok_log()? else {
+        return None;
+    };
+    if get(&container, "version") != Some(&Value::I64(VERSION)) {
+        return None;
+    }
+    let Some(Value::List(items)) = get(&container, "items") else {
+        return None;
+    };
+    items
+        .iter()
+        .map(|item| {
+            let Value::Map(item) = item else {
+                return None;
+            };
+            let suggested_name = match get(item, "name") {
+                Some(Value::String(name)) => Some(name.clone()),
+                _ => None,
+            };
+            let Some(Value::List(formats)) = get(item, "formats") else {
+                return None;
+            };
+            let formats = formats
+                .iter()
+                .map(|f| match f {
+                    Value::String(format) => Some(format.clone()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(ContainerItem {
+                suggested_name,
+                formats,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_items, encode_items, item_target, parse_item_target, ContainerItem};
+
+    #[test]
+    fn test_round_trip() {
+        let items = vec![
+            ContainerItem {
+                suggested_name: Some("a.txt".into()),
+                formats: vec!["text/plain".into(), "application/x-custom".into()],
+            },
+            ContainerItem {
+                suggested_name: None,
+                formats: vec!["text/uri-list".into()],
+            },
+        ];
+        let encoded = encode_items(&items).unwrap();
+        assert_eq!(decode_items(&encoded).unwrap(), items);
+        assert_eq!(decode_items(b"text"), None);
+    }
+
+    #[test]
+    fn test_item_target() {
+        let target = item_target(12, "application/x-custom;charset=utf-8");
+        assert_eq!(
+            parse_item_target(&target),
+            Some((12, "application/x-custom;charset=utf-8"))
+        );
+        assert_eq!(parse_item_target(&item_target(0, "a:b")), Some((0, "a:b")));
+        assert_eq!(parse_item_target("text/plain"), None);
+        assert_eq!(
+            parse_item_target("application/x-super-native-extensions-item-x:text/plain"),
+            None
+        );
+    }
+}
@@ -7,23 +7,51 @@ use std::{
     sync::Arc,
 };
 
-use gdk::{glib::SignalHandlerId, prelude::ObjectExt, Atom, Display, DragContext};
+use gdk::{
+    glib::{self, SignalHandlerId},
+    prelude::ObjectExt,
+    Atom, DragContext,
+};
 use gtk::{traits::WidgetExt, Clipboard, SelectionData, Widget};
 
 use irondash_message_channel::{Late, Value};
 use irondash_run_loop::{spawn, util::FutureCompleter};
-use url::Url;
 
 use crate::{
+    api_model::ClipboardIntent,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::{FormatConverter, HtmlToText, RtfToText},
+    html_codec::decode_html,
     reader_manager::{ReadProgress, VirtualFileReader},
+    uri_list::{
+        file_uri_to_path, parse_gnome_copied_files, parse_uri_list, sanitize_file_name,
+        suggested_name_for_uri,
+    },
+    value_coerce::{decode_text, Charset},
 };
 
 use super::{
     clipboard_async::ClipboardAsync,
-    common::{target_includes_text, TYPE_TEXT, TYPE_URI},
+    common::{
+        default_clipboard, target_includes_text, TYPE_GNOME_COPIED_FILES, TYPE_HTML,
+        TYPE_KDE_CUT_SELECTION, TYPE_TEXT, TYPE_URI,
+    },
+    item_container::{decode_items, item_target, parse_item_target, ContainerItem, TYPE_ITEMS},
 };
 
+#[cfg(feature = "image")]
+use super::image_transcode::ImageToPng;
+
+/// Converters used to synthesize formats not provided by clipboard owner.
+pub fn platform_format_converters() -> Vec<Rc<dyn FormatConverter>> {
+    vec![
+        #[cfg(feature = "image")]
+        Rc::new(ImageToPng::new()),
+        Rc::new(HtmlToText::new(&[TYPE_HTML], TYPE_TEXT)),
+        Rc::new(RtfToText::new(&["text/rtf", "application/rtf"], TYPE_TEXT)),
+    ]
+}
+
 pub struct PlatformDataReader {
     reader: Reader,
     initializing: Cell<bool>,
@@ -33,6 +61,9 @@ pub struct PlatformDataReader {
 struct Inner {
     targets: Vec<String>,
     uris: Vec<String>,
+    /// Items written by this plugin; Empty for data from other applications.
+    items: Vec<ContainerItem>,
+    clipboard_intent: Option<ClipboardIntent>,
 }
 
 enum Reader {
@@ -70,9 +101,47 @@ impl Reader {
     }
 }
 
+impl Inner {
+    fn number_of_items(&self) -> usize {
+        if !self.items.is_empty() {
+            self.items.len()
+        } else {
+            // uris from urilist are represented as separate items
+            1.max(self.uris.len())
+        }
+    }
+
+    fn formats_for_item(&self, item: usize) -> Vec<String> {
+        if !self.items.is_empty() {
+            self.items
+                .get(item)
+                .map(|i| i.formats.clone())
+                .unwrap_or_default()
+        } else if item == 0 {
+            self.targets.clone()
+        } else if item < self.uris.len() {
+            vec![TYPE_URI.into()]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+fn value_for_data(data_type: &str, data: Option<Vec<u8>>) -> Value {
+    if data_type == TYPE_HTML {
+        // Firefox writes UTF-16 HTML; Normalize to UTF-8.
+        data.map(|d| decode_html(&d).into_bytes()).into()
+    } else if let Some(charset) = Charset::from_mime_type(data_type) {
+        data.map(|d| decode_text(&d, Some(charset))).into()
+    } else {
+        data.into()
+    }
+}
+
 pub struct ReaderInfo {
     pub number_of_items: usize,
-    pub targets: Vec<String>,
+    /// Formats of each item.
+    pub item_formats: Vec<Vec<String>>,
 }
 
 impl PlatformDataReader {
@@ -80,6 +149,13 @@ impl PlatformDataReader {
         if !self.inner.is_set() && !self.initializing.get() {
             self.initializing.set(true);
             let mut targets = self.reader.get_targets().await;
+            let items = if targets.iter().any(|t| t == TYPE_ITEMS) {
+                targets.retain(|t| t != TYPE_ITEMS && parse_item_target(t).is_none());
+                let data = self.reader.get_data(TYPE_ITEMS).await;
+                data.and_then(|d| decode_items(&d)).unwrap_or_default()
+            } else {
+                Vec::new()
+            };
             let has_text = targets
                 .iter()
                 .any(|t| target_includes_text(&Atom::intern(t)));
@@ -91,23 +167,53 @@ impl PlatformDataReader {
                     targets.push(TYPE_TEXT.into());
                 }
             }
-            let uris = if targets.iter().any(|t| t == TYPE_URI) {
+            let mut uris = if items.is_empty() && targets.iter().any(|t| t == TYPE_URI) {
                 self.reader.get_uri_list().await
             } else {
                 Vec::new()
             };
+            let clipboard_intent = if targets.iter().any(|t| t == TYPE_GNOME_COPIED_FILES) {
+                let data = self.reader.get_data(TYPE_GNOME_COPIED_FILES).await;
+                match data.and_then(|d| parse_gnome_copied_files(&d)) {
+                    Some((intent, copied_uris)) => {
+                        // Source only provided URIs in file manager format.
+                        if items.is_empty() && uris.is_empty() && !copied_uris.is_empty() {
+                            uris = copied_uris;
+                            targets.push(TYPE_URI.into());
+                        }
+                        Some(intent)
+                    }
+                    None => None,
+                }
+            } else if targets.iter().any(|t| t == TYPE_KDE_CUT_SELECTION) {
+                let data = self.reader.get_data(TYPE_KDE_CUT_SELECTION).await;
+                match data.as_deref().map(|d| d.trim_ascii()) {
+                    Some(b"1") => Some(ClipboardIntent::Cut),
+                    _ => Some(ClipboardIntent::Copy),
+                }
+            } else {
+                None
+            };
             // double check - we might have been preempted
             if !self.inner.is_set() {
-                self.inner.set(Inner { targets, uris })
+                self.inner.set(Inner {
+                    targets,
+                    uris,
+                    items,
+                    clipboard_intent,
+                })
             }
         }
     }
 
     pub fn reader_info(self: &Rc<Self>) -> Option<ReaderInfo> {
         if self.inner.is_set() {
+            let number_of_items = self.inner.number_of_items();
             Some(ReaderInfo {
-                number_of_items: 1.max(self.inner.uris.len()),
-                targets: self.inner.targets.clone(),
+                number_of_items,
+                item_formats: (0..number_of_items)
+                    .map(|i| self.inner.formats_for_item(i))
+                    .collect(),
             })
         } else {
             let this = self.clone();
@@ -120,20 +226,19 @@ impl PlatformDataReader {
 
     pub async fn get_items(&self) -> NativeExtensionsResult<Vec<i64>> {
         self.init().await;
-        // uris from urilist are represented as separate items
-        let num_items = 1.max(self.inner.uris.len());
-        Ok((0..num_items as i64).collect())
+        Ok((0..self.inner.number_of_items() as i64).collect())
+    }
+
+    /// Returns whether files were copied or cut in file manager (or by
+    /// another application using the same formats).
+    pub async fn get_clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        self.init().await;
+        Ok(self.inner.clipboard_intent)
     }
 
     pub async fn get_formats_for_item(&self, item: i64) -> NativeExtensionsResult<Vec<String>> {
         self.init().await;
-        if item == 0 {
-            Ok(self.inner.targets.clone())
-        } else if (item as usize) < self.inner.uris.len() {
-            Ok(vec![TYPE_URI.into()])
-        } else {
-            Ok(Vec::new())
-        }
+        Ok(self.inner.formats_for_item(item as usize))
     }
 
     pub async fn get_suggested_name_for_item(
@@ -141,14 +246,36 @@ impl PlatformDataReader {
         item: i64,
     ) -> NativeExtensionsResult<Option<String>> {
         let item = item as usize;
-        let uri = self.inner.uris.get(item).and_then(|u| Url::parse(u).ok());
-        if let Some(uri) = uri {
-            if let Some(mut segments) = uri.path_segments() {
-                let last: Option<&str> = segments.next_back().filter(|s| !s.is_empty());
-                return Ok(last.map(|f| f.to_owned()));
-            }
+        if !self.inner.items.is_empty() {
+            return Ok(self
+                .inner
+                .items
+                .get(item)
+                .and_then(|i| i.suggested_name.clone()));
         }
-        Ok(None)
+        if let Some(path) = self.local_path_for_item(item) {
+            let name = path.file_name().map(|n| n.to_string_lossy());
+            return Ok(name.and_then(|n| sanitize_file_name(&n)));
+        }
+        Ok(self
+            .inner
+            .uris
+            .get(item)
+            .and_then(|u| suggested_name_for_uri(u)))
+    }
+
+    /// Returns local path for item with `file` URI.
+    pub async fn get_local_path_for_item(
+        &self,
+        item: i64,
+    ) -> NativeExtensionsResult<Option<PathBuf>> {
+        self.init().await;
+        Ok(self.local_path_for_item(item as usize))
+    }
+
+    fn local_path_for_item(&self, item: usize) -> Option<PathBuf> {
+        let uri = self.inner.uris.get(item)?;
+        file_uri_to_path(uri, Some(glib::host_name().as_str()))
     }
 
     pub async fn get_item_format_for_uri(
@@ -156,19 +283,12 @@ impl PlatformDataReader {
         item: i64,
     ) -> NativeExtensionsResult<Option<String>> {
         let item = item as usize;
-        let uri = self.inner.uris.get(item).and_then(|u| Url::parse(u).ok());
-        if let Some(uri) = uri {
-            let name: Option<&str> = uri.path_segments().and_then(|mut s| s.next_back());
-            match name {
-                Some(name) => {
-                    let format = mime_from_name(name);
-                    Ok(Some(format))
-                }
-                None => Ok(None),
-            }
-        } else {
-            Ok(None)
-        }
+        let name = self
+            .inner
+            .uris
+            .get(item)
+            .and_then(|u| suggested_name_for_uri(u));
+        Ok(name.map(|name| mime_from_name(&name)))
     }
 
     pub async fn get_data_for_item(
@@ -178,7 +298,23 @@ impl PlatformDataReader {
         _progress: Option<Arc<ReadProgress>>,
     ) -> NativeExtensionsResult<Value> {
         let item = item as usize;
-        if data_type == TYPE_URI && item < self.inner.uris.len() {
+        if !self.inner.items.is_empty() {
+            let has_format = self
+                .inner
+                .items
+                .get(item)
+                .is_some_and(|i| i.formats.contains(&data_type));
+            let data = if has_format {
+                self.reader.get_data(&item_target(item, &data_type)).await
+            } else {
+                None
+            };
+            if data_type == TYPE_TEXT || data_type == TYPE_URI {
+                Ok(data.map(|d| decode_text(&d, None)).into())
+            } else {
+                Ok(value_for_data(&data_type, data))
+            }
+        } else if data_type == TYPE_URI && item < self.inner.uris.len() {
             Ok(self.inner.uris[item].clone().into())
         } else if item == 0 {
             let target = Atom::intern(&data_type);
@@ -186,7 +322,8 @@ impl PlatformDataReader {
             if is_text {
                 Ok(self.reader.get_text().await.into())
             } else {
-                Ok(self.reader.get_data(&data_type).await.into())
+                let data = self.reader.get_data(&data_type).await;
+                Ok(value_for_data(&data_type, data))
             }
         } else {
             Ok(Value::Null)
@@ -195,10 +332,7 @@ impl PlatformDataReader {
 
     pub fn new_clipboard_reader() -> NativeExtensionsResult<Rc<Self>> {
         unsafe { gtk::set_initialized() };
-        let display = Display::default()
-            .ok_or_else(|| NativeExtensionsError::OtherError("Display not found".into()))?;
-        let clipboard = Clipboard::default(&display)
-            .ok_or_else(|| NativeExtensionsError::OtherError("Clipboard not found".into()))?;
+        let clipboard = default_clipboard()?;
         let res = Rc::new(PlatformDataReader {
             reader: Reader::Clipboard(ClipboardReader { clipboard }),
             initializing: Cell::new(false),
@@ -344,7 +478,7 @@ impl WidgetReader {
         let (future, completer) = FutureCompleter::new();
         self.request_data_if_needed(Atom::intern(TYPE_URI), completer);
         let data: SelectionData = future.await;
-        data.uris().iter().map(|a| a.as_str().to_owned()).collect()
+        parse_uri_list(&data.data())
     }
 
     async fn get_text(&self) -> Option<String> {
@@ -410,14 +544,13 @@ impl Drop for WidgetReader {
 }
 
 fn mime_from_name(name: &str) -> String {
+    #[cfg(feature = "mime_guess")]
+    if let Some(mime) = mime_guess::from_path(name).first() {
+        return mime.to_string();
+    }
     let ext = Path::new(name).extension();
-    mime_guess::from_path(name)
-        .first()
-        .map(|m| m.to_string())
-        .unwrap_or_else(|| {
-            format!(
-                "application/octet-stream;extension={}",
-                ext.unwrap_or_default().to_string_lossy()
-            )
-        })
+    format!(
+        "application/octet-stream;extension={}",
+        ext.unwrap_or_default().to_string_lossy()
+    )
 }
@@ -1,23 +1,212 @@
-use std::{fmt::Display, panic::Location};
+use std::{
+    cell::Cell,
+    fmt::Display,
+    panic::Location,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, RwLock,
+    },
+};
 
-use irondash_message_channel::{MethodCallError, SendMessageError};
-use log::{Level, Record};
+use irondash_message_channel::{IntoValue, MethodCallError, SendMessageError};
+use irondash_run_loop::RunLoopSender;
+use log::{Level, LevelFilter, Log, Metadata, Record};
 
-use crate::error::NativeExtensionsError;
+use crate::{
+    context::Context, error::NativeExtensionsError, log_config_manager::GetLogConfigManager,
+};
 
-fn log_error<E: Display>(err: E, location: &Location) {
+/// Plugin subsystems that can have log level configured separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    Drag,
+    Drop,
+    Reader,
+    Provider,
+    Menu,
+    HotKey,
+}
+
+impl Subsystem {
+    const COUNT: usize = 6;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "drag" => Some(Subsystem::Drag),
+            "drop" => Some(Subsystem::Drop),
+            "reader" => Some(Subsystem::Reader),
+            "provider" => Some(Subsystem::Provider),
+            "menu" => Some(Subsystem::Menu),
+            "hotKey" => Some(Subsystem::HotKey),
+            _ => None,
+        }
+    }
+
+    /// Determines subsystem from log target, which is either module path or
+    /// source file path (for errors logged through [`OkLog`]).
+    pub fn for_target(target: &str) -> Option<Self> {
+        let name = target
+            .rsplit([':', '/', '\\'])
+            .next()
+            .unwrap_or(target)
+            .trim_end_matches(".rs");
+        match name {
+            "drag_manager" | "drag" | "drag_common" => Some(Subsystem::Drag),
+            "drop_manager" | "drop" => Some(Subsystem::Drop),
+            "reader_manager" | "reader" | "clipboard_reader" | "clipboard_async" => {
+                Some(Subsystem::Reader)
+            }
+            "data_provider_manager"
+            | "data_provider"
+            | "data_object"
+            | "clipboard_writer"
+            | "virtual_file_stream" => Some(Subsystem::Provider),
+            "menu_manager" | "menu" => Some(Subsystem::Menu),
+            "hot_key_manager" | "hot_key" | "hot_key_sys" => Some(Subsystem::HotKey),
+            _ => None,
+        }
+    }
+}
+
+struct LogLevels {
+    default: LevelFilter,
+    subsystems: [Option<LevelFilter>; Subsystem::COUNT],
+}
+
+impl LogLevels {
+    fn level_for_target(&self, target: &str) -> LevelFilter {
+        Subsystem::for_target(target)
+            .and_then(|s| self.subsystems[s as usize])
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.subsystems
+            .iter()
+            .flatten()
+            .fold(self.default, |a, b| a.max(*b))
+    }
+}
+
+static LEVELS: RwLock<LogLevels> = RwLock::new(LogLevels {
+    default: LevelFilter::Info,
+    subsystems: [None; Subsystem::COUNT],
+});
+
+static FORWARD_TO_DART: AtomicBool = AtomicBool::new(false);
+static FORWARD_SENDER: Mutex<Option<RunLoopSender>> = Mutex::new(None);
+
+thread_local! {
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets default log level and per-subsystem overrides.
+pub fn set_log_levels(default: LevelFilter, subsystems: &[(Subsystem, LevelFilter)]) {
+    let mut levels = LEVELS.write().unwrap();
+    levels.default = default;
+    levels.subsystems = [None; Subsystem::COUNT];
+    for (subsystem, level) in subsystems {
+        levels.subsystems[*subsystem as usize] = Some(*level);
+    }
+    log::set_max_level(levels.max_level());
+}
+
+/// Enables or disables forwarding of log records to Dart. Records are
+/// delivered on the thread that called this method.
+pub fn set_forward_to_dart(forward: bool) {
+    let mut sender = FORWARD_SENDER.lock().unwrap();
+    if forward && sender.is_none() {
+        sender.replace(irondash_run_loop::RunLoop::current().new_sender());
+    }
+    FORWARD_TO_DART.store(forward, Ordering::Relaxed);
+}
+
+#[derive(IntoValue, Clone, Debug)]
+#[irondash(rename_all = "camelCase")]
+pub struct ForwardedRecord {
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+struct PluginLogger {
+    inner: Box<dyn Log>,
+}
+
+impl PluginLogger {
+    fn forward(&self, record: &Record) {
+        if FORWARDING.with(|f| f.get()) {
+            return;
+        }
+        let record = ForwardedRecord {
+            level: record.level().as_str().to_lowercase(),
+            target: record.target().into(),
+            message: record.args().to_string(),
+        };
+        if let Some(sender) = FORWARD_SENDER.lock().unwrap().as_ref() {
+            sender.send(move || {
+                FORWARDING.with(|f| f.set(true));
+                if let Some(context) = Context::current() {
+                    context.log_config_manager().forward_record(record);
+                }
+                FORWARDING.with(|f| f.set(false));
+            });
+        }
+    }
+}
+
+impl Log for PluginLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= LEVELS.read().unwrap().level_for_target(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(record);
+        if FORWARD_TO_DART.load(Ordering::Relaxed) {
+            self.forward(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs plugin logger that filters records according to configured
+/// levels and passes them to `inner`.
+pub fn init_logger(inner: Box<dyn Log>, default_level: LevelFilter) {
+    if log::set_boxed_logger(Box::new(PluginLogger { inner })).is_ok() {
+        set_log_levels(default_level, &[]);
+    }
+}
+
+fn log_error<E: Display>(err: E, location: &Location, level: Level) {
+    let prefix = if level == Level::Error {
+        "Unexpected error"
+    } else {
+        "Error"
+    };
     log::logger().log(
         &Record::builder()
-            .args(format_args!("Unexpected error {err} at {location}"))
+            .args(format_args!("{prefix} {err} at {location}"))
+            .target(location.file())
             .file(Some(location.file()))
             .line(Some(location.line()))
-            .level(Level::Error)
+            .level(level)
             .build(),
     );
 }
 
 pub trait OkLog<T> {
     fn ok_log(self) -> Option<T>;
+
+    /// Like [`OkLog::ok_log`], but logs the error with given level. Meant
+    /// for failures that are expected to happen from time to time (i.e.
+    /// cleanup of files that may already be gone).
+    fn ok_log_at(self, level: Level) -> Option<T>;
 }
 
 impl<T, E> OkLog<T> for std::result::Result<T, E>
@@ -30,7 +219,19 @@ where
             Ok(value) => Some(value),
             Err(err) => {
                 let location = Location::caller();
-                log_error(err, location);
+                log_error(err, location, Level::Error);
+                None
+            }
+        }
+    }
+
+    #[track_caller]
+    fn ok_log_at(self, level: Level) -> Option<T> {
+        match self {
+            Ok(value) => Some(value),
+            Err(err) => {
+                let location = Location::caller();
+                log_error(err, location, level);
                 None
             }
         }
@@ -60,9 +261,35 @@ impl<T> OkLogUnexpected<T> for std::result::Result<T, NativeExtensionsError> {
                     }
                 }
                 let location = Location::caller();
-                log_error(err, location);
+                log_error(err, location, Level::Error);
                 None
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Subsystem;
+
+    #[test]
+    fn test_subsystem_for_target() {
+        assert_eq!(
+            Subsystem::for_target("super_native_extensions::drag_manager"),
+            Some(Subsystem::Drag)
+        );
+        assert_eq!(
+            Subsystem::for_target("super_native_extensions::platform_impl::platform::drop"),
+            Some(Subsystem::Drop)
+        );
+        assert_eq!(
+            Subsystem::for_target("src/linux/data_provider.rs"),
+            Some(Subsystem::Provider)
+        );
+        assert_eq!(
+            Subsystem::for_target("src\\win32\\hot_key.rs"),
+            Some(Subsystem::HotKey)
+        );
+        assert_eq!(Subsystem::for_target("super_native_extensions"), None);
+    }
+}
@@ -0,0 +1,124 @@
+use std::{cell::RefCell, collections::HashSet, convert::TryInto, rc::Rc, str::FromStr};
+
+use irondash_message_channel::{
+    IntoPlatformResult, IsolateId, Late, MethodCall, MethodCallReply, MethodHandler, MethodInvoker,
+    PlatformResult, RegisteredMethodHandler, TryFromValue, Value,
+};
+use log::LevelFilter;
+
+use crate::{
+    context::Context,
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    invoker::Invoker,
+    log::{set_forward_to_dart, set_log_levels, ForwardedRecord, Subsystem},
+};
+
+#[derive(TryFromValue, Debug)]
+#[irondash(rename_all = "camelCase")]
+struct SubsystemLogLevel {
+    subsystem: String,
+    level: String,
+}
+
+#[derive(TryFromValue, Debug)]
+#[irondash(rename_all = "camelCase")]
+struct SetLogLevelsRequest {
+    default_level: String,
+    subsystems: Vec<SubsystemLogLevel>,
+}
+
+pub struct LogConfigManager {
+    invoker: Late<Invoker>,
+    forward_isolates: RefCell<HashSet<IsolateId>>,
+}
+
+pub trait GetLogConfigManager {
+    fn log_config_manager(&self) -> Rc<LogConfigManager>;
+}
+
+impl GetLogConfigManager for Context {
+    fn log_config_manager(&self) -> Rc<LogConfigManager> {
+        self.get_attachment(LogConfigManager::new).handler()
+    }
+}
+
+fn parse_level(level: &str) -> NativeExtensionsResult<LevelFilter> {
+    LevelFilter::from_str(level)
+        .map_err(|_| NativeExtensionsError::OtherError(format!("Invalid log level: {level}")))
+}
+
+impl LogConfigManager {
+    pub fn new() -> RegisteredMethodHandler<Self> {
+        Self {
+            invoker: Late::new(),
+            forward_isolates: RefCell::new(HashSet::new()),
+        }
+        .register("LogConfig")
+    }
+
+    fn set_log_levels(&self, request: SetLogLevelsRequest) -> NativeExtensionsResult<()> {
+        let default = parse_level(&request.default_level)?;
+        let subsystems = request
+            .subsystems
+            .iter()
+            .map(|s| {
+                let subsystem = Subsystem::from_name(&s.subsystem).ok_or_else(|| {
+                    NativeExtensionsError::OtherError(format!(
+                        "Unknown log subsystem: {}",
+                        s.subsystem
+                    ))
+                })?;
+                Ok((subsystem, parse_level(&s.level)?))
+            })
+            .collect::<NativeExtensionsResult<Vec<_>>>()?;
+        set_log_levels(default, &subsystems);
+        Ok(())
+    }
+
+    fn set_forward_to_dart(&self, isolate: IsolateId, forward: bool) {
+        let mut isolates = self.forward_isolates.borrow_mut();
+        if forward {
+            isolates.insert(isolate);
+        } else {
+            isolates.remove(&isolate);
+        }
+        set_forward_to_dart(!isolates.is_empty());
+    }
+
+    /// Sends log record to all isolates that requested forwarding.
+    pub fn forward_record(&self, record: ForwardedRecord) {
+        for isolate in self.forward_isolates.borrow().iter() {
+            // Failure to deliver is not logged, it would only produce
+            // another record to forward.
+            self.invoker
+                .call_method(*isolate, "onLog", record.clone(), |_| {});
+        }
+    }
+
+    pub(crate) fn on_method_call(&self, call: MethodCall) -> PlatformResult {
+        match call.method.as_str() {
+            "setLogLevels" => self
+                .set_log_levels(call.args.try_into()?)
+                .into_platform_result(),
+            "setForwardToDart" => {
+                self.set_forward_to_dart(call.isolate, call.args.try_into()?);
+                Ok(Value::Null)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+}
+
+impl MethodHandler for LogConfigManager {
+    fn on_method_call(&self, call: MethodCall, reply: MethodCallReply) {
+        reply.send(self.on_method_call(call))
+    }
+
+    fn assign_invoker(&self, invoker: MethodInvoker) {
+        self.invoker.set(invoker.into());
+    }
+
+    fn on_isolate_destroyed(&self, isolate: IsolateId) {
+        self.set_forward_to_dart(isolate, false);
+    }
+}
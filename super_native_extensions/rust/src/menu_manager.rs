@@ -19,8 +19,10 @@ use crate::{
         ShowContextMenuRequest, ShowContextMenuResponse, WritingToolsReplacementRequest,
     },
     context::Context,
+    diagnostics::{ResourceKind, ResourceOrigin, ResourceVisitor},
     drag_manager::GetDragManager,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    invoker::AsyncInvoker,
     log::{OkLog, OkLogUnexpected},
     platform_impl::platform::{PlatformDragContext, PlatformMenu, PlatformMenuContext},
     util::NextId,
@@ -70,10 +72,16 @@ pub trait PlatformMenuDelegate {
 
 pub struct MenuManager {
     weak_self: Late<Weak<Self>>,
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
     contexts: RefCell<HashMap<PlatformMenuContextId, Rc<PlatformMenuContext>>>,
     next_id: Cell<i64>,
-    menus: RefCell<HashMap<i64, Rc<PlatformMenu>>>,
+    menus: RefCell<HashMap<i64, MenuEntry>>,
+}
+
+struct MenuEntry {
+    isolate_id: IsolateId,
+    origin: ResourceOrigin,
+    menu: Rc<PlatformMenu>,
 }
 
 pub trait GetMenuManager {
@@ -99,6 +107,14 @@ struct UpdatePreviewImageRequest {
     image: ImageData,
 }
 
+#[derive(TryFromValue)]
+#[irondash(rename_all = "camelCase")]
+struct RegisterMenuRequest {
+    menu: MenuElement,
+    /// Dart code location registering the menu, reported by leak diagnostics.
+    creation_site: Option<String>,
+}
+
 impl MenuManager {
     pub fn new() -> RegisteredAsyncMethodHandler<Self> {
         Self {
@@ -115,6 +131,12 @@ impl MenuManager {
         self.contexts.borrow().values().cloned().collect()
     }
 
+    pub fn visit_resources(&self, visitor: &mut dyn ResourceVisitor) {
+        for entry in self.menus.borrow().values() {
+            visitor.visit(entry.isolate_id, ResourceKind::Menu, &entry.origin);
+        }
+    }
+
     fn new_context(
         &self,
         isolate: IsolateId,
@@ -137,13 +159,20 @@ impl MenuManager {
 
     async fn register_menu(
         &self,
-        menu: MenuElement,
+        request: RegisterMenuRequest,
         isolate: IsolateId,
     ) -> NativeExtensionsResult<i64> {
-        if let MenuElement::Menu(menu) = menu {
+        if let MenuElement::Menu(menu) = request.menu {
             let platform_menu = PlatformMenu::new(isolate, self.weak_self.clone(), menu)?;
             let id = self.next_id.next_id();
-            self.menus.borrow_mut().insert(id, platform_menu);
+            self.menus.borrow_mut().insert(
+                id,
+                MenuEntry {
+                    isolate_id: isolate,
+                    origin: ResourceOrigin::new(request.creation_site, "registerMenu"),
+                    menu: platform_menu,
+                },
+            );
             Ok(id)
         } else {
             Err(NativeExtensionsError::InvalidMenuElement)
@@ -183,7 +212,7 @@ impl MenuManager {
             .menus
             .borrow()
             .get(&menu_request.menu_handle)
-            .cloned()
+            .map(|e| e.menu.clone())
             .ok_or(NativeExtensionsError::PlatformMenuNotFound)?;
         menu_request.menu = Some(menu);
         context.show_context_menu(menu_request).await
@@ -224,7 +253,11 @@ impl MenuManager {
                 if configuration.configuration_id != configuration_id {
                     return Err(NativeExtensionsError::InvalidMenuConfigurationId);
                 }
-                let menu = self.menus.borrow().get(&configuration.menu_handle).cloned();
+                let menu = self
+                    .menus
+                    .borrow()
+                    .get(&configuration.menu_handle)
+                    .map(|e| e.menu.clone());
                 if let Some(menu) = menu {
                     configuration.menu = Some(menu);
                     Ok(Some(configuration))
@@ -353,7 +386,7 @@ impl AsyncMethodHandler for MenuManager {
     }
 
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     async fn on_method_call(&self, call: MethodCall) -> PlatformResult {
@@ -385,4 +418,215 @@ impl AsyncMethodHandler for MenuManager {
             _ => Ok(Value::Null),
         }
     }
+
+    fn on_isolate_destroyed(&self, isolate: IsolateId) {
+        self.menus
+            .borrow_mut()
+            .retain(|_, entry| entry.isolate_id != isolate);
+        let context = self.contexts.borrow_mut().remove(&isolate);
+        drop(context);
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use irondash_message_channel::{
+        AsyncMethodHandler, IntoValue, MethodCall, Value, ValueTupleList,
+    };
+    use irondash_run_loop::{spawn, util::FutureCompleter};
+
+    use super::GetMenuManager;
+    use crate::{
+        api_model::{ImageData, Point, Rect},
+        context::Context,
+        platform::{next_turn, run_test, value_at, MockIsolate},
+    };
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ContextInitRequest {
+        engine_handle: i64,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct TargettedImage {
+        image_data: ImageData,
+        rect: Rect,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct MenuConfiguration {
+        configuration_id: Value,
+        preview_image: Value,
+        preview_size: Value,
+        lift_image: TargettedImage,
+        menu_handle: i64,
+        preview_size_policy: Value,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct MenuConfigurationResponse {
+        configuration: MenuConfiguration,
+    }
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ShowContextMenuRequest {
+        menu_handle: i64,
+        location: Point,
+        writing_tools_configuration: Value,
+    }
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(ValueTupleList::new(
+            entries
+                .into_iter()
+                .map(|(k, v)| (Value::String(k.into()), v))
+                .collect(),
+        ))
+    }
+
+    /// Menu without children, tagged the same way as Dart serializes it.
+    fn menu(unique_id: i64) -> Value {
+        map(vec![
+            ("type", Value::String("menu".into())),
+            (
+                "content",
+                map(vec![
+                    ("uniqueId", Value::I64(unique_id)),
+                    ("identifier", Value::Null),
+                    ("title", Value::String("Menu".into())),
+                    ("subitle", Value::Null),
+                    ("image", Value::Null),
+                    ("children", Value::List(Vec::new())),
+                ]),
+            ),
+        ])
+    }
+
+    async fn register_menu(isolate: &MockIsolate) -> i64 {
+        isolate
+            .call_method(
+                "MenuManager",
+                "newContext",
+                ContextInitRequest { engine_handle: 0 },
+            )
+            .await
+            .unwrap();
+        let request = map(vec![("menu", menu(1)), ("creationSite", Value::Null)]);
+        isolate
+            .call_method("MenuManager", "registerMenu", request)
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_menu_request() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let handle = register_menu(&isolate).await;
+            isolate.on_call("getConfigurationForLocation", move |request| {
+                Ok(MenuConfigurationResponse {
+                    configuration: MenuConfiguration {
+                        configuration_id: value_at(&request, &["configurationId"]).clone(),
+                        preview_image: Value::Null,
+                        preview_size: Value::Null,
+                        lift_image: TargettedImage {
+                            image_data: ImageData {
+                                width: 1,
+                                height: 1,
+                                bytes_per_row: 4,
+                                data: vec![0; 4],
+                                ..Default::default()
+                            },
+                            rect: Rect::xywh(0.0, 0.0, 1.0, 1.0),
+                        },
+                        menu_handle: handle,
+                        preview_size_policy: Value::Null,
+                    },
+                }
+                .into())
+            });
+
+            let context = Context::get().menu_manager().get_platform_menu_contexts()[0].clone();
+            let configuration_id = context
+                .simulate_menu_request(Point { x: 1.0, y: 1.0 })
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                isolate.calls_to("onShowMenu"),
+                vec![Value::I64(configuration_id)]
+            );
+            context.simulate_menu_hide(configuration_id, false).unwrap();
+            let hidden = isolate.calls_to("onHideMenu");
+            assert_eq!(hidden.len(), 1);
+            assert_eq!(
+                value_at(&hidden[0], &["menuConfigurationId"]),
+                &Value::I64(configuration_id)
+            );
+        });
+    }
+
+    #[test]
+    fn test_show_context_menu() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let handle = register_menu(&isolate).await;
+
+            // The call only completes once the menu is dismissed.
+            let manager = Context::get().menu_manager();
+            let call = MethodCall {
+                method: "showContextMenu".into(),
+                args: ShowContextMenuRequest {
+                    menu_handle: handle,
+                    location: Point { x: 1.0, y: 1.0 },
+                    writing_tools_configuration: Value::Null,
+                }
+                .into(),
+                isolate: isolate.id(),
+            };
+            let (future, completer) = FutureCompleter::new();
+            spawn(async move { completer.complete(manager.on_method_call(call).await) });
+            next_turn().await;
+
+            let context = Context::get().menu_manager().get_platform_menu_contexts()[0].clone();
+            let menu = context.visible_menu().unwrap();
+            assert_eq!(menu.menu().unique_id, 1);
+            context.simulate_menu_dismiss(Some(5)).unwrap();
+            let result = future.await.unwrap();
+            assert_eq!(value_at(&result, &["itemSelected"]), &Value::Bool(true));
+            assert_eq!(isolate.calls_to("onAction"), vec![Value::I64(5)]);
+        });
+    }
+
+    #[test]
+    fn test_isolate_destroyed() {
+        run_test(async {
+            let isolate_1 = MockIsolate::new();
+            let isolate_2 = MockIsolate::new();
+            register_menu(&isolate_1).await;
+            let handle = register_menu(&isolate_2).await;
+            let manager = Context::get().menu_manager();
+            assert_eq!(manager.menus.borrow().len(), 2);
+
+            drop(isolate_1);
+            assert_eq!(manager.menus.borrow().keys().collect::<Vec<_>>(), [&handle]);
+            assert_eq!(manager.get_platform_menu_contexts().len(), 1);
+
+            isolate_2
+                .call_method("MenuManager", "disposeMenu", handle)
+                .await
+                .unwrap();
+            assert!(manager.menus.borrow().is_empty());
+
+            drop(isolate_2);
+            assert!(manager.get_platform_menu_contexts().is_empty());
+        });
+    }
 }
@@ -0,0 +1,26 @@
+use crate::capabilities::{
+    Capabilities, ClipboardCapabilities, DragCapabilities, HotKeyCapabilities, MenuCapabilities,
+    VirtualFileCapabilities,
+};
+
+pub fn platform_capabilities() -> Capabilities {
+    Capabilities {
+        clipboard: ClipboardCapabilities {
+            multiple_items: true,
+            primary_selection: false,
+            events: true,
+        },
+        virtual_files: VirtualFileCapabilities {
+            read: true,
+            copy: true,
+            provide: true,
+        },
+        hot_keys: HotKeyCapabilities { supported: true },
+        menu: MenuCapabilities {
+            preview_image: true,
+        },
+        drag: DragCapabilities {
+            preview_animations: false,
+        },
+    }
+}
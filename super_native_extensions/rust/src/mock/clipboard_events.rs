@@ -0,0 +1,51 @@
+use std::rc::Weak;
+
+use crate::clipboard_events_manager::{
+    ClipboardEventManagerDelegate, PlatformClipboardEventManagerId,
+};
+
+pub struct PlatformClipboardEventManager {
+    id: PlatformClipboardEventManagerId,
+    delegate: Weak<dyn ClipboardEventManagerDelegate>,
+}
+
+impl PlatformClipboardEventManager {
+    pub fn new(
+        id: PlatformClipboardEventManagerId,
+        delegate: Weak<dyn ClipboardEventManagerDelegate>,
+    ) -> Self {
+        Self { id, delegate }
+    }
+
+    pub fn assign_weak_self(&self, _weak: Weak<PlatformClipboardEventManager>) {}
+
+    // Each method returns whether the event was handled by Dart.
+
+    pub async fn simulate_cut(&self) -> bool {
+        match self.delegate.upgrade() {
+            Some(delegate) => delegate.on_cut(self.id).await,
+            None => false,
+        }
+    }
+
+    pub async fn simulate_copy(&self) -> bool {
+        match self.delegate.upgrade() {
+            Some(delegate) => delegate.on_copy(self.id).await,
+            None => false,
+        }
+    }
+
+    pub async fn simulate_paste(&self) -> bool {
+        match self.delegate.upgrade() {
+            Some(delegate) => delegate.on_paste(self.id).await,
+            None => false,
+        }
+    }
+
+    pub async fn simulate_select_all(&self) -> bool {
+        match self.delegate.upgrade() {
+            Some(delegate) => delegate.on_select_all(self.id).await,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::{Rc, Weak},
+    sync::{Arc, Mutex},
+};
+
+use irondash_message_channel::{IsolateId, Value};
+use irondash_run_loop::util::{Capsule, FutureCompleter};
+
+use crate::{
+    api_model::{DataProvider, DataProviderValueId, DataRepresentation},
+    data_provider_manager::{DataProviderHandle, PlatformDataProviderDelegate, VirtualFileResult},
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    reader_manager::ReadProgress,
+    value_cbor::value_for_format,
+    value_promise::ValuePromiseResult,
+};
+
+thread_local! {
+    /// Live data providers. Can be used by tests to verify that providers
+    /// are released.
+    pub static PROVIDERS: RefCell<Vec<Weak<PlatformDataProvider>>> = const { RefCell::new(Vec::new()) };
+
+    /// Contents of in-memory clipboard.
+    static CLIPBOARD: RefCell<Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Data written to virtual file streams, keyed by stream handle.
+static STREAM_ENTRIES: Mutex<BTreeMap<i32, Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+fn add_stream_entry() -> i32 {
+    thread_local! {
+        static NEXT_STREAM_ENTRY_HANDLE : Cell<i32> = const { Cell::new(1) }
+    }
+    let handle = NEXT_STREAM_ENTRY_HANDLE.with(|handle| {
+        let res = handle.get();
+        handle.set(res + 1);
+        res
+    });
+    STREAM_ENTRIES.lock().unwrap().insert(handle, Vec::new());
+    handle
+}
+
+fn take_stream_entry(handle: i32) -> Option<Vec<u8>> {
+    STREAM_ENTRIES.lock().unwrap().remove(&handle)
+}
+
+pub fn platform_stream_write(handle: i32, data: &[u8]) -> i32 {
+    let mut entries = STREAM_ENTRIES.lock().unwrap();
+    match entries.get_mut(&handle) {
+        Some(entry) => {
+            entry.extend_from_slice(data);
+            1
+        }
+        None => 0,
+    }
+}
+
+pub fn platform_stream_close(handle: i32, delete: bool) {
+    // Data is kept until the reader picks it up, unless the stream is being
+    // discarded.
+    if delete {
+        take_stream_entry(handle);
+    }
+}
+
+/// Mock providers resolve lazy values again on every read.
+pub fn platform_provider_cache_bytes() -> Option<usize> {
+    None
+}
+
+/// Removes all items from in-memory clipboard, releasing their providers.
+pub fn clear_clipboard() {
+    let previous = CLIPBOARD.with(|c| c.take());
+    drop(previous);
+}
+
+pub(super) fn clipboard_providers() -> Vec<Rc<PlatformDataProvider>> {
+    CLIPBOARD.with(|c| c.borrow().iter().map(|p| p.0.clone()).collect())
+}
+
+pub struct PlatformDataProvider {
+    delegate: Weak<dyn PlatformDataProviderDelegate>,
+    isolate_id: IsolateId,
+    data: DataProvider,
+}
+
+impl PlatformDataProvider {
+    pub fn new(
+        delegate: Weak<dyn PlatformDataProviderDelegate>,
+        isolate_id: IsolateId,
+        data_provider: DataProvider,
+    ) -> Self {
+        Self {
+            delegate,
+            isolate_id,
+            data: data_provider,
+        }
+    }
+
+    pub fn assign_weak_self(&self, weak_self: Weak<Self>) {
+        PROVIDERS.with(|p| p.borrow_mut().push(weak_self));
+    }
+
+    /// Clears the in-memory clipboard.
+    pub fn release_clipboard() {
+        clear_clipboard();
+    }
+
+    pub async fn write_to_clipboard(
+        providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
+    ) -> NativeExtensionsResult<()> {
+        // Replacing clipboard content releases previous providers.
+        let previous = CLIPBOARD.with(|c| c.replace(providers));
+        drop(previous);
+        Ok(())
+    }
+
+    pub fn data(&self) -> &DataProvider {
+        &self.data
+    }
+
+    /// Returns value for given format, requesting lazy data from delegate
+    /// if necessary. Values are encoded for the format the same way platform
+    /// implementations encode them.
+    pub(super) async fn get_value(&self, format: &str) -> Option<Value> {
+        for representation in &self.data.representations {
+            match representation {
+                DataRepresentation::Simple { format: f, data } if f == format => {
+                    return Some(value_for_format(data, format).into_owned());
+                }
+                DataRepresentation::Lazy { id, format: f } if f == format => {
+                    let delegate = self.delegate.upgrade()?;
+                    return match delegate.get_lazy_data_async(self.isolate_id, *id).await {
+                        ValuePromiseResult::Ok { value } => {
+                            Some(value_for_format(&value, format).into_owned())
+                        }
+                        ValuePromiseResult::Cancelled => None,
+                    };
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    pub(super) fn virtual_file_id(&self, format: &str) -> Option<DataProviderValueId> {
+        self.data
+            .representations
+            .iter()
+            .find_map(|representation| match representation {
+                DataRepresentation::VirtualFile { id, format: f, .. } if f == format => Some(*id),
+                _ => None,
+            })
+    }
+
+    /// Requests virtual file from delegate and returns its content once
+    /// the file is fully written.
+    pub(super) async fn receive_virtual_file(
+        &self,
+        format: &str,
+        progress: Arc<ReadProgress>,
+    ) -> NativeExtensionsResult<Vec<u8>> {
+        let id = self.virtual_file_id(format).ok_or_else(|| {
+            NativeExtensionsError::VirtualFileReceiveError("virtual file not found".into())
+        })?;
+        let delegate = self
+            .delegate
+            .upgrade()
+            .ok_or(NativeExtensionsError::DataSourceNotFound)?;
+        let handle = add_stream_entry();
+        let (future, completer) = FutureCompleter::new();
+        let progress_clone = progress.clone();
+        let session = delegate.get_virtual_file(
+            self.isolate_id,
+            id,
+            handle,
+            Box::new(|_| {}),
+            Box::new(move |fraction| progress_clone.report_progress(Some(fraction))),
+            Box::new(move |result| completer.complete(result)),
+        );
+        let weak_session = Capsule::new(Arc::downgrade(&session));
+        progress.set_cancellation_handler(Some(Box::new(move || {
+            if let Some(session) = weak_session.get_ref().and_then(|s| s.upgrade()) {
+                session.dispose();
+            }
+        })));
+        let result = future.await;
+        progress.set_cancellation_handler(None);
+        let data = take_stream_entry(handle);
+        match result {
+            VirtualFileResult::Done => Ok(data.unwrap_or_default()),
+            VirtualFileResult::Error { message } => {
+                Err(NativeExtensionsError::VirtualFileReceiveError(message))
+            }
+            VirtualFileResult::Cancelled => Err(NativeExtensionsError::VirtualFileReceiveError(
+                "cancelled".into(),
+            )),
+        }
+    }
+}
+
+impl Drop for PlatformDataProvider {
+    fn drop(&mut self) {
+        PROVIDERS
+            .try_with(|p| {
+                p.borrow_mut().retain(|p| p.as_ptr() != self as *const Self);
+            })
+            .ok();
+    }
+}
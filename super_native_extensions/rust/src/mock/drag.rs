@@ -0,0 +1,182 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+    sync::Arc,
+};
+
+use irondash_message_channel::{Late, Value};
+
+use crate::{
+    api_model::{DataProviderId, DragConfiguration, DragRequest, DropOperation, Point},
+    data_provider_manager::DataProviderHandle,
+    drag_manager::{
+        DataProviderEntry, DragSessionId, PlatformDragContextDelegate, PlatformDragContextId,
+    },
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    value_promise::PromiseResult,
+};
+
+use super::{util::wait_for_promise, PlatformDataProvider, PlatformDataReader};
+
+pub struct PlatformDragContext {
+    id: PlatformDragContextId,
+    delegate: Weak<dyn PlatformDragContextDelegate>,
+    weak_self: Late<Weak<Self>>,
+    sessions: RefCell<HashMap<DragSessionId, Rc<Session>>>,
+}
+
+struct Session {
+    configuration: DragConfiguration,
+    providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
+}
+
+impl PlatformDragContext {
+    pub fn new(
+        id: PlatformDragContextId,
+        _engine_handle: i64,
+        delegate: Weak<dyn PlatformDragContextDelegate>,
+    ) -> NativeExtensionsResult<Self> {
+        Ok(Self {
+            id,
+            delegate,
+            weak_self: Late::new(),
+            sessions: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub fn assign_weak_self(&self, weak_self: Weak<Self>) {
+        self.weak_self.set(weak_self);
+    }
+
+    pub fn needs_combined_drag_image() -> bool {
+        false
+    }
+
+    fn delegate(&self) -> NativeExtensionsResult<Rc<dyn PlatformDragContextDelegate>> {
+        self.delegate
+            .upgrade()
+            .ok_or_else(|| NativeExtensionsError::OtherError("missing context delegate".into()))
+    }
+
+    fn session(&self, session_id: DragSessionId) -> NativeExtensionsResult<Rc<Session>> {
+        self.sessions
+            .borrow()
+            .get(&session_id)
+            .cloned()
+            .ok_or(NativeExtensionsError::DragSessionNotFound)
+    }
+
+    fn begin_session(
+        &self,
+        session_id: DragSessionId,
+        configuration: DragConfiguration,
+        mut providers: HashMap<DataProviderId, DataProviderEntry>,
+    ) -> NativeExtensionsResult<()> {
+        let providers = configuration
+            .items
+            .iter()
+            .map(|item| {
+                let entry = providers
+                    .remove(&item.data_provider_id)
+                    .ok_or(NativeExtensionsError::DataSourceNotFound)?;
+                Ok((entry.provider, entry.handle))
+            })
+            .collect::<NativeExtensionsResult<_>>()?;
+        self.sessions.borrow_mut().insert(
+            session_id,
+            Rc::new(Session {
+                configuration,
+                providers,
+            }),
+        );
+        Ok(())
+    }
+
+    pub async fn start_drag(
+        &self,
+        request: DragRequest,
+        providers: HashMap<DataProviderId, DataProviderEntry>,
+        session_id: DragSessionId,
+    ) -> NativeExtensionsResult<()> {
+        self.begin_session(session_id, request.configuration, providers)
+    }
+
+    pub fn get_local_data(&self) -> Option<Vec<Value>> {
+        self.sessions
+            .borrow()
+            .values()
+            .next()
+            .map(|s| s.configuration.get_local_data())
+    }
+
+    pub fn get_local_data_for_session_id(
+        &self,
+        session_id: DragSessionId,
+    ) -> NativeExtensionsResult<Vec<Value>> {
+        Ok(self.session(session_id)?.configuration.get_local_data())
+    }
+
+    /// Returns identifiers of drag sessions that are currently in progress.
+    pub fn active_sessions(&self) -> Vec<DragSessionId> {
+        self.sessions.borrow().keys().cloned().collect()
+    }
+
+    /// Returns reader for data of given drag session. The reader can be used
+    /// to simulate drop within the application.
+    pub fn reader_for_session(
+        &self,
+        session_id: DragSessionId,
+    ) -> NativeExtensionsResult<Rc<PlatformDataReader>> {
+        let session = self.session(session_id)?;
+        Ok(PlatformDataReader::new_with_providers(
+            session.providers.iter().map(|p| p.0.clone()).collect(),
+        ))
+    }
+
+    /// Simulates platform initiated drag (i.e. long press on mobile). Asks
+    /// Dart for drag configuration and starts the session if there is any.
+    pub async fn simulate_drag_request(
+        &self,
+        location: Point,
+    ) -> NativeExtensionsResult<Option<DragSessionId>> {
+        let promise = self
+            .delegate()?
+            .get_drag_configuration_for_location(self.id, location);
+        match wait_for_promise(&promise).await {
+            PromiseResult::Ok { value } => {
+                self.begin_session(value.session_id, value.configuration, value.providers)?;
+                Ok(Some(value.session_id))
+            }
+            PromiseResult::Cancelled => Ok(None),
+        }
+    }
+
+    pub fn simulate_drag_move(
+        &self,
+        session_id: DragSessionId,
+        screen_location: Point,
+    ) -> NativeExtensionsResult<()> {
+        self.session(session_id)?;
+        self.delegate()?
+            .drag_session_did_move_to_location(self.id, session_id, screen_location);
+        Ok(())
+    }
+
+    /// Ends the drag session. This releases data providers for the session.
+    pub fn simulate_drag_end(
+        &self,
+        session_id: DragSessionId,
+        operation: DropOperation,
+    ) -> NativeExtensionsResult<()> {
+        let session = self
+            .sessions
+            .borrow_mut()
+            .remove(&session_id)
+            .ok_or(NativeExtensionsError::DragSessionNotFound)?;
+        drop(session);
+        self.delegate()?
+            .drag_session_did_end_with_operation(self.id, session_id, operation);
+        Ok(())
+    }
+}
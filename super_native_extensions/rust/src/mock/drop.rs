@@ -0,0 +1,214 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use irondash_message_channel::{Late, Value};
+use irondash_run_loop::util::FutureCompleter;
+
+use crate::{
+    api_model::{DropOperation, Point},
+    drop_manager::{
+        BaseDropEvent, DropEvent, DropItem, DropSessionId, PlatformDropContextDelegate,
+        PlatformDropContextId,
+    },
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    reader_manager::RegisteredDataReader,
+    util::NextId,
+};
+
+use super::PlatformDataReader;
+
+pub struct PlatformDropContext {
+    id: PlatformDropContextId,
+    delegate: Weak<dyn PlatformDropContextDelegate>,
+    weak_self: Late<Weak<Self>>,
+    next_session_id: Cell<i64>,
+    current_session: RefCell<Option<Rc<Session>>>,
+    drop_formats: RefCell<Vec<String>>,
+}
+
+struct Session {
+    id: DropSessionId,
+    platform_reader: Rc<PlatformDataReader>,
+    registered_reader: RegisteredDataReader,
+    allowed_operations: Vec<DropOperation>,
+    last_operation: Cell<DropOperation>,
+}
+
+impl PlatformDropContext {
+    pub fn new(
+        id: PlatformDropContextId,
+        _engine_handle: i64,
+        delegate: Weak<dyn PlatformDropContextDelegate>,
+    ) -> NativeExtensionsResult<Self> {
+        Ok(Self {
+            id,
+            delegate,
+            weak_self: Late::new(),
+            next_session_id: Cell::new(0),
+            current_session: RefCell::new(None),
+            drop_formats: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn assign_weak_self(&self, weak_self: Weak<Self>) {
+        self.weak_self.set(weak_self);
+    }
+
+    fn delegate(&self) -> NativeExtensionsResult<Rc<dyn PlatformDropContextDelegate>> {
+        self.delegate
+            .upgrade()
+            .ok_or_else(|| NativeExtensionsError::OtherError("missing context delegate".into()))
+    }
+
+    pub fn register_drop_formats(&self, formats: &[String]) -> NativeExtensionsResult<()> {
+        self.drop_formats.replace(formats.to_vec());
+        Ok(())
+    }
+
+    /// Formats most recently registered from Dart.
+    pub fn registered_drop_formats(&self) -> Vec<String> {
+        self.drop_formats.borrow().clone()
+    }
+
+    fn session_for_reader(
+        &self,
+        reader: Rc<PlatformDataReader>,
+        allowed_operations: Vec<DropOperation>,
+    ) -> NativeExtensionsResult<Rc<Session>> {
+        if let Some(session) = self.current_session.borrow().as_ref() {
+            if Rc::ptr_eq(&session.platform_reader, &reader) {
+                return Ok(session.clone());
+            }
+        }
+        let registered_reader = self
+            .delegate()?
+            .register_platform_reader(self.id, reader.clone());
+        let session = Rc::new(Session {
+            id: self.next_session_id.next_id().into(),
+            platform_reader: reader,
+            registered_reader,
+            allowed_operations,
+            last_operation: Cell::new(DropOperation::None),
+        });
+        self.current_session.replace(Some(session.clone()));
+        Ok(session)
+    }
+
+    async fn create_drop_event(
+        &self,
+        session: &Session,
+        location: Point,
+        accepted_operation: Option<DropOperation>,
+    ) -> NativeExtensionsResult<DropEvent> {
+        let local_data = self
+            .delegate()?
+            .get_platform_drag_contexts()
+            .iter()
+            .map(|c| c.get_local_data())
+            .find(|c| c.is_some())
+            .flatten()
+            .unwrap_or_default();
+        let reader = &session.platform_reader;
+        let mut items = Vec::new();
+        for item in reader.get_items().await? {
+            items.push(DropItem {
+                item_id: item.into(),
+                formats: reader.get_formats_for_item(item).await?,
+                local_data: local_data
+                    .get(item as usize)
+                    .cloned()
+                    .unwrap_or(Value::Null),
+            });
+        }
+        Ok(DropEvent {
+            session_id: session.id,
+            location_in_view: location,
+            allowed_operations: session.allowed_operations.clone(),
+            accepted_operation,
+            items,
+            reader: Some(session.registered_reader.clone()),
+        })
+    }
+
+    /// Simulates dragging content of `reader` over the view. Starts new drop
+    /// session if `reader` differs from the one in current session. Returns
+    /// operation accepted by Dart.
+    pub async fn simulate_drop_update(
+        &self,
+        reader: Rc<PlatformDataReader>,
+        location: Point,
+        allowed_operations: Vec<DropOperation>,
+    ) -> NativeExtensionsResult<DropOperation> {
+        let session = self.session_for_reader(reader, allowed_operations)?;
+        let event = self.create_drop_event(&session, location, None).await?;
+        let (future, completer) = FutureCompleter::new();
+        self.delegate()?.send_drop_update(
+            self.id,
+            event,
+            Box::new(move |res| completer.complete(res)),
+        );
+        let operation = future.await?;
+        session.last_operation.set(operation);
+        Ok(operation)
+    }
+
+    /// Simulates drop for current session. Returns operation that was
+    /// performed, or `None` if there was no session or Dart did not accept
+    /// the drop.
+    pub async fn simulate_perform_drop(
+        &self,
+        location: Point,
+    ) -> NativeExtensionsResult<DropOperation> {
+        let session = match self.current_session.take() {
+            Some(session) => session,
+            None => return Ok(DropOperation::None),
+        };
+        let operation = session.last_operation.get();
+        if operation == DropOperation::None {
+            self.end_session(&session, true)?;
+            return Ok(DropOperation::None);
+        }
+        let event = self
+            .create_drop_event(&session, location, Some(operation))
+            .await?;
+        let (future, completer) = FutureCompleter::new();
+        self.delegate()?.send_perform_drop(
+            self.id,
+            event,
+            Box::new(move |res| completer.complete(res)),
+        );
+        let res = future.await;
+        self.end_session(&session, false)?;
+        res?;
+        Ok(operation)
+    }
+
+    /// Simulates drag leaving the view.
+    pub fn simulate_drop_leave(&self) -> NativeExtensionsResult<()> {
+        if let Some(session) = self.current_session.take() {
+            self.end_session(&session, true)?;
+        }
+        Ok(())
+    }
+
+    fn end_session(&self, session: &Session, leave: bool) -> NativeExtensionsResult<()> {
+        let delegate = self.delegate()?;
+        if leave {
+            delegate.send_drop_leave(
+                self.id,
+                BaseDropEvent {
+                    session_id: session.id,
+                },
+            );
+        }
+        delegate.send_drop_ended(
+            self.id,
+            BaseDropEvent {
+                session_id: session.id,
+            },
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use crate::{
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    hot_key_manager::{HotKeyCreateRequest, HotKeyHandle, HotKeyManagerDelegate},
+};
+
+pub struct PlatformHotKeyManager {
+    delegate: Weak<dyn HotKeyManagerDelegate>,
+    hot_keys: RefCell<HashMap<HotKeyHandle, HotKeyCreateRequest>>,
+}
+
+impl PlatformHotKeyManager {
+    pub fn new(delegate: Weak<dyn HotKeyManagerDelegate>) -> Self {
+        Self {
+            delegate,
+            hot_keys: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn assign_weak_self(&self, _weak: Weak<PlatformHotKeyManager>) {}
+
+    pub fn create_hot_key(
+        &self,
+        handle: HotKeyHandle,
+        request: HotKeyCreateRequest,
+    ) -> NativeExtensionsResult<()> {
+        self.hot_keys.borrow_mut().insert(handle, request);
+        Ok(())
+    }
+
+    pub fn destroy_hot_key(&self, handle: HotKeyHandle) -> NativeExtensionsResult<()> {
+        self.hot_keys.borrow_mut().remove(&handle);
+        Ok(())
+    }
+
+    /// Currently registered hot keys.
+    pub fn hot_keys(&self) -> Vec<(HotKeyHandle, HotKeyCreateRequest)> {
+        self.hot_keys
+            .borrow()
+            .iter()
+            .map(|(handle, request)| (*handle, request.clone()))
+            .collect()
+    }
+
+    fn delegate_for(
+        &self,
+        handle: HotKeyHandle,
+    ) -> NativeExtensionsResult<Rc<dyn HotKeyManagerDelegate>> {
+        if !self.hot_keys.borrow().contains_key(&handle) {
+            return Err(NativeExtensionsError::OtherError(format!(
+                "hot key {:?} not registered",
+                handle
+            )));
+        }
+        self.delegate
+            .upgrade()
+            .ok_or_else(|| NativeExtensionsError::OtherError("missing hot key delegate".into()))
+    }
+
+    pub fn simulate_hot_key_pressed(&self, handle: HotKeyHandle) -> NativeExtensionsResult<()> {
+        self.delegate_for(handle)?.on_hot_key_pressed(handle);
+        Ok(())
+    }
+
+    pub fn simulate_hot_key_released(&self, handle: HotKeyHandle) -> NativeExtensionsResult<()> {
+        self.delegate_for(handle)?.on_hot_key_released(handle);
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    rc::{Rc, Weak},
+};
+
+use irondash_message_channel::{
+    AsyncMethodHandler, IsolateId, MethodCall, MethodCallError, MethodHandler, PlatformError,
+    PlatformResult, Value,
+};
+use irondash_run_loop::{spawn, RunLoop};
+
+#[cfg(feature = "clipboard-events")]
+use crate::clipboard_events_manager::GetClipboardEventManager;
+#[cfg(feature = "clipboard")]
+use crate::clipboard_reader::GetClipboardReader;
+#[cfg(feature = "clipboard")]
+use crate::clipboard_writer::GetClipboardWriter;
+#[cfg(feature = "drag-drop")]
+use crate::drag_manager::GetDragManager;
+#[cfg(feature = "drag-drop")]
+use crate::drop_manager::GetDropManager;
+#[cfg(feature = "hot-key")]
+use crate::hot_key_manager::GetHotKeyManager;
+#[cfg(feature = "keyboard-layout")]
+use crate::keyboard_layout_manager::GetKeyboardLayoutDelegate;
+#[cfg(feature = "menu")]
+use crate::menu_manager::GetMenuManager;
+use crate::{
+    capabilities::GetCapabilitiesManager, context::Context,
+    data_provider_manager::GetDataProviderManager, diagnostics::GetDiagnosticsManager,
+    log_config_manager::GetLogConfigManager, reader_manager::GetDataReaderManager,
+};
+
+type Responder = Rc<dyn Fn(Value) -> Result<Value, PlatformError>>;
+
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    pub method: String,
+    pub args: Value,
+}
+
+#[derive(Default)]
+struct IsolateState {
+    responders: RefCell<HashMap<String, Responder>>,
+    calls: RefCell<Vec<RecordedCall>>,
+}
+
+thread_local! {
+    static ISOLATES: RefCell<HashMap<IsolateId, Weak<IsolateState>>> = RefCell::new(HashMap::new());
+    // Keep clear of ids that Dart would hand out.
+    static NEXT_ISOLATE_ID: Cell<i64> = const { Cell::new(1 << 48) };
+}
+
+/// Answers call from Rust to Dart if the target is a mock isolate.
+pub(crate) fn mock_isolate_call(
+    isolate: IsolateId,
+    method: &str,
+    args: &Value,
+) -> Option<Result<Value, MethodCallError>> {
+    let state = ISOLATES.with(|i| i.borrow().get(&isolate).and_then(|s| s.upgrade()))?;
+    state.calls.borrow_mut().push(RecordedCall {
+        method: method.into(),
+        args: args.clone(),
+    });
+    // Responder may register other responders or call back into Rust.
+    let responder = state.responders.borrow().get(method).cloned();
+    let res = match responder {
+        Some(responder) => responder(args.clone()).map_err(MethodCallError::PlatformError),
+        None => Ok(Value::Null),
+    };
+    Some(res)
+}
+
+/// In-process stand-in for a Dart isolate. Sends method calls directly to the
+/// handlers attached to current [`Context`] and answers calls from Rust with
+/// scripted responses. Dropping the isolate notifies all handlers the same
+/// way engine shutdown would.
+pub struct MockIsolate {
+    id: IsolateId,
+    state: Rc<IsolateState>,
+}
+
+impl MockIsolate {
+    pub fn new() -> Self {
+        let id = IsolateId(NEXT_ISOLATE_ID.with(|id| {
+            let res = id.get();
+            id.set(res + 1);
+            res
+        }));
+        let state = Rc::new(IsolateState::default());
+        ISOLATES.with(|i| i.borrow_mut().insert(id, Rc::downgrade(&state)));
+        Self { id, state }
+    }
+
+    pub fn id(&self) -> IsolateId {
+        self.id
+    }
+
+    /// Registers response for calls from Rust to given method. Calls without
+    /// responder are answered with `Value::Null`.
+    pub fn on_call<F>(&self, method: &str, responder: F)
+    where
+        F: Fn(Value) -> Result<Value, PlatformError> + 'static,
+    {
+        self.state
+            .responders
+            .borrow_mut()
+            .insert(method.into(), Rc::new(responder));
+    }
+
+    /// Returns calls from Rust received so far.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.state.calls.borrow().clone()
+    }
+
+    /// Returns arguments of calls from Rust to given method.
+    pub fn calls_to(&self, method: &str) -> Vec<Value> {
+        self.state
+            .calls
+            .borrow()
+            .iter()
+            .filter(|c| c.method == method)
+            .map(|c| c.args.clone())
+            .collect()
+    }
+
+    /// Invokes method on handler registered for given channel.
+    pub async fn call_method<V: Into<Value>>(
+        &self,
+        channel: &str,
+        method: &str,
+        args: V,
+    ) -> PlatformResult {
+        let call = MethodCall {
+            method: method.into(),
+            args: args.into(),
+            isolate: self.id,
+        };
+        let context = Context::get();
+        match channel {
+            "CapabilitiesManager" => context.capabilities_manager().on_method_call(call).await,
+            #[cfg(feature = "clipboard-events")]
+            "ClipboardEventManager" => context.clipboard_event_manager().on_method_call(call).await,
+            #[cfg(feature = "clipboard")]
+            "ClipboardReader" => context.clipboard_reader().on_method_call(call).await,
+            #[cfg(feature = "clipboard")]
+            "ClipboardWriter" => context.clipboard_writer().on_method_call(call).await,
+            "DataProviderManager" => context.data_provider_manager().on_method_call(call).await,
+            "DiagnosticsManager" => context.diagnostics_manager().on_method_call(call).await,
+            "DataReaderManager" => context.data_reader_manager().on_method_call(call).await,
+            #[cfg(feature = "drag-drop")]
+            "DragManager" => context.drag_manager().on_method_call(call).await,
+            #[cfg(feature = "drag-drop")]
+            "DropManager" => context.drop_manager().on_method_call(call).await,
+            #[cfg(feature = "menu")]
+            "MenuManager" => context.menu_manager().on_method_call(call).await,
+            #[cfg(feature = "hot-key")]
+            "HotKeyManager" => context.hot_key_manager().on_method_call(call),
+            #[cfg(feature = "keyboard-layout")]
+            "KeyboardLayoutManager" => context.keyboard_map_manager().on_method_call(call),
+            "LogConfig" => context.log_config_manager().on_method_call(call),
+            _ => Err(PlatformError {
+                code: "invalid_channel".into(),
+                message: Some(format!("Unknown channel: {}", channel)),
+                detail: Value::Null,
+            }),
+        }
+    }
+
+    fn notify_destroyed(&self) {
+        let id = self.id;
+        let context = Context::get();
+        AsyncMethodHandler::on_isolate_destroyed(&*context.capabilities_manager(), id);
+        #[cfg(feature = "clipboard-events")]
+        AsyncMethodHandler::on_isolate_destroyed(&*context.clipboard_event_manager(), id);
+        #[cfg(feature = "clipboard")]
+        AsyncMethodHandler::on_isolate_destroyed(&*context.clipboard_reader(), id);
+        #[cfg(feature = "clipboard")]
+        AsyncMethodHandler::on_isolate_destroyed(&*context.clipboard_writer(), id);
+        AsyncMethodHandler::on_isolate_destroyed(&*context.data_provider_manager(), id);
+        AsyncMethodHandler::on_isolate_destroyed(&*context.data_reader_manager(), id);
+        AsyncMethodHandler::on_isolate_destroyed(&*context.diagnostics_manager(), id);
+        #[cfg(feature = "drag-drop")]
+        AsyncMethodHandler::on_isolate_destroyed(&*context.drag_manager(), id);
+        #[cfg(feature = "drag-drop")]
+        AsyncMethodHandler::on_isolate_destroyed(&*context.drop_manager(), id);
+        #[cfg(feature = "menu")]
+        AsyncMethodHandler::on_isolate_destroyed(&*context.menu_manager(), id);
+        #[cfg(feature = "hot-key")]
+        MethodHandler::on_isolate_destroyed(&*context.hot_key_manager(), id);
+        #[cfg(feature = "keyboard-layout")]
+        MethodHandler::on_isolate_destroyed(&*context.keyboard_map_manager(), id);
+        MethodHandler::on_isolate_destroyed(&*context.log_config_manager(), id);
+    }
+}
+
+impl Drop for MockIsolate {
+    fn drop(&mut self) {
+        self.notify_destroyed();
+        ISOLATES.with(|i| i.borrow_mut().remove(&self.id));
+    }
+}
+
+/// Runs the future with fresh [`Context`] on current thread, pumping the run
+/// loop until the future completes. The context is shut down afterwards.
+pub fn run_test<F: Future<Output = ()> + 'static>(f: F) {
+    let _context = Context::new();
+    let done = Rc::new(Cell::new(false));
+    let done_clone = done.clone();
+    spawn(async move {
+        f.await;
+        done_clone.set(true);
+    });
+    while !done.get() {
+        RunLoop::current().platform_run_loop.poll_once();
+    }
+    Context::shutdown();
+}
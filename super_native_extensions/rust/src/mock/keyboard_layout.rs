@@ -0,0 +1,31 @@
+use std::{cell::RefCell, rc::Weak};
+
+use crate::keyboard_layout_manager::{KeyboardLayout, KeyboardLayoutDelegate};
+
+pub struct PlatformKeyboardLayout {
+    current_layout: RefCell<KeyboardLayout>,
+    delegate: Weak<dyn KeyboardLayoutDelegate>,
+}
+
+impl PlatformKeyboardLayout {
+    pub fn new(delegate: Weak<dyn KeyboardLayoutDelegate>) -> Self {
+        Self {
+            current_layout: RefCell::new(KeyboardLayout { keys: Vec::new() }),
+            delegate,
+        }
+    }
+
+    pub fn assign_weak_self(&self, _weak: Weak<PlatformKeyboardLayout>) {}
+
+    pub fn get_current_layout(&self) -> Option<KeyboardLayout> {
+        Some(self.current_layout.borrow().clone())
+    }
+
+    /// Replaces current layout and notifies delegate.
+    pub fn simulate_layout_change(&self, layout: KeyboardLayout) {
+        self.current_layout.replace(layout);
+        if let Some(delegate) = self.delegate.upgrade() {
+            delegate.keyboard_map_did_change();
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+use irondash_message_channel::IsolateId;
+use irondash_run_loop::util::FutureCompleter;
+
+use crate::{
+    api_model::{
+        ImageData, Menu, MenuElement, Point, ShowContextMenuRequest, ShowContextMenuResponse,
+    },
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    menu_manager::{PlatformMenuContextDelegate, PlatformMenuContextId, PlatformMenuDelegate},
+    value_promise::PromiseResult,
+};
+
+use super::util::wait_for_promise;
+
+pub struct PlatformMenu {
+    isolate: IsolateId,
+    delegate: Weak<dyn PlatformMenuDelegate>,
+    menu: Menu,
+}
+
+impl std::fmt::Debug for PlatformMenu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlatformMenu")
+            .field("menu", &self.menu)
+            .finish()
+    }
+}
+
+impl PlatformMenu {
+    pub fn new(
+        isolate: IsolateId,
+        delegate: Weak<dyn PlatformMenuDelegate>,
+        menu: Menu,
+    ) -> NativeExtensionsResult<Rc<Self>> {
+        Ok(Rc::new(Self {
+            isolate,
+            delegate,
+            menu,
+        }))
+    }
+
+    pub fn menu(&self) -> &Menu {
+        &self.menu
+    }
+
+    fn delegate(&self) -> NativeExtensionsResult<Rc<dyn PlatformMenuDelegate>> {
+        self.delegate
+            .upgrade()
+            .ok_or_else(|| NativeExtensionsError::OtherError("missing menu delegate".into()))
+    }
+
+    /// Simulates selecting menu action with given unique id.
+    pub fn simulate_action(&self, action: i64) -> NativeExtensionsResult<()> {
+        self.delegate()?.on_action(self.isolate, action);
+        Ok(())
+    }
+
+    /// Simulates expanding deferred menu element with given unique id.
+    pub async fn simulate_load_deferred(
+        &self,
+        id: i64,
+    ) -> NativeExtensionsResult<Vec<MenuElement>> {
+        self.delegate()?.get_deferred_menu(self.isolate, id).await
+    }
+}
+
+struct VisibleMenu {
+    menu: Rc<PlatformMenu>,
+    completer: FutureCompleter<bool>,
+}
+
+pub struct PlatformMenuContext {
+    id: PlatformMenuContextId,
+    delegate: Weak<dyn PlatformMenuContextDelegate>,
+    preview_images: RefCell<HashMap<i64, ImageData>>,
+    visible_menu: RefCell<Option<VisibleMenu>>,
+}
+
+impl PlatformMenuContext {
+    pub fn new(
+        id: PlatformMenuContextId,
+        _engine_handle: i64,
+        delegate: Weak<dyn PlatformMenuContextDelegate>,
+    ) -> NativeExtensionsResult<Self> {
+        Ok(Self {
+            id,
+            delegate,
+            preview_images: RefCell::new(HashMap::new()),
+            visible_menu: RefCell::new(None),
+        })
+    }
+
+    pub fn assign_weak_self(&self, _weak_self: Weak<Self>) {}
+
+    fn delegate(&self) -> NativeExtensionsResult<Rc<dyn PlatformMenuContextDelegate>> {
+        self.delegate
+            .upgrade()
+            .ok_or_else(|| NativeExtensionsError::OtherError("missing context delegate".into()))
+    }
+
+    pub fn update_preview_image(
+        &self,
+        configuration_id: i64,
+        image_data: ImageData,
+    ) -> NativeExtensionsResult<()> {
+        self.preview_images
+            .borrow_mut()
+            .insert(configuration_id, image_data);
+        Ok(())
+    }
+
+    /// Returns last preview image set for given menu configuration.
+    pub fn preview_image(&self, configuration_id: i64) -> Option<ImageData> {
+        self.preview_images.borrow().get(&configuration_id).cloned()
+    }
+
+    /// Returns menu currently shown through `show_context_menu`.
+    pub fn visible_menu(&self) -> Option<Rc<PlatformMenu>> {
+        self.visible_menu.borrow().as_ref().map(|m| m.menu.clone())
+    }
+
+    pub async fn show_context_menu(
+        &self,
+        request: ShowContextMenuRequest,
+    ) -> NativeExtensionsResult<ShowContextMenuResponse> {
+        let menu = request
+            .menu
+            .ok_or(NativeExtensionsError::PlatformMenuNotFound)?;
+        let (future, completer) = FutureCompleter::new();
+        let previous = self
+            .visible_menu
+            .replace(Some(VisibleMenu { menu, completer }));
+        if let Some(previous) = previous {
+            previous.completer.complete(false);
+        }
+        let item_selected = future.await;
+        Ok(ShowContextMenuResponse { item_selected })
+    }
+
+    /// Dismisses menu shown through `show_context_menu`. If `action` is
+    /// specified it is invoked before the menu is dismissed.
+    pub fn simulate_menu_dismiss(&self, action: Option<i64>) -> NativeExtensionsResult<()> {
+        let visible = self
+            .visible_menu
+            .take()
+            .ok_or(NativeExtensionsError::PlatformMenuNotFound)?;
+        if let Some(action) = action {
+            visible.menu.simulate_action(action)?;
+        }
+        visible.completer.complete(action.is_some());
+        Ok(())
+    }
+
+    /// Simulates platform menu request (i.e. right click). Asks Dart for menu
+    /// configuration and reports menu as shown. Returns configuration id, or
+    /// `None` if there is no menu at given location.
+    pub async fn simulate_menu_request(
+        &self,
+        location: Point,
+    ) -> NativeExtensionsResult<Option<i64>> {
+        let promise = self
+            .delegate()?
+            .get_menu_configuration_for_location(self.id, location);
+        match wait_for_promise(&promise).await {
+            PromiseResult::Ok { value } => {
+                if let Some(image) = value.preview_image {
+                    self.update_preview_image(value.configuration_id, image)?;
+                }
+                self.delegate()?
+                    .on_show_menu(self.id, value.configuration_id);
+                Ok(Some(value.configuration_id))
+            }
+            PromiseResult::Cancelled => Ok(None),
+        }
+    }
+
+    /// Simulates hiding menu shown through `simulate_menu_request`.
+    pub fn simulate_menu_hide(
+        &self,
+        configuration_id: i64,
+        item_selected: bool,
+    ) -> NativeExtensionsResult<()> {
+        self.delegate()?
+            .on_hide_menu(self.id, configuration_id, item_selected);
+        Ok(())
+    }
+}
@@ -1,4 +1,43 @@
+//! In-memory platform implementation that does not depend on any window
+//! system. Enabled by the `mock` cargo feature and used to exercise the
+//! managers in headless environments (CI, unit tests).
+//!
+//! In addition to the regular platform interface, mock types expose
+//! `simulate_*` methods that drive the corresponding delegate callbacks the
+//! same way a real platform would.
+
+mod capabilities;
+#[cfg(feature = "clipboard-events")]
+mod clipboard_events;
+mod data_provider;
+#[cfg(feature = "drag-drop")]
+mod drag;
+#[cfg(feature = "drag-drop")]
+mod drop;
+#[cfg(feature = "hot-key")]
+mod hot_key;
+mod isolate;
+#[cfg(feature = "keyboard-layout")]
+mod keyboard_layout;
+#[cfg(feature = "menu")]
+mod menu;
 mod reader;
-mod writer;
+mod util;
+
+pub use capabilities::*;
+#[cfg(feature = "clipboard-events")]
+pub use clipboard_events::*;
+pub use data_provider::*;
+#[cfg(feature = "drag-drop")]
+pub use drag::*;
+#[cfg(feature = "drag-drop")]
+pub use drop::*;
+#[cfg(feature = "hot-key")]
+pub use hot_key::*;
+pub use isolate::*;
+#[cfg(feature = "keyboard-layout")]
+pub use keyboard_layout::*;
+#[cfg(feature = "menu")]
+pub use menu::*;
 pub use reader::*;
-pub use writer::*;
+pub use util::{next_turn, value_at};
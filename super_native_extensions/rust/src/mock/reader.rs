@@ -1,55 +1,274 @@
-use std::{cell::RefCell, rc::Weak};
+use std::{
+    cell::RefCell,
+    fs,
+    path::PathBuf,
+    rc::{Rc, Weak},
+    sync::Arc,
+};
 
-use nativeshell_core::{util::Late, Value};
+use async_trait::async_trait;
+use irondash_message_channel::Value;
 
-use crate::error::NativeExtensionsResult;
+use crate::{
+    api_model::{ClipboardIntent, DataProvider, DataRepresentation},
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::{FormatConverter, HtmlToText, RtfToText},
+    reader_manager::{ReadProgress, VirtualFileReader},
+    util::get_target_path,
+};
+
+use super::{data_provider::clipboard_providers, PlatformDataProvider};
 
 thread_local! {
-    pub static READERS: RefCell<Vec<Weak<PlatformClipboardReader>>> = RefCell::new(Vec::new());
+    /// Live readers. Can be used by tests to verify that readers are disposed.
+    pub static READERS: RefCell<Vec<Weak<PlatformDataReader>>> = const { RefCell::new(Vec::new()) };
 }
 
-pub struct PlatformClipboardReader {
-    weak_self: Late<Weak<Self>>,
+enum Item {
+    /// Item backed by data provider from this process.
+    Provider(Rc<PlatformDataProvider>),
+    /// Item with static content, used to simulate other applications.
+    Static(DataProvider),
 }
 
-impl PlatformClipboardReader {
+impl Item {
+    fn data(&self) -> &DataProvider {
+        match self {
+            Item::Provider(provider) => provider.data(),
+            Item::Static(data) => data,
+        }
+    }
+
+    async fn get_value(&self, format: &str) -> Option<Value> {
+        match self {
+            Item::Provider(provider) => provider.get_value(format).await,
+            Item::Static(data) => data.representations.iter().find_map(|r| match r {
+                DataRepresentation::Simple { format: f, data } if f == format => Some(data.clone()),
+                _ => None,
+            }),
+        }
+    }
+
+    fn provider(&self) -> Option<&Rc<PlatformDataProvider>> {
+        match self {
+            Item::Provider(provider) => Some(provider),
+            Item::Static(_) => None,
+        }
+    }
+}
+
+/// Converters used to synthesize formats not provided by items.
+pub fn platform_format_converters() -> Vec<Rc<dyn FormatConverter>> {
+    vec![
+        Rc::new(HtmlToText::new(&["text/html"], "text/plain")),
+        Rc::new(RtfToText::new(&["text/rtf"], "text/plain")),
+    ]
+}
+
+pub struct PlatformDataReader {
+    items: Vec<Item>,
+}
+
+impl PlatformDataReader {
+    fn new(items: Vec<Item>) -> Rc<Self> {
+        let res = Rc::new(Self { items });
+        READERS.with(|r| r.borrow_mut().push(Rc::downgrade(&res)));
+        res
+    }
+
+    pub fn new_clipboard_reader() -> NativeExtensionsResult<Rc<Self>> {
+        let items = clipboard_providers()
+            .into_iter()
+            .map(Item::Provider)
+            .collect();
+        Ok(Self::new(items))
+    }
+
+    /// Creates reader for items provided by data providers in this process
+    /// (i.e. for a drag session started by the application).
+    pub fn new_with_providers(providers: Vec<Rc<PlatformDataProvider>>) -> Rc<Self> {
+        Self::new(providers.into_iter().map(Item::Provider).collect())
+    }
+
+    /// Creates reader with static content. Only simple representations are
+    /// readable; This is meant to simulate data coming from other applications.
+    pub fn new_with_data(data: Vec<DataProvider>) -> Rc<Self> {
+        Self::new(data.into_iter().map(Item::Static).collect())
+    }
+
+    fn item(&self, item: i64) -> Option<&Item> {
+        self.items.get(item as usize)
+    }
+
     pub async fn get_items(&self) -> NativeExtensionsResult<Vec<i64>> {
-        Ok(vec![])
+        Ok((0..self.items.len() as i64).collect())
     }
 
-    pub async fn get_types_for_item(&self, _item: i64) -> NativeExtensionsResult<Vec<String>> {
-        Ok(vec![])
+    pub async fn get_formats_for_item(&self, item: i64) -> NativeExtensionsResult<Vec<String>> {
+        Ok(self
+            .item(item)
+            .map(|i| {
+                i.data()
+                    .representations
+                    .iter()
+                    .map(|r| r.format().to_owned())
+                    .collect()
+            })
+            .unwrap_or_default())
     }
 
-    pub async fn get_data_for_item(
+    pub async fn get_suggested_name_for_item(
+        &self,
+        item: i64,
+    ) -> NativeExtensionsResult<Option<String>> {
+        Ok(self
+            .item(item)
+            .and_then(|i| i.data().suggested_name.clone()))
+    }
+
+    pub async fn get_clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        Ok(self.items.iter().find_map(|i| i.data().clipboard_intent))
+    }
+
+    pub async fn get_local_path_for_item(
+        &self,
+        _item: i64,
+    ) -> NativeExtensionsResult<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    pub async fn get_item_format_for_uri(
         &self,
         _item: i64,
-        _data_type: String,
+    ) -> NativeExtensionsResult<Option<String>> {
+        Ok(None)
+    }
+
+    pub async fn get_data_for_item(
+        &self,
+        item: i64,
+        data_type: String,
+        _progress: Option<Arc<ReadProgress>>,
     ) -> NativeExtensionsResult<Value> {
-        Ok(Value::Null)
+        match self.item(item) {
+            Some(item) => Ok(item.get_value(&data_type).await.unwrap_or(Value::Null)),
+            None => Ok(Value::Null),
+        }
+    }
+
+    pub fn item_format_is_synthesized(
+        &self,
+        _item: i64,
+        _format: &str,
+    ) -> NativeExtensionsResult<bool> {
+        Ok(false)
+    }
+
+    fn has_virtual_file(&self, item: i64, format: &str) -> bool {
+        self.item(item)
+            .and_then(|i| i.provider())
+            .map(|p| p.virtual_file_id(format).is_some())
+            .unwrap_or(false)
+    }
+
+    pub async fn can_copy_virtual_file_for_item(
+        &self,
+        item: i64,
+        format: &str,
+    ) -> NativeExtensionsResult<bool> {
+        Ok(self.has_virtual_file(item, format))
+    }
+
+    pub async fn can_read_virtual_file_for_item(
+        &self,
+        item: i64,
+        format: &str,
+    ) -> NativeExtensionsResult<bool> {
+        Ok(self.has_virtual_file(item, format))
     }
 
-    pub fn new_default() -> NativeExtensionsResult<Self> {
-        Ok(Self {
-            weak_self: Late::new(),
-        })
+    async fn receive_virtual_file(
+        &self,
+        item: i64,
+        format: &str,
+        progress: Arc<ReadProgress>,
+    ) -> NativeExtensionsResult<Vec<u8>> {
+        let provider = self
+            .item(item)
+            .and_then(|i| i.provider())
+            .ok_or(NativeExtensionsError::UnsupportedOperation)?;
+        provider.receive_virtual_file(format, progress).await
+    }
+
+    pub async fn create_virtual_file_reader_for_item(
+        &self,
+        item: i64,
+        format: &str,
+        progress: Arc<ReadProgress>,
+    ) -> NativeExtensionsResult<Option<Rc<dyn VirtualFileReader>>> {
+        if !self.has_virtual_file(item, format) {
+            return Ok(None);
+        }
+        let data = self.receive_virtual_file(item, format, progress).await?;
+        let file_name = self.get_suggested_name_for_item(item).await?;
+        Ok(Some(Rc::new(MemoryVirtualFileReader {
+            file_size: data.len() as i64,
+            data: RefCell::new(Some(data)),
+            file_name,
+        })))
     }
 
-    pub fn assign_weak_self(&self, weak_self: Weak<PlatformClipboardReader>) {
-        self.weak_self.set(weak_self.clone());
-        READERS.with(|f| {
-            f.borrow_mut().push(weak_self);
-        })
+    pub async fn copy_virtual_file_for_item(
+        &self,
+        item: i64,
+        format: &str,
+        target_folder: PathBuf,
+        progress: Arc<ReadProgress>,
+    ) -> NativeExtensionsResult<PathBuf> {
+        let data = self.receive_virtual_file(item, format, progress).await?;
+        let file_name = self
+            .get_suggested_name_for_item(item)
+            .await?
+            .unwrap_or_else(|| "Untitled".into());
+        let path = get_target_path(&target_folder, &file_name);
+        fs::write(&path, data)?;
+        Ok(path)
     }
 }
 
-impl Drop for PlatformClipboardReader {
+impl Drop for PlatformDataReader {
     fn drop(&mut self) {
         READERS
-            .try_with(|f| {
-                f.borrow_mut()
-                    .retain(|a| a.as_ptr() != self.weak_self.as_ptr());
+            .try_with(|r| {
+                r.borrow_mut().retain(|r| r.as_ptr() != self as *const Self);
             })
             .ok();
     }
 }
+
+/// Virtual file reader that returns the whole content in one chunk.
+struct MemoryVirtualFileReader {
+    data: RefCell<Option<Vec<u8>>>,
+    file_size: i64,
+    file_name: Option<String>,
+}
+
+#[async_trait(?Send)]
+impl VirtualFileReader for MemoryVirtualFileReader {
+    async fn read_next(&self) -> NativeExtensionsResult<Vec<u8>> {
+        Ok(self.data.borrow_mut().take().unwrap_or_default())
+    }
+
+    fn file_size(&self) -> NativeExtensionsResult<Option<i64>> {
+        Ok(Some(self.file_size))
+    }
+
+    fn file_name(&self) -> Option<String> {
+        self.file_name.clone()
+    }
+
+    fn close(&self) -> NativeExtensionsResult<()> {
+        self.data.replace(None);
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use irondash_message_channel::Value;
+use irondash_run_loop::{util::FutureCompleter, RunLoop};
+
+use crate::value_promise::Promise;
+
+/// Waits for one run loop turn.
+pub async fn next_turn() {
+    let (future, completer) = FutureCompleter::new();
+    RunLoop::current()
+        .schedule_next(move || completer.complete(()))
+        .detach();
+    future.await;
+}
+
+/// Returns value at given path of string keys in nested maps, i.e. arguments
+/// of recorded Rust to Dart calls. Panics if any key is missing.
+pub fn value_at<'a>(value: &'a Value, path: &[&str]) -> &'a Value {
+    path.iter().fold(value, |value, key| match value {
+        Value::Map(map) => map
+            .iter()
+            .find(|(k, _)| matches!(k, Value::String(k) if k == key))
+            .map(|(_, v)| v)
+            .unwrap_or_else(|| panic!("missing key {key}")),
+        other => panic!("expected map, got {other:?}"),
+    })
+}
+
+/// Waits for the promise to be fulfilled without blocking the run loop.
+pub(super) async fn wait_for_promise<T>(promise: &Promise<T>) -> T {
+    loop {
+        if let Some(result) = promise.try_take() {
+            return result;
+        }
+        next_turn().await;
+    }
+}
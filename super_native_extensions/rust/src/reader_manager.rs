@@ -15,8 +15,12 @@ use irondash_message_channel::{
 use irondash_run_loop::{util::Capsule, RunLoop, RunLoopSender};
 
 use crate::{
+    api_model::ClipboardIntent,
     context::Context,
+    diagnostics::{ResourceKind, ResourceOrigin, ResourceVisitor},
     error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::{item_data, item_format_is_synthesized, item_formats},
+    invoker::AsyncInvoker,
     log::OkLog,
     platform::PlatformDataReader,
     util::{DropNotifier, NextId},
@@ -33,18 +37,30 @@ impl From<i64> for DataReaderId {
 
 pub struct DataReaderManager {
     weak_self: Late<Weak<Self>>,
-    invoker: Late<AsyncMethodInvoker>,
+    invoker: Late<AsyncInvoker>,
     next_id: Cell<i64>,
     readers: RefCell<HashMap<DataReaderId, ReaderEntry>>,
-    progresses: RefCell<HashMap<(IsolateId, i64), sync::Weak<ReadProgress>>>,
-    virtual_file_readers: RefCell<HashMap<(IsolateId, i64), Rc<dyn VirtualFileReader>>>,
+    progresses: RefCell<HashMap<(IsolateId, i64), ProgressEntry>>,
+    virtual_file_readers: RefCell<HashMap<(IsolateId, i64), VirtualFileReaderEntry>>,
 }
 
 struct ReaderEntry {
+    isolate_id: IsolateId,
+    origin: ResourceOrigin,
     platform_reader: Rc<PlatformDataReader>,
     _finalizable_handle: Arc<FinalizableHandle>,
 }
 
+struct ProgressEntry {
+    origin: ResourceOrigin,
+    progress: sync::Weak<ReadProgress>,
+}
+
+struct VirtualFileReaderEntry {
+    origin: ResourceOrigin,
+    reader: Rc<dyn VirtualFileReader>,
+}
+
 pub trait GetDataReaderManager {
     fn data_reader_manager(&self) -> Rc<DataReaderManager>;
 }
@@ -152,7 +168,12 @@ impl DataReaderManager {
         .register("DataReaderManager")
     }
 
-    fn new_read_progress(&self, isolate_id: IsolateId, progress_id: i64) -> Arc<ReadProgress> {
+    fn new_read_progress(
+        &self,
+        isolate_id: IsolateId,
+        progress_id: i64,
+        tag: &str,
+    ) -> Arc<ReadProgress> {
         #[derive(IntoValue)]
         #[irondash(rename_all = "camelCase")]
         struct SetProgressCancellable {
@@ -207,9 +228,13 @@ impl DataReaderManager {
                 }
             },
         ));
-        self.progresses
-            .borrow_mut()
-            .insert((isolate_id, progress_id), Arc::downgrade(&res));
+        self.progresses.borrow_mut().insert(
+            (isolate_id, progress_id),
+            ProgressEntry {
+                origin: ResourceOrigin::new(None, tag),
+                progress: Arc::downgrade(&res),
+            },
+        );
         res
     }
 
@@ -217,6 +242,7 @@ impl DataReaderManager {
         &self,
         platform_reader: Rc<PlatformDataReader>,
         isolate_id: IsolateId,
+        origin: ResourceOrigin,
     ) -> RegisteredDataReader {
         let id: DataReaderId = self.next_id.next_id().into();
         let weak_self = self.weak_self.clone();
@@ -229,6 +255,8 @@ impl DataReaderManager {
         self.readers.borrow_mut().insert(
             id,
             ReaderEntry {
+                isolate_id,
+                origin,
                 platform_reader,
                 _finalizable_handle: finalizable_handle.clone(),
             },
@@ -257,13 +285,30 @@ impl DataReaderManager {
         self.get_reader(reader)?.get_items().await
     }
 
+    async fn get_clipboard_intent(
+        &self,
+        reader: DataReaderId,
+    ) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        self.get_reader(reader)?.get_clipboard_intent().await
+    }
+
+    /// Returns local path of file item. Paths that are not valid UTF-8 can
+    /// not be represented in Dart and are omitted.
+    async fn get_item_local_path(
+        &self,
+        request: ItemLocalPathRequest,
+    ) -> NativeExtensionsResult<Option<String>> {
+        let reader = self.get_reader(request.reader_handle)?;
+        let path = reader.get_local_path_for_item(request.item_handle).await?;
+        Ok(path.and_then(|p| p.into_os_string().into_string().ok()))
+    }
+
     async fn get_item_formats(
         &self,
         request: ItemFormatsRequest,
     ) -> NativeExtensionsResult<Vec<String>> {
-        self.get_reader(request.reader_handle)?
-            .get_formats_for_item(request.item_handle)
-            .await
+        let reader = self.get_reader(request.reader_handle)?;
+        item_formats(&reader, request.item_handle).await
     }
 
     async fn get_item_info(
@@ -274,12 +319,12 @@ impl DataReaderManager {
         let reader = self.get_reader(request.reader_handle)?;
         let start = std::time::Instant::now();
         for item_handle in request.item_handles {
-            let formats = reader.get_formats_for_item(item_handle).await?;
+            let formats = item_formats(&reader, item_handle).await?;
             let mut synthesized_formats = Vec::new();
             let mut read_virtual_file_formats = Vec::new();
             let mut copy_virtual_file_formats = Vec::new();
             for format in &formats {
-                if reader.item_format_is_synthesized(item_handle, format)? {
+                if item_format_is_synthesized(&reader, item_handle, format).await? {
                     synthesized_formats.push(format.clone());
                 }
                 if reader
@@ -326,10 +371,11 @@ impl DataReaderManager {
         request: ItemDataRequest,
     ) -> NativeExtensionsResult<Value> {
         let reader = self.get_reader(request.reader_handle)?;
-        let progress = self.new_read_progress(isolate_id, request.progress_id);
-        reader
-            .get_data_for_item(request.item_handle, request.format, Some(progress))
+        let progress = self.new_read_progress(isolate_id, request.progress_id, "getItemData");
+        let format = request.format.clone();
+        item_data(&reader, request.item_handle, request.format, Some(progress))
             .await
+            .map_err(|e| e.for_item(request.item_handle, Some(&format)))
     }
 
     fn cancel_progress(
@@ -341,7 +387,7 @@ impl DataReaderManager {
             .progresses
             .borrow_mut()
             .remove(&(isolate_id, progress_id));
-        if let Some(progress) = progress.and_then(|p| p.upgrade()) {
+        if let Some(progress) = progress.and_then(|p| p.progress.upgrade()) {
             progress.cancel();
         }
         Ok(())
@@ -353,18 +399,24 @@ impl DataReaderManager {
         request: VirtualFileReaderRequest,
     ) -> NativeExtensionsResult<VirtualFileReaderResponse> {
         let reader = self.get_reader(request.reader_handle)?;
-        let progress = self.new_read_progress(isolate_id, request.progress_id);
+        let progress =
+            self.new_read_progress(isolate_id, request.progress_id, "virtualFileReaderCreate");
         let res = reader
             .create_virtual_file_reader_for_item(request.item_handle, &request.format, progress)
-            .await?;
+            .await
+            .map_err(|e| e.for_item(request.item_handle, Some(&request.format)))?;
         match res {
             Some(reader) => {
                 let reader_handle = self.next_id.next_id();
                 let file_size = reader.file_size()?;
                 let file_name = reader.file_name();
-                self.virtual_file_readers
-                    .borrow_mut()
-                    .insert((isolate_id, reader_handle), reader);
+                self.virtual_file_readers.borrow_mut().insert(
+                    (isolate_id, reader_handle),
+                    VirtualFileReaderEntry {
+                        origin: ResourceOrigin::new(None, "virtualFileReaderCreate"),
+                        reader,
+                    },
+                );
                 Ok(VirtualFileReaderResponse {
                     reader_handle,
                     file_name,
@@ -386,7 +438,7 @@ impl DataReaderManager {
             .virtual_file_readers
             .borrow()
             .get(&(isolate_id, virtual_reader_id))
-            .cloned();
+            .map(|e| e.reader.clone());
         match reader {
             Some(reader) => reader.read_next().await.map(Some),
             None => Ok(None),
@@ -402,8 +454,8 @@ impl DataReaderManager {
             .virtual_file_readers
             .borrow_mut()
             .remove(&(isolate_id, virtual_reader_id));
-        if let Some(reader) = reader {
-            reader.close()?;
+        if let Some(entry) = reader {
+            entry.reader.close()?;
         }
         Ok(())
     }
@@ -414,7 +466,7 @@ impl DataReaderManager {
         request: VirtualFileCopyRequest,
     ) -> NativeExtensionsResult<String> {
         let reader = self.get_reader(request.reader_handle)?;
-        let progress = self.new_read_progress(isolate_id, request.progress_id);
+        let progress = self.new_read_progress(isolate_id, request.progress_id, "copyVirtualFile");
         let res = reader
             .copy_virtual_file_for_item(
                 request.item_handle,
@@ -422,9 +474,22 @@ impl DataReaderManager {
                 request.target_folder.into(),
                 progress,
             )
-            .await?;
+            .await
+            .map_err(|e| e.for_item(request.item_handle, Some(&request.format)))?;
         Ok(res.to_string_lossy().into_owned())
     }
+
+    pub fn visit_resources(&self, visitor: &mut dyn ResourceVisitor) {
+        for entry in self.readers.borrow().values() {
+            visitor.visit(entry.isolate_id, ResourceKind::Reader, &entry.origin);
+        }
+        for ((isolate_id, _), entry) in self.progresses.borrow().iter() {
+            visitor.visit(*isolate_id, ResourceKind::ReadProgress, &entry.origin);
+        }
+        for ((isolate_id, _), entry) in self.virtual_file_readers.borrow().iter() {
+            visitor.visit(*isolate_id, ResourceKind::VirtualFileReader, &entry.origin);
+        }
+    }
 }
 
 #[derive(IntoValue, TryFromValue, Debug, Clone)]
@@ -434,6 +499,12 @@ pub struct RegisteredDataReader {
     finalizable_handle: Value,
 }
 
+impl RegisteredDataReader {
+    pub fn handle(&self) -> i64 {
+        self.handle.0
+    }
+}
+
 #[derive(TryFromValue)]
 #[irondash(rename_all = "camelCase")]
 struct ItemFormatsRequest {
@@ -441,6 +512,13 @@ struct ItemFormatsRequest {
     reader_handle: DataReaderId,
 }
 
+#[derive(TryFromValue)]
+#[irondash(rename_all = "camelCase")]
+struct ItemLocalPathRequest {
+    item_handle: i64,
+    reader_handle: DataReaderId,
+}
+
 #[derive(TryFromValue)]
 #[irondash(rename_all = "camelCase")]
 struct ItemDataRequest {
@@ -510,6 +588,19 @@ struct ItemInfoResponse {
     items: Vec<ItemInfo>,
 }
 
+impl Drop for DataReaderManager {
+    fn drop(&mut self) {
+        for entry in self.progresses.get_mut().values() {
+            if let Some(progress) = entry.progress.upgrade() {
+                progress.cancel();
+            }
+        }
+        for entry in self.virtual_file_readers.get_mut().values() {
+            entry.reader.close().ok_log();
+        }
+    }
+}
+
 #[async_trait(?Send)]
 pub trait VirtualFileReader {
     async fn read_next(&self) -> NativeExtensionsResult<Vec<u8>>;
@@ -525,14 +616,14 @@ impl AsyncMethodHandler for DataReaderManager {
     }
 
     fn assign_invoker(&self, invoker: AsyncMethodInvoker) {
-        self.invoker.set(invoker);
+        self.invoker.set(invoker.into());
     }
 
     fn on_isolate_destroyed(&self, destroyed_isolate_id: IsolateId) {
         let mut progresses = self.progresses.borrow_mut();
-        progresses.retain(|(isolate_id, _), progress| {
+        progresses.retain(|(isolate_id, _), entry| {
             if *isolate_id == destroyed_isolate_id {
-                if let Some(progress) = progress.upgrade() {
+                if let Some(progress) = entry.progress.upgrade() {
                     progress.cancel();
                 }
                 false
@@ -542,9 +633,9 @@ impl AsyncMethodHandler for DataReaderManager {
         });
 
         let mut readers = self.virtual_file_readers.borrow_mut();
-        readers.retain(|(isolate_id, _), reader| {
+        readers.retain(|(isolate_id, _), entry| {
             if *isolate_id == destroyed_isolate_id {
-                reader.close().ok_log();
+                entry.reader.close().ok_log();
                 false
             } else {
                 true
@@ -561,10 +652,18 @@ impl AsyncMethodHandler for DataReaderManager {
                 .get_items(call.args.try_into()?)
                 .await
                 .into_platform_result(),
+            "getClipboardIntent" => self
+                .get_clipboard_intent(call.args.try_into()?)
+                .await
+                .into_platform_result(),
             "getItemFormats" => self
                 .get_item_formats(call.args.try_into()?)
                 .await
                 .into_platform_result(),
+            "getItemLocalPath" => self
+                .get_item_local_path(call.args.try_into()?)
+                .await
+                .into_platform_result(),
             "getItemData" => self
                 .get_item_data(call.isolate, call.args.try_into()?)
                 .await
@@ -600,85 +699,209 @@ impl AsyncMethodHandler for DataReaderManager {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::ClipboardReaderManager;
-//     use crate::{platform::READERS, reader_manager::NewClipboardReaderResult};
-//     use nativeshell_core::{Context, FinalizableHandle, GetMessageChannel, MockIsolate, Value};
-//     use std::{sync::Arc, time::Duration};
-
-//     async fn test_dispose_main() {
-//         let _reader_manager = ClipboardReaderManager::new();
-//         let context = Context::get();
-//         let channel = "ClipboardReaderManager";
-
-//         let isolate_1 = MockIsolate::new();
-//         let isolate_1 = isolate_1.attach(&context.message_channel());
-
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 0);
-
-//         //
-//         // Finalizable handle
-//         //
-
-//         let reader_id: NewClipboardReaderResult = isolate_1
-//             .call_method_async(channel, "newDefaultReader", Value::Null)
-//             .await
-//             .unwrap()
-//             .try_into()
-//             .unwrap();
-
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 1);
+#[cfg(all(test, feature = "mock", feature = "clipboard"))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use irondash_message_channel::{IntoValue, Value};
+
+    use super::{DataReaderId, GetDataReaderManager, ReadProgress, RegisteredDataReader};
+    use crate::{
+        api_model::{DataProvider, DataProviderId, DataRepresentation},
+        clipboard_reader::NewClipboardReaderRequest,
+        context::Context,
+        data_provider_manager::RegisterDataProviderRequest,
+        platform::{clear_clipboard, next_turn, run_test, MockIsolate, READERS},
+    };
+
+    #[derive(IntoValue)]
+    #[irondash(rename_all = "camelCase")]
+    struct ItemDataRequest {
+        item_handle: i64,
+        reader_handle: DataReaderId,
+        format: String,
+        progress_id: i64,
+    }
 
-//         let handle: Arc<FinalizableHandle> = reader_id.finalizable_handle.try_into().unwrap();
-//         // Simulate finalizing handle
-//         handle.finalize();
+    #[derive(IntoValue)]
+    #[irondash(tag = "type", rename_all = "camelCase")]
+    enum LazyDataResult {
+        Ok { value: Value },
+    }
 
-//         // wait one run loop turn
-//         context.run_loop().wait(Duration::from_secs(0)).await;
+    fn reader_count() -> usize {
+        READERS.with(|r| r.borrow().len())
+    }
 
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 0);
+    async fn new_clipboard_reader(isolate: &MockIsolate) -> RegisteredDataReader {
+        isolate
+            .call_method(
+                "ClipboardReader",
+                "newClipboardReader",
+                NewClipboardReaderRequest {
+                    creation_site: None,
+                },
+            )
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
 
-//         //
-//         // disposeReader call
-//         //
+    #[test]
+    fn test_dispose_reader() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            assert_eq!(reader_count(), 0);
 
-//         let reader_id: NewClipboardReaderResult = isolate_1
-//             .call_method_async(channel, "newDefaultReader", Value::Null)
-//             .await
-//             .unwrap()
-//             .try_into()
-//             .unwrap();
+            let reader = new_clipboard_reader(&isolate).await;
+            assert_eq!(reader_count(), 1);
 
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 1);
+            isolate
+                .call_method("DataReaderManager", "disposeReader", reader.handle)
+                .await
+                .unwrap();
+            assert_eq!(reader_count(), 0);
+        });
+    }
 
-//         isolate_1
-//             .call_method_async(channel, "disposeReader", reader_id.handle.into())
-//             .await
-//             .unwrap();
+    #[test]
+    fn test_finalize_reader() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let reader = new_clipboard_reader(&isolate).await;
+            assert_eq!(reader_count(), 1);
+
+            // Simulate Dart garbage collecting the reader.
+            match &reader.finalizable_handle {
+                Value::FinalizableHandle(handle) => handle.finalize(),
+                other => panic!("Unexpected handle value: {:?}", other),
+            }
+            next_turn().await;
+            assert_eq!(reader_count(), 0);
+        });
+    }
 
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 0);
+    /// Returns progress of a pending read and flag set when it is cancelled.
+    fn cancellable_progress(
+        isolate: &MockIsolate,
+        progress_id: i64,
+    ) -> (Arc<ReadProgress>, Arc<AtomicBool>) {
+        let manager = Context::get().data_reader_manager();
+        let progress = manager.new_read_progress(isolate.id(), progress_id, "test");
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelled_clone = cancelled.clone();
+        progress.set_cancellation_handler(Some(Box::new(move || {
+            cancelled_clone.store(true, Ordering::SeqCst);
+        })));
+        (progress, cancelled)
+    }
 
-//         //
-//         // Removing isolate
-//         //
+    fn progress_count() -> usize {
+        Context::get()
+            .data_reader_manager()
+            .progresses
+            .borrow()
+            .len()
+    }
 
-//         isolate_1
-//             .call_method_async(channel, "newDefaultReader", Value::Null)
-//             .await
-//             .unwrap();
+    #[test]
+    fn test_cancel_progress() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            let (_progress, cancelled) = cancellable_progress(&isolate, 7);
+            assert_eq!(isolate.calls_to("setProgressCancellable").len(), 1);
+            assert_eq!(progress_count(), 1);
 
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 1);
+            isolate
+                .call_method("DataReaderManager", "cancelProgress", 7i64)
+                .await
+                .unwrap();
+            assert!(cancelled.load(Ordering::SeqCst));
+            assert_eq!(progress_count(), 0);
+        });
+    }
 
-//         drop(isolate_1);
+    #[test]
+    fn test_isolate_destroyed() {
+        run_test(async {
+            let isolate_1 = MockIsolate::new();
+            let isolate_2 = MockIsolate::new();
+            let (_progress_1, cancelled_1) = cancellable_progress(&isolate_1, 1);
+            let (_progress_2, cancelled_2) = cancellable_progress(&isolate_2, 1);
+            assert_eq!(progress_count(), 2);
+
+            drop(isolate_1);
+            assert!(cancelled_1.load(Ordering::SeqCst));
+            assert!(!cancelled_2.load(Ordering::SeqCst));
+            assert_eq!(progress_count(), 1);
+
+            drop(isolate_2);
+            assert!(cancelled_2.load(Ordering::SeqCst));
+            assert_eq!(progress_count(), 0);
+        });
+    }
 
-//         context.run_loop().wait(Duration::from_secs(0)).await;
+    #[test]
+    fn test_lazy_data() {
+        run_test(async {
+            let isolate = MockIsolate::new();
+            isolate.on_call("getLazyData", |_| {
+                Ok(LazyDataResult::Ok {
+                    value: Value::String("lazy".into()),
+                }
+                .into())
+            });
 
-//         assert_eq!(READERS.with(|c| c.borrow().len()), 0);
-//     }
+            let provider = DataProvider {
+                representations: vec![DataRepresentation::Lazy {
+                    id: 1.into(),
+                    format: "text/plain".into(),
+                }],
+                suggested_name: None,
+                clipboard_intent: None,
+            };
+            let provider_id: DataProviderId = isolate
+                .call_method(
+                    "DataProviderManager",
+                    "registerDataProvider",
+                    RegisterDataProviderRequest {
+                        provider,
+                        creation_site: None,
+                    },
+                )
+                .await
+                .unwrap()
+                .try_into()
+                .unwrap();
+            isolate
+                .call_method("ClipboardWriter", "writeToClipboard", vec![provider_id])
+                .await
+                .unwrap();
+
+            let reader = new_clipboard_reader(&isolate).await;
+            let data = isolate
+                .call_method(
+                    "DataReaderManager",
+                    "getItemData",
+                    ItemDataRequest {
+                        item_handle: 0,
+                        reader_handle: reader.handle,
+                        format: "text/plain".into(),
+                        progress_id: 1,
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(data, Value::String("lazy".into()));
+            assert_eq!(isolate.calls_to("getLazyData").len(), 1);
 
-//     #[test]
-//     fn test_dispose() {
-//         Context::run_test(test_dispose_main());
-//     }
-// }
+            // Replacing clipboard content releases the provider.
+            clear_clipboard();
+            assert_eq!(isolate.calls_to("releaseDataProvider").len(), 1);
+        });
+    }
+}
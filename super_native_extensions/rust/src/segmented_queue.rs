@@ -5,13 +5,32 @@ use std::{
     io,
     ops::Deref,
     path::PathBuf,
-    sync::{Arc, Condvar, Mutex},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
 };
 
 use rand::{distributions::Alphanumeric, Rng};
 
 use crate::log::OkLog;
 
+static MEMORY_BYTES: AtomicUsize = AtomicUsize::new(0);
+static FILE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Amount of data currently buffered by all segmented queues.
+pub struct BufferedBytes {
+    pub memory: usize,
+    pub file: usize,
+}
+
+pub fn buffered_bytes() -> BufferedBytes {
+    BufferedBytes {
+        memory: MEMORY_BYTES.load(Ordering::Relaxed),
+        file: FILE_BYTES.load(Ordering::Relaxed),
+    }
+}
+
 trait Segment {
     /// Writes data to segment. Error is returned if segment already reached
     /// or exceeded its capacity.
@@ -62,6 +81,7 @@ impl Segment for MemorySegment {
             return Err(());
         }
         inner.data.extend_from_slice(data);
+        MEMORY_BYTES.fetch_add(data.len(), Ordering::Relaxed);
         inner.completed |= inner.data.len() >= self.max_size;
         self.condition.notify_all();
         Ok(())
@@ -83,6 +103,7 @@ impl Segment for MemorySegment {
                 inner.read_position += to_read;
                 return res;
             } else if inner.completed {
+                MEMORY_BYTES.fetch_sub(inner.data.len(), Ordering::Relaxed);
                 inner.data.clear();
                 return Vec::new();
             } else {
@@ -96,6 +117,14 @@ impl Segment for MemorySegment {
     }
 }
 
+impl Drop for MemorySegment {
+    fn drop(&mut self) {
+        if let Ok(inner) = self.inner.get_mut() {
+            MEMORY_BYTES.fetch_sub(inner.data.len(), Ordering::Relaxed);
+        }
+    }
+}
+
 struct FileHolder {
     file: File,
     path: PathBuf,
@@ -149,6 +178,14 @@ struct FileSegmentInner {
     completed: bool,
 }
 
+impl FileSegmentInner {
+    fn release_file(&mut self) {
+        if self.file.take().is_some() {
+            FILE_BYTES.fetch_sub(self.write_position as usize, Ordering::Relaxed);
+        }
+    }
+}
+
 struct FileSegment {
     max_file_length: u64,
     inner: Mutex<FileSegmentInner>,
@@ -201,6 +238,7 @@ impl Segment for FileSegment {
                         file.write_all_at(data, inner.write_position).ok();
                     }
                     inner.write_position += data.len() as u64;
+                    FILE_BYTES.fetch_add(data.len(), Ordering::Relaxed);
                     inner.completed |= inner.write_position >= self.max_file_length;
                     self.condition.notify_all();
                     Ok(())
@@ -214,7 +252,7 @@ impl Segment for FileSegment {
         let mut inner = self.inner.lock().unwrap();
         inner.completed = true;
         if inner.read_position >= inner.write_position {
-            inner.file.take();
+            inner.release_file();
         }
         self.condition.notify_all();
     }
@@ -236,7 +274,7 @@ impl Segment for FileSegment {
                     None => return Vec::new(),
                 }
             } else if inner.completed {
-                inner.file.take();
+                inner.release_file();
                 return Vec::new();
             } else {
                 inner = self.condition.wait(inner).unwrap();
@@ -249,6 +287,14 @@ impl Segment for FileSegment {
     }
 }
 
+impl Drop for FileSegment {
+    fn drop(&mut self) {
+        if let Ok(inner) = self.inner.get_mut() {
+            inner.release_file();
+        }
+    }
+}
+
 type BoxedSegment = Box<dyn Segment + Send + Sync>;
 
 struct QueueStateInner {
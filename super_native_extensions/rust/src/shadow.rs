@@ -1,47 +1,104 @@
 use crate::{
-    api_model::{ImageData, TargettedImage},
-    blur::blur_image_data,
+    api_model::{ImageData, PixelFormat, Rect, ShadowStyle, TargettedImage},
+    blur::{blur, BlurKind},
 };
 
-fn inflate_image_data(source: &ImageData, padding: i32) -> ImageData {
-    let new_width = source.width + 2 * padding;
-    let new_height = source.height + 2 * padding;
-    let mut res = ImageData {
-        width: new_width,
-        height: new_height,
-        bytes_per_row: new_width * 4,
-        data: vec![0; (new_width * new_height * 4) as usize],
-        device_pixel_ratio: source.device_pixel_ratio,
-    };
+/// Space around the image in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Padding {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+fn inflate_image_data(source: &ImageData, padding: Padding) -> ImageData {
+    let new_width = source.width + padding.left + padding.right;
+    let new_height = source.height + padding.top + padding.bottom;
+    let mut res = ImageData::new(
+        new_width,
+        new_height,
+        PixelFormat::Rgba,
+        source.device_pixel_ratio,
+    );
+    let source = source.to_format(PixelFormat::Rgba);
 
     let line_length = (source.width * 4) as usize;
     for y in 0..source.height {
-        let dest_start = ((y + padding) * res.bytes_per_row + padding * 4) as usize;
-        let src_start = (y * source.bytes_per_row) as usize;
-        res.data[dest_start..dest_start + line_length]
-            .copy_from_slice(&source.data[src_start..src_start + line_length]);
+        let dest_start = ((y + padding.top) * res.bytes_per_row + padding.left * 4) as usize;
+        res.data[dest_start..dest_start + line_length].copy_from_slice(source.row(y));
     }
     res
 }
 
-fn draw_shadow(image: &mut ImageData, radius: i32) {
+/// Grows (positive spread) or shrinks (negative spread) the mask using
+/// separable max / min filter.
+fn spread_mask(mask: &mut [u8], width: usize, height: usize, spread: i32) {
+    if spread == 0 {
+        return;
+    }
+    let extent = spread.unsigned_abs() as usize;
+    let pick = |a: u8, b: u8| if spread > 0 { a.max(b) } else { a.min(b) };
+    let mut line = Vec::new();
+    for y in 0..height {
+        let row = &mut mask[y * width..(y + 1) * width];
+        line.clear();
+        line.extend_from_slice(row);
+        for (x, value) in row.iter_mut().enumerate() {
+            let range = x.saturating_sub(extent)..(x + extent + 1).min(width);
+            *value = line[range].iter().fold(line[x], |a, b| pick(a, *b));
+        }
+    }
+    for x in 0..width {
+        line.clear();
+        line.extend((0..height).map(|y| mask[y * width + x]));
+        for y in 0..height {
+            let range = y.saturating_sub(extent)..(y + extent + 1).min(height);
+            mask[y * width + x] = line[range].iter().fold(line[y], |a, b| pick(a, *b));
+        }
+    }
+}
+
+/// Draws shadow below the image content. Offset, spread and radius are in
+/// pixels; Image must have enough transparent padding for the shadow.
+fn draw_shadow(
+    image: &mut ImageData,
+    style: &ShadowStyle,
+    offset: (i32, i32),
+    spread: i32,
+    radius: i32,
+) {
     assert!(image.bytes_per_row == image.width * 4);
 
+    let (width, height) = (image.width as usize, image.height as usize);
+    let [color_a, color_r, color_g, color_b] = (style.color as u32).to_be_bytes();
+    let opacity = (style.opacity.clamp(0.0, 1.0) * color_a as f64 / 255.0 * 256.0) as u32;
     let data = &mut image.data;
 
-    let mut shadow = vec![0u8; (image.width * image.height) as usize];
+    let mut shadow = vec![0u8; width * height];
+    for y in 0..height as i32 {
+        let source_y = y - offset.1;
+        if !(0..height as i32).contains(&source_y) {
+            continue;
+        }
+        for x in 0..width as i32 {
+            let source_x = x - offset.0;
+            if (0..width as i32).contains(&source_x) {
+                let alpha = data[(source_y as usize * width + source_x as usize) * 4 + 3] as u32;
+                shadow[y as usize * width + x as usize] = ((alpha * opacity) >> 8) as u8;
+            }
+        }
+    }
 
-    (0..data.len() / 4).for_each(|i| {
-        shadow[i] = data[i * 4 + 3] / 2;
-    });
+    spread_mask(&mut shadow, width, height, spread);
 
-    blur_image_data(
+    blur(
         &mut shadow,
-        0,
-        0,
-        image.width as usize,
-        image.height as usize,
+        width,
+        height,
+        1,
         radius as usize,
+        BlurKind::Gaussian,
     );
 
     (0..data.len() / 4).for_each(|i| {
@@ -51,9 +108,9 @@ fn draw_shadow(image: &mut ImageData, radius: i32) {
             // full opacity, no shadow
         } else if a0_ == 0 {
             // zero opacity, only shadow
-            data[index] = 0;
-            data[index + 1] = 0;
-            data[index + 2] = 0;
+            data[index] = color_r;
+            data[index + 1] = color_g;
+            data[index + 2] = color_b;
             data[index + 3] = shadow[i];
         } else {
             // blend
@@ -64,9 +121,10 @@ fn draw_shadow(image: &mut ImageData, radius: i32) {
             let a1 = f64::from(shadow[i]) / 255.0;
 
             let a = a0 + a1 * (1.0 - a0);
-            let r = (r0 * a0) / a;
-            let g = (g0 * a0) / a;
-            let b = (b0 * a0) / a;
+            let blend = |c0: f64, c1: u8| (c0 * a0 + f64::from(c1) / 255.0 * a1 * (1.0 - a0)) / a;
+            let r = blend(r0, color_r);
+            let g = blend(g0, color_g);
+            let b = blend(b0, color_b);
 
             data[index] = (r * 255.0) as u8;
             data[index + 1] = (g * 255.0) as u8;
@@ -77,18 +135,131 @@ fn draw_shadow(image: &mut ImageData, radius: i32) {
 }
 
 pub trait WithShadow {
-    fn with_shadow(&self, radius: i32) -> Self;
+    fn with_shadow(&self, style: &ShadowStyle) -> Self;
+
+    /// Draws shadow with given style, or with the default style for device
+    /// pixel ratio of the image if there is none.
+    fn with_shadow_or_default(&self, style: Option<&ShadowStyle>) -> Self;
 }
 
 impl WithShadow for TargettedImage {
-    fn with_shadow(&self, radius: i32) -> Self {
-        let adjusted_radius =
-            ((radius as f64) * self.image_data.device_pixel_ratio.unwrap_or(1.0)) as i32;
-        let mut image_data = inflate_image_data(&self.image_data, adjusted_radius);
-        draw_shadow(&mut image_data, adjusted_radius);
+    fn with_shadow(&self, style: &ShadowStyle) -> Self {
+        let device_pixel_ratio = self.image_data.device_pixel_ratio.unwrap_or(1.0);
+        let to_pixels = |v: f64| (v * device_pixel_ratio).round() as i32;
+        let radius = to_pixels(style.radius.max(0.0));
+        let spread = to_pixels(style.spread);
+        let offset = (to_pixels(style.offset_x), to_pixels(style.offset_y));
+        // How far the shadow reaches past the image on each side.
+        let extent = (radius + spread).max(0);
+        let padding = Padding {
+            left: (extent - offset.0).max(0),
+            top: (extent - offset.1).max(0),
+            right: (extent + offset.0).max(0),
+            bottom: (extent + offset.1).max(0),
+        };
+        let mut image_data = inflate_image_data(&self.image_data, padding);
+        if style.opacity > 0.0 {
+            draw_shadow(&mut image_data, style, offset, spread, radius);
+        }
+        let to_points = |v: i32| v as f64 / device_pixel_ratio;
         TargettedImage {
             image_data,
-            rect: self.rect.inflated(radius as f64, radius as f64),
+            rect: Rect {
+                x: self.rect.x - to_points(padding.left),
+                y: self.rect.y - to_points(padding.top),
+                width: self.rect.width + to_points(padding.left + padding.right),
+                height: self.rect.height + to_points(padding.top + padding.bottom),
+            },
+        }
+    }
+
+    fn with_shadow_or_default(&self, style: Option<&ShadowStyle>) -> Self {
+        match style {
+            Some(style) => self.with_shadow(style),
+            None => {
+                let device_pixel_ratio = self.image_data.device_pixel_ratio.unwrap_or(1.0);
+                self.with_shadow(&ShadowStyle::default_for_device_pixel_ratio(
+                    device_pixel_ratio,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WithShadow;
+    use crate::api_model::{ImageData, PixelFormat, Rect, ShadowStyle, TargettedImage};
+
+    fn opaque_square() -> TargettedImage {
+        let mut image_data = ImageData::new(4, 4, PixelFormat::Rgba, Some(2.0));
+        image_data.data = [10, 20, 30, 255].repeat(16);
+        TargettedImage {
+            image_data,
+            rect: Rect::xywh(10.0, 10.0, 2.0, 2.0),
+        }
+    }
+
+    #[test]
+    fn test_shadow_geometry() {
+        let style = ShadowStyle {
+            color: 0xFFFF0000,
+            opacity: 1.0,
+            offset_x: 2.0,
+            offset_y: -1.0,
+            spread: 0.0,
+            radius: 1.0,
+        };
+        let res = opaque_square().with_shadow(&style);
+        let image = &res.image_data;
+        // Radius and offset are in points; Image has device pixel ratio 2.
+        // Padding is left 0, top 2 + 2, right 2 + 4 and bottom 0.
+        assert_eq!((image.width, image.height), (10, 8));
+        assert_eq!(res.rect, Rect::xywh(10.0, 8.0, 5.0, 4.0));
+        // Opaque pixels are not touched.
+        assert_eq!(image.pixel(0, 4), [10, 20, 30, 255]);
+        // Shadow is offset to the right and up and has shadow color.
+        let [r, g, b, a] = image.pixel(6, 3);
+        assert_eq!((r, g, b), (255, 0, 0));
+        assert!(a > 128);
+        assert_eq!(image.pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn test_shadow_spread_and_opacity() {
+        let style = ShadowStyle {
+            color: 0xFF000000,
+            opacity: 0.5,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            spread: 1.0,
+            radius: 0.0,
+        };
+        let res = opaque_square().with_shadow(&style);
+        let image = &res.image_data;
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(res.rect, Rect::xywh(9.0, 9.0, 4.0, 4.0));
+        // Without blur the spread shadow has uniform alpha.
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 127]);
+        assert_eq!(image.pixel(2, 2), [10, 20, 30, 255]);
+
+        let transparent = ShadowStyle {
+            opacity: 0.0,
+            ..style
+        };
+        let res = opaque_square().with_shadow(&transparent);
+        assert_eq!(res.image_data.pixel(0, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_default_shadow_radius() {
+        // Default radius is 10 physical pixels regardless of device pixel
+        // ratio.
+        for device_pixel_ratio in [1.0, 2.0] {
+            let mut image = opaque_square();
+            image.image_data.device_pixel_ratio = Some(device_pixel_ratio);
+            let res = image.with_shadow_or_default(None);
+            assert_eq!((res.image_data.width, res.image_data.height), (24, 24));
         }
     }
 }
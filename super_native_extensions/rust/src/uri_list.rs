@@ -0,0 +1,341 @@
+//! Parsing and serialization of `text/uri-list` (RFC 2483) and conversion
+//! between `file` URIs and local paths.
+
+use std::path::PathBuf;
+
+use crate::api_model::ClipboardIntent;
+
+/// Parses `text/uri-list` content. Lines are separated by CRLF, though
+/// single LF is accepted as well. Blank lines and comments (lines starting
+/// with `#`) are skipped.
+pub fn parse_uri_list(data: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(data)
+        .split('\n')
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_owned())
+        .collect()
+}
+
+/// Serializes URIs as `text/uri-list`, each line terminated by CRLF.
+pub fn serialize_uri_list<S: AsRef<str>>(uris: &[S]) -> Vec<u8> {
+    let mut res = Vec::new();
+    for uri in uris {
+        let uri = uri.as_ref().trim();
+        if uri.is_empty() || uri.starts_with('#') {
+            continue;
+        }
+        res.extend_from_slice(uri.as_bytes());
+        res.extend_from_slice(b"\r\n");
+    }
+    res
+}
+
+/// Serializes file URIs in `x-special/gnome-copied-files` format, which is
+/// the intent on the first line followed by URIs separated by LF. URIs with
+/// other schemes than `file` are left out since file managers can not paste
+/// them.
+pub fn serialize_gnome_copied_files<S: AsRef<str>>(intent: ClipboardIntent, uris: &[S]) -> Vec<u8> {
+    let mut res = match intent {
+        ClipboardIntent::Copy => b"copy".to_vec(),
+        ClipboardIntent::Cut => b"cut".to_vec(),
+    };
+    for uri in uris {
+        let uri = uri.as_ref().trim();
+        if is_file_uri(uri) {
+            res.push(b'\n');
+            res.extend_from_slice(uri.as_bytes());
+        }
+    }
+    res
+}
+
+/// Parses `x-special/gnome-copied-files` content.
+pub fn parse_gnome_copied_files(data: &[u8]) -> Option<(ClipboardIntent, Vec<String>)> {
+    let (intent, uris) = match data.iter().position(|b| *b == b'\n') {
+        Some(index) => (&data[..index], &data[index + 1..]),
+        None => (data, &[][..]),
+    };
+    let intent = match String::from_utf8_lossy(intent).trim() {
+        "copy" => ClipboardIntent::Copy,
+        "cut" => ClipboardIntent::Cut,
+        _ => return None,
+    };
+    Some((intent, parse_uri_list(uris)))
+}
+
+/// Decodes percent encoded string. Invalid escape sequences are kept as is.
+/// Result might not be valid UTF-8.
+pub fn percent_decode(s: &str) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(h), Some(l)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                res.push(h << 4 | l);
+                i += 3;
+                continue;
+            }
+        }
+        res.push(bytes[i]);
+        i += 1;
+    }
+    res
+}
+
+/// Percent encodes path bytes, leaving unreserved characters and path
+/// separators intact.
+pub fn percent_encode_path(path: &[u8]) -> String {
+    let mut res = String::with_capacity(path.len());
+    for b in path {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                res.push(*b as char)
+            }
+            _ => res.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    res
+}
+
+/// Splits URI into scheme and the rest, without query and fragment.
+fn split_uri(uri: &str) -> Option<(&str, &str)> {
+    let uri = uri.split(['#', '?']).next().unwrap_or_default();
+    let (scheme, rest) = uri.split_once(':')?;
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid_scheme.then_some((scheme, rest))
+}
+
+/// Returns whether the URI has `file` scheme.
+pub fn is_file_uri(uri: &str) -> bool {
+    split_uri(uri).is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case("file"))
+}
+
+/// Returns authority and path of hierarchical URI part.
+fn split_authority(rest: &str) -> (Option<&str>, &str) {
+    match rest.strip_prefix("//") {
+        Some(rest) => match rest.find('/') {
+            Some(index) => (Some(&rest[..index]), &rest[index..]),
+            None => (Some(rest), ""),
+        },
+        None => (None, rest),
+    }
+}
+
+/// Converts `file` URI to local path. Accepts `file:///path`, `file:/path`
+/// and `file://host/path` where host is `localhost` or `local_host_name`.
+/// Percent decoded path is not required to be valid UTF-8 on Unix.
+pub fn file_uri_to_path(uri: &str, local_host_name: Option<&str>) -> Option<PathBuf> {
+    let (scheme, rest) = split_uri(uri.trim())?;
+    if !scheme.eq_ignore_ascii_case("file") {
+        return None;
+    }
+    let (host, path) = split_authority(rest);
+    if let Some(host) = host {
+        let is_local = host.is_empty()
+            || host.eq_ignore_ascii_case("localhost")
+            || local_host_name.is_some_and(|name| host.eq_ignore_ascii_case(name));
+        if !is_local {
+            return None;
+        }
+    }
+    if !path.starts_with('/') {
+        return None;
+    }
+    let path = percent_decode(path);
+    if path.contains(&0) {
+        return None;
+    }
+    path_from_bytes(path)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(path: Vec<u8>) -> Option<PathBuf> {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+    Some(PathBuf::from(OsString::from_vec(path)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(path: Vec<u8>) -> Option<PathBuf> {
+    let path = String::from_utf8(path).ok()?;
+    // file:///C:/dir -> C:\dir
+    let bytes = path.as_bytes();
+    let path = if bytes.len() >= 3 && bytes[0] == b'/' && bytes[2] == b':' {
+        &path[1..]
+    } else {
+        &path
+    };
+    Some(PathBuf::from(path.replace('/', "\\")))
+}
+
+/// Converts absolute local path to `file` URI.
+#[cfg(unix)]
+pub fn path_to_file_uri(path: &std::path::Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    format!(
+        "file://{}",
+        percent_encode_path(path.as_os_str().as_bytes())
+    )
+}
+
+/// Returns name suitable for a file from the last path segment of URI.
+pub fn suggested_name_for_uri(uri: &str) -> Option<String> {
+    let (_, rest) = split_uri(uri.trim())?;
+    let (_, path) = split_authority(rest);
+    let name = path.split('/').rfind(|s| !s.is_empty())?;
+    sanitize_file_name(&String::from_utf8_lossy(&percent_decode(name)))
+}
+
+/// Replaces path separators and control characters in file name. Returns
+/// `None` for names that can not be used as file name.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c == '/' || c == '\\' || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{
+        file_uri_to_path, is_file_uri, parse_gnome_copied_files, parse_uri_list, percent_decode,
+        sanitize_file_name, serialize_gnome_copied_files, serialize_uri_list,
+        suggested_name_for_uri,
+    };
+    use crate::api_model::ClipboardIntent;
+
+    #[test]
+    fn test_parse_and_serialize() {
+        let data = b"# comment\r\nfile:///tmp/a\r\n\r\n  https://example.com/b  \nfile:///c\0";
+        let uris = parse_uri_list(data);
+        assert_eq!(
+            uris,
+            vec!["file:///tmp/a", "https://example.com/b", "file:///c"]
+        );
+        assert_eq!(
+            serialize_uri_list(&uris),
+            b"file:///tmp/a\r\nhttps://example.com/b\r\nfile:///c\r\n"
+        );
+        assert_eq!(serialize_uri_list(&["", "#x", "a:b"]), b"a:b\r\n");
+    }
+
+    #[test]
+    fn test_gnome_copied_files() {
+        let data = serialize_gnome_copied_files(ClipboardIntent::Cut, &["file:///a", "file:///b"]);
+        assert_eq!(data, b"cut\nfile:///a\nfile:///b");
+        assert_eq!(
+            parse_gnome_copied_files(&data),
+            Some((
+                ClipboardIntent::Cut,
+                vec!["file:///a".to_owned(), "file:///b".to_owned()]
+            ))
+        );
+        assert_eq!(
+            parse_gnome_copied_files(b"copy\r\nfile:///a\r\n"),
+            Some((ClipboardIntent::Copy, vec!["file:///a".to_owned()]))
+        );
+        assert_eq!(
+            parse_gnome_copied_files(b"copy"),
+            Some((ClipboardIntent::Copy, vec![]))
+        );
+        assert_eq!(parse_gnome_copied_files(b"file:///a\n"), None);
+    }
+
+    #[test]
+    fn test_gnome_copied_files_non_file_uri() {
+        assert!(is_file_uri("file:///a"));
+        assert!(is_file_uri("FILE://localhost/a"));
+        assert!(!is_file_uri("https://example.com/file:///a"));
+        assert!(!is_file_uri("/tmp/a"));
+        let data = serialize_gnome_copied_files(
+            ClipboardIntent::Copy,
+            &["https://example.com/a", "file:///b", "#file:///c"],
+        );
+        assert_eq!(data, b"copy\nfile:///b");
+        assert_eq!(
+            serialize_gnome_copied_files(ClipboardIntent::Cut, &["https://example.com/a"]),
+            b"cut"
+        );
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b%2Fc"), b"a b/c");
+        assert_eq!(percent_decode("%e2%82%ac"), "€".as_bytes());
+        assert_eq!(percent_decode("100%"), b"100%");
+        assert_eq!(percent_decode("%zz%4"), b"%zz%4");
+        assert_eq!(percent_decode("%FF"), [0xFF]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_uri_to_path() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        use super::path_to_file_uri;
+
+        let path = |uri: &str| file_uri_to_path(uri, Some("myhost"));
+        assert_eq!(
+            path("file:///home/user/My%20File.txt"),
+            Some(PathBuf::from("/home/user/My File.txt"))
+        );
+        assert_eq!(path("file:/tmp/x"), Some(PathBuf::from("/tmp/x")));
+        assert_eq!(
+            path("FILE://localhost/tmp/x"),
+            Some(PathBuf::from("/tmp/x"))
+        );
+        assert_eq!(path("file://myhost/tmp/x#y"), Some(PathBuf::from("/tmp/x")));
+        assert_eq!(path("file://other/tmp/x"), None);
+        assert_eq!(path("file://myhost"), None);
+        assert_eq!(path("file:///a%00b"), None);
+        assert_eq!(path("https://example.com/x"), None);
+
+        let non_utf8 = PathBuf::from(OsStr::from_bytes(b"/tmp/\xFFname"));
+        assert_eq!(path("file:///tmp/%FFname").as_ref(), Some(&non_utf8));
+        let uri = path_to_file_uri(&non_utf8);
+        assert_eq!(uri, "file:///tmp/%FFname");
+        assert_eq!(
+            path_to_file_uri(&PathBuf::from("/a b/ü")),
+            "file:///a%20b/%C3%BC"
+        );
+        assert_eq!(path(&uri), Some(non_utf8));
+    }
+
+    #[test]
+    fn test_suggested_name() {
+        assert_eq!(
+            suggested_name_for_uri("file:///home/user/My%20File.txt"),
+            Some("My File.txt".into())
+        );
+        assert_eq!(
+            suggested_name_for_uri("https://example.com/dir/?query#frag"),
+            Some("dir".into())
+        );
+        assert_eq!(
+            suggested_name_for_uri("https://example.com/a%2Fb"),
+            Some("a_b".into())
+        );
+        assert_eq!(suggested_name_for_uri("https://example.com"), None);
+        assert_eq!(suggested_name_for_uri("file:///tmp/.."), None);
+        assert_eq!(sanitize_file_name(" a\nb "), Some("a_b".into()));
+    }
+}
@@ -0,0 +1,456 @@
+//! CBOR (RFC 8949) encoding of [`Value`] trees for structured app-private
+//! formats. A format is encoded as CBOR if its MIME type is
+//! `application/cbor` or has the `+cbor` structured syntax suffix, for
+//! example `application/vnd.example.items+cbor`.
+//!
+//! Values are mapped as follows:
+//! - `Null`, `Bool`, `I64`, `F64` and `String` to null, booleans, integers,
+//!   double precision floats and text strings;
+//! - `U8List` to byte string;
+//! - other typed lists to RFC 8746 little endian typed arrays (tags 69, 70,
+//!   72, 77, 78, 79, 85 and 86);
+//! - `List` and `Map` to arrays and maps. Map keys can be any value.
+//!
+//! Decoding additionally accepts half and single precision floats,
+//! `undefined` (decoded as `Null`) and indefinite length items. Unknown tags
+//! are ignored.
+//!
+//! Binary values (`U8List`) written as CBOR format are assumed to be already
+//! encoded and are passed through unchanged.
+
+use std::borrow::Cow;
+
+use irondash_message_channel::{Value, ValueTupleList};
+
+use crate::{
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    log::OkLog,
+};
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xFF;
+
+const TAG_U8: u64 = 64;
+const TAG_U16: u64 = 69;
+const TAG_U32: u64 = 70;
+const TAG_I8: u64 = 72;
+const TAG_I16: u64 = 77;
+const TAG_I32: u64 = 78;
+const TAG_I64: u64 = 79;
+const TAG_F32: u64 = 85;
+const TAG_F64: u64 = 86;
+
+/// Maximum nesting of arrays, maps and tags accepted by decoder.
+const MAX_DEPTH: usize = 256;
+
+pub fn is_cbor_format(format: &str) -> bool {
+    let mime_type = format.split(';').next().unwrap_or_default().trim();
+    mime_type.eq_ignore_ascii_case("application/cbor")
+        || mime_type.to_ascii_lowercase().ends_with("+cbor")
+}
+
+/// Returns value as it should be written for given format. Values of CBOR
+/// formats are encoded, other values are returned unchanged.
+pub fn value_for_format<'a>(value: &'a Value, format: &str) -> Cow<'a, Value> {
+    if is_cbor_format(format) && !matches!(value, Value::U8List(_) | Value::Null) {
+        if let Some(data) = encode_value(value).ok_log() {
+            return Cow::Owned(Value::U8List(data));
+        }
+    }
+    Cow::Borrowed(value)
+}
+
+/// Returns value read from given format. Data of CBOR formats is decoded,
+/// other values (and data that fails to decode) are returned unchanged.
+pub fn value_from_format(value: Value, format: &str) -> Value {
+    match value {
+        Value::U8List(data) if is_cbor_format(format) => match decode_value(&data).ok_log() {
+            Some(value) => value,
+            None => Value::U8List(data),
+        },
+        value => value,
+    }
+}
+
+pub fn encode_value(value: &Value) -> NativeExtensionsResult<Vec<u8>> {
+    let mut res = Vec::new();
+    encode(value, &mut res)?;
+    Ok(res)
+}
+
+pub fn decode_value(data: &[u8]) -> NativeExtensionsResult<Value> {
+    let mut decoder = Decoder { data, pos: 0 };
+    let res = decoder.value(0)?;
+    if decoder.pos != data.len() {
+        return Err(NativeExtensionsError::InvalidData);
+    }
+    Ok(res)
+}
+
+fn write_head(out: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    if argument < 24 {
+        out.push(major | argument as u8);
+    } else if argument <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(argument as u8);
+    } else if argument <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(argument as u16).to_be_bytes());
+    } else if argument <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(argument as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&argument.to_be_bytes());
+    }
+}
+
+fn write_typed_array<T: Copy, const N: usize>(
+    out: &mut Vec<u8>,
+    tag: u64,
+    data: &[T],
+    to_le_bytes: fn(T) -> [u8; N],
+) {
+    write_head(out, MAJOR_TAG, tag);
+    write_head(out, MAJOR_BYTES, (data.len() * N) as u64);
+    for v in data {
+        out.extend_from_slice(&to_le_bytes(*v));
+    }
+}
+
+fn encode(value: &Value, out: &mut Vec<u8>) -> NativeExtensionsResult<()> {
+    match value {
+        Value::Null => out.push(0xF6),
+        Value::Bool(v) => out.push(if *v { 0xF5 } else { 0xF4 }),
+        Value::I64(v) if *v >= 0 => write_head(out, MAJOR_UNSIGNED, *v as u64),
+        // Negative integer n is encoded as -1 - n, which is !n.
+        Value::I64(v) => write_head(out, MAJOR_NEGATIVE, !*v as u64),
+        Value::F64(v) => {
+            out.push(0xFB);
+            out.extend_from_slice(&v.to_be_bytes());
+        }
+        Value::String(v) => {
+            write_head(out, MAJOR_TEXT, v.len() as u64);
+            out.extend_from_slice(v.as_bytes());
+        }
+        Value::U8List(v) => {
+            write_head(out, MAJOR_BYTES, v.len() as u64);
+            out.extend_from_slice(v);
+        }
+        Value::I8List(v) => write_typed_array(out, TAG_I8, v, i8::to_le_bytes),
+        Value::I16List(v) => write_typed_array(out, TAG_I16, v, i16::to_le_bytes),
+        Value::U16List(v) => write_typed_array(out, TAG_U16, v, u16::to_le_bytes),
+        Value::I32List(v) => write_typed_array(out, TAG_I32, v, i32::to_le_bytes),
+        Value::U32List(v) => write_typed_array(out, TAG_U32, v, u32::to_le_bytes),
+        Value::I64List(v) => write_typed_array(out, TAG_I64, v, i64::to_le_bytes),
+        Value::F32List(v) => write_typed_array(out, TAG_F32, v, f32::to_le_bytes),
+        Value::F64List(v) => write_typed_array(out, TAG_F64, v, f64::to_le_bytes),
+        Value::List(v) => {
+            write_head(out, MAJOR_ARRAY, v.len() as u64);
+            for item in v {
+                encode(item, out)?;
+            }
+        }
+        Value::Map(v) => {
+            write_head(out, MAJOR_MAP, v.iter().count() as u64);
+            for (key, value) in v.iter() {
+                encode(key, out)?;
+                encode(value, out)?;
+            }
+        }
+        v => {
+            return Err(NativeExtensionsError::OtherError(format!(
+                "Value can not be encoded as CBOR: {v:?}"
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn half_to_f64(bits: u16) -> f64 {
+    let exponent = (bits >> 10) & 0x1F;
+    let mantissa = (bits & 0x3FF) as f64;
+    let value = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        e => (mantissa + 1024.0) * 2f64.powi(e as i32 - 25),
+    };
+    if bits & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+fn read_typed_array<T, const N: usize>(
+    data: &[u8],
+    from_le_bytes: fn([u8; N]) -> T,
+) -> NativeExtensionsResult<Vec<T>> {
+    if data.len() % N != 0 {
+        return Err(NativeExtensionsError::InvalidData);
+    }
+    Ok(data
+        .chunks_exact(N)
+        .map(|c| from_le_bytes(c.try_into().unwrap()))
+        .collect())
+}
+
+fn tagged_value(tag: u64, value: Value) -> NativeExtensionsResult<Value> {
+    let data = match value {
+        Value::U8List(data) => data,
+        value => return Ok(value),
+    };
+    Ok(match tag {
+        TAG_I8 => Value::I8List(read_typed_array(&data, i8::from_le_bytes)?),
+        TAG_I16 => Value::I16List(read_typed_array(&data, i16::from_le_bytes)?),
+        TAG_U16 => Value::U16List(read_typed_array(&data, u16::from_le_bytes)?),
+        TAG_I32 => Value::I32List(read_typed_array(&data, i32::from_le_bytes)?),
+        TAG_U32 => Value::U32List(read_typed_array(&data, u32::from_le_bytes)?),
+        TAG_I64 => Value::I64List(read_typed_array(&data, i64::from_le_bytes)?),
+        TAG_F32 => Value::F32List(read_typed_array(&data, f32::from_le_bytes)?),
+        TAG_F64 => Value::F64List(read_typed_array(&data, f64::from_le_bytes)?),
+        TAG_U8 => Value::U8List(data),
+        _ => Value::U8List(data),
+    })
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> NativeExtensionsResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(NativeExtensionsError::InvalidData)?;
+        let res = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(res)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> NativeExtensionsResult<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    /// Reads initial byte and argument. Returns major type, additional
+    /// information and argument (zero for indefinite length).
+    fn head(&mut self) -> NativeExtensionsResult<(u8, u8, u64)> {
+        let [initial] = self.take_array::<1>()?;
+        let (major, info) = (initial >> 5, initial & 0x1F);
+        let argument = match info {
+            0..=23 => info as u64,
+            24 => self.take_array::<1>()?[0] as u64,
+            25 => u16::from_be_bytes(self.take_array()?) as u64,
+            26 => u32::from_be_bytes(self.take_array()?) as u64,
+            27 => u64::from_be_bytes(self.take_array()?),
+            INDEFINITE => 0,
+            _ => return Err(NativeExtensionsError::InvalidData),
+        };
+        Ok((major, info, argument))
+    }
+
+    /// Validates length of definite item. Every element takes at least one
+    /// byte, which bounds allocations for malformed input.
+    fn length(&self, argument: u64) -> NativeExtensionsResult<usize> {
+        usize::try_from(argument)
+            .ok()
+            .filter(|len| *len <= self.data.len() - self.pos)
+            .ok_or(NativeExtensionsError::InvalidData)
+    }
+
+    /// Returns whether there is another element of array or map.
+    fn has_next(&mut self, info: u8, len: usize, index: usize) -> bool {
+        if info == INDEFINITE {
+            if self.data.get(self.pos) == Some(&BREAK) {
+                self.pos += 1;
+                false
+            } else {
+                true
+            }
+        } else {
+            index < len
+        }
+    }
+
+    fn bytes(&mut self, major: u8, info: u8, argument: u64) -> NativeExtensionsResult<Vec<u8>> {
+        if info != INDEFINITE {
+            let len = self.length(argument)?;
+            return Ok(self.take(len)?.to_vec());
+        }
+        // Indefinite length strings consist of definite length chunks.
+        let mut res = Vec::new();
+        while self.has_next(info, 0, 0) {
+            let (chunk_major, chunk_info, argument) = self.head()?;
+            if chunk_major != major || chunk_info == INDEFINITE {
+                return Err(NativeExtensionsError::InvalidData);
+            }
+            let len = self.length(argument)?;
+            res.extend_from_slice(self.take(len)?);
+        }
+        Ok(res)
+    }
+
+    fn value(&mut self, depth: usize) -> NativeExtensionsResult<Value> {
+        if depth > MAX_DEPTH {
+            return Err(NativeExtensionsError::InvalidData);
+        }
+        let (major, info, argument) = self.head()?;
+        let definite = || {
+            if info == INDEFINITE {
+                Err(NativeExtensionsError::InvalidData)
+            } else {
+                Ok(argument)
+            }
+        };
+        let invalid = |_| NativeExtensionsError::InvalidData;
+        match major {
+            MAJOR_UNSIGNED => Ok(Value::I64(i64::try_from(definite()?).map_err(invalid)?)),
+            MAJOR_NEGATIVE => Ok(Value::I64(!i64::try_from(definite()?).map_err(invalid)?)),
+            MAJOR_BYTES => Ok(Value::U8List(self.bytes(major, info, argument)?)),
+            MAJOR_TEXT => {
+                let data = self.bytes(major, info, argument)?;
+                Ok(Value::String(
+                    String::from_utf8(data).map_err(|_| NativeExtensionsError::InvalidData)?,
+                ))
+            }
+            MAJOR_ARRAY => {
+                let len = if info == INDEFINITE {
+                    0
+                } else {
+                    self.length(argument)?
+                };
+                let mut res = Vec::new();
+                while self.has_next(info, len, res.len()) {
+                    res.push(self.value(depth + 1)?);
+                }
+                Ok(Value::List(res))
+            }
+            MAJOR_MAP => {
+                let len = if info == INDEFINITE {
+                    0
+                } else {
+                    self.length(argument)?
+                };
+                let mut res = Vec::new();
+                while self.has_next(info, len, res.len()) {
+                    let key = self.value(depth + 1)?;
+                    let value = self.value(depth + 1)?;
+                    res.push((key, value));
+                }
+                Ok(Value::Map(ValueTupleList::new(res)))
+            }
+            MAJOR_TAG => {
+                let tag = definite()?;
+                let value = self.value(depth + 1)?;
+                tagged_value(tag, value)
+            }
+            MAJOR_SIMPLE => match info {
+                20 => Ok(Value::Bool(false)),
+                21 => Ok(Value::Bool(true)),
+                22 | 23 => Ok(Value::Null),
+                25 => Ok(Value::F64(half_to_f64(argument as u16))),
+                26 => Ok(Value::F64(f32::from_bits(argument as u32) as f64)),
+                27 => Ok(Value::F64(f64::from_bits(argument))),
+                _ => Err(NativeExtensionsError::InvalidData),
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use irondash_message_channel::{Value, ValueTupleList};
+
+    use super::{decode_value, encode_value, is_cbor_format, value_for_format, value_from_format};
+
+    #[test]
+    fn test_round_trip() {
+        let value = Value::Map(ValueTupleList::new(vec![
+            (
+                Value::String("name".into()),
+                Value::String("Žluťoučký kůň".into()),
+            ),
+            (Value::String("count".into()), Value::I64(-1234567890123)),
+            (Value::String("ratio".into()), Value::F64(0.25)),
+            (
+                Value::String("flags".into()),
+                Value::List(vec![Value::Bool(true), Value::Bool(false), Value::Null]),
+            ),
+            (Value::I64(42), Value::U8List(vec![1, 2, 3])),
+            (
+                Value::String("points".into()),
+                Value::F32List(vec![1.5, -2.0]),
+            ),
+            (
+                Value::String("ids".into()),
+                Value::I64List(vec![i64::MIN, 0, i64::MAX]),
+            ),
+            (
+                Value::String("shorts".into()),
+                Value::U16List(vec![0, 1, u16::MAX]),
+            ),
+        ]));
+        let encoded = encode_value(&value).unwrap();
+        assert_eq!(decode_value(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn test_encoding() {
+        // Examples from RFC 8949 Appendix A.
+        assert_eq!(
+            encode_value(&Value::I64(500)).unwrap(),
+            vec![0x19, 0x01, 0xF4]
+        );
+        assert_eq!(encode_value(&Value::I64(-100)).unwrap(), vec![0x38, 0x63]);
+        assert_eq!(
+            encode_value(&Value::String("IETF".into())).unwrap(),
+            b"\x64IETF".to_vec()
+        );
+        assert_eq!(decode_value(&[0xF9, 0x3C, 0x00]).unwrap(), Value::F64(1.0));
+        assert_eq!(decode_value(&[0xF9, 0xC4, 0x00]).unwrap(), Value::F64(-4.0));
+        assert_eq!(
+            decode_value(&[0x9F, 0x01, 0x82, 0x02, 0x03, 0xFF]).unwrap(),
+            Value::List(vec![
+                Value::I64(1),
+                Value::List(vec![Value::I64(2), Value::I64(3)])
+            ])
+        );
+        assert_eq!(
+            decode_value(&[0x7F, 0x62, b'a', b'b', 0x61, b'c', 0xFF]).unwrap(),
+            Value::String("abc".into())
+        );
+        // Truncated, trailing data, huge length.
+        assert!(decode_value(&[0x82, 0x01]).is_err());
+        assert!(decode_value(&[0x01, 0x01]).is_err());
+        assert!(decode_value(&[0x9B, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        assert!(is_cbor_format("application/vnd.example.items+cbor"));
+        assert!(is_cbor_format("application/cbor; foo=bar"));
+        assert!(!is_cbor_format("application/json"));
+
+        let value = Value::List(vec![Value::I64(1)]);
+        let format = "application/x-test+cbor";
+        let written = value_for_format(&value, format).into_owned();
+        assert_eq!(written, Value::U8List(vec![0x81, 0x01]));
+        assert_eq!(value_from_format(written, format), value);
+        assert_eq!(*value_for_format(&value, "text/plain"), value);
+    }
+}
@@ -6,6 +6,215 @@ pub enum StringFormat {
     Utf8,
     Utf8NullTerminated,
     Utf16NullTerminated,
+    Charset(Charset),
+}
+
+impl StringFormat {
+    /// Returns format for strings written as given MIME type or X11 target.
+    /// Formats without charset are UTF-8.
+    pub fn for_mime_type(mime_type: &str) -> Self {
+        match Charset::from_mime_type(mime_type) {
+            Some(charset) => StringFormat::Charset(charset),
+            None => StringFormat::Utf8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Iso8859_1,
+    Windows1252,
+    /// X11 `COMPOUND_TEXT`. Only ISO-8859-1 and UTF-8 segments are supported.
+    CompoundText,
+}
+
+/// Characters for bytes 0x80 - 0x9F in Windows-1252. Other bytes map to
+/// the same code points as in ISO-8859-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+impl Charset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().trim_matches(|c| c == '"' || c == '\'');
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(Self::Utf8),
+            // UTF-16 without byte order mark is little endian in practice.
+            "utf-16" | "utf16" | "utf-16le" | "ucs-2" | "unicode" => Some(Self::Utf16Le),
+            "utf-16be" => Some(Self::Utf16Be),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" => Some(Self::Iso8859_1),
+            "windows-1252" | "cp1252" | "x-cp1252" => Some(Self::Windows1252),
+            _ => None,
+        }
+    }
+
+    /// Returns charset from `charset` parameter of MIME type, or charset
+    /// implied by X11 legacy targets.
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "UTF8_STRING" => return Some(Self::Utf8),
+            "STRING" => return Some(Self::Iso8859_1),
+            "COMPOUND_TEXT" => return Some(Self::CompoundText),
+            _ => {}
+        }
+        mime_type.split(';').skip(1).find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            if key.trim().eq_ignore_ascii_case("charset") {
+                Self::from_name(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Decodes text in this charset. Invalid sequences are replaced with
+    /// U+FFFD.
+    pub fn decode(&self, data: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(data).into_owned(),
+            Self::Utf16Le | Self::Utf16Be => {
+                let units: Vec<u16> = data
+                    .chunks_exact(2)
+                    .map(|c| match self {
+                        Self::Utf16Le => u16::from_le_bytes([c[0], c[1]]),
+                        _ => u16::from_be_bytes([c[0], c[1]]),
+                    })
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            Self::Iso8859_1 => data.iter().map(|&b| b as char).collect(),
+            Self::Windows1252 => data
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                    b => b as char,
+                })
+                .collect(),
+            Self::CompoundText => decode_compound_text(data),
+        }
+    }
+
+    /// Encodes text in this charset. Characters that can not be represented
+    /// are replaced with `?`.
+    pub fn encode(&self, str: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => str.as_bytes().to_owned(),
+            Self::Utf16Le => str.encode_utf16().flat_map(|u| u.to_le_bytes()).collect(),
+            Self::Utf16Be => str.encode_utf16().flat_map(|u| u.to_be_bytes()).collect(),
+            Self::Iso8859_1 => str
+                .chars()
+                .map(|c| u8::try_from(c).unwrap_or(b'?'))
+                .collect(),
+            Self::Windows1252 => str
+                .chars()
+                .map(|c| match WINDOWS_1252_HIGH.iter().position(|h| *h == c) {
+                    Some(index) => index as u8 + 0x80,
+                    None => u8::try_from(c)
+                        .ok()
+                        .filter(|b| !(0x80..=0x9F).contains(b))
+                        .unwrap_or(b'?'),
+                })
+                .collect(),
+            Self::CompoundText => encode_compound_text(str),
+        }
+    }
+
+    fn is_unicode(&self) -> bool {
+        matches!(self, Self::Utf8 | Self::Utf16Le | Self::Utf16Be)
+    }
+}
+
+/// Decodes text with optional charset. Byte order mark takes precedence over
+/// Unicode charsets; Text without charset or byte order mark is UTF-8.
+/// Trailing null characters are removed.
+pub fn decode_text(data: &[u8], charset: Option<Charset>) -> String {
+    let (data, charset) = match charset {
+        Some(charset) if !charset.is_unicode() => (data, charset),
+        charset => {
+            if let Some(data) = data.strip_prefix(UTF8_BOM) {
+                (data, Charset::Utf8)
+            } else if let Some(data) = data.strip_prefix(UTF16_LE_BOM) {
+                (data, Charset::Utf16Le)
+            } else if let Some(data) = data.strip_prefix(UTF16_BE_BOM) {
+                (data, Charset::Utf16Be)
+            } else {
+                (data, charset.unwrap_or(Charset::Utf8))
+            }
+        }
+    };
+    let mut res = charset.decode(data);
+    res.truncate(res.trim_end_matches('\0').len());
+    res
+}
+
+const ESC: u8 = 0x1B;
+
+fn decode_compound_text(data: &[u8]) -> String {
+    let mut res = String::new();
+    let mut utf8 = Vec::new();
+    let mut in_utf8 = false;
+    // Whether right half (GR) is ISO-8859-1, which is the initial state.
+    let mut gr_latin1 = true;
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        if b == ESC {
+            // ESC, intermediate bytes (0x20 - 0x2F), final byte.
+            let end = data[i + 1..]
+                .iter()
+                .position(|b| !(0x20..=0x2F).contains(b))
+                .map(|p| i + 1 + p);
+            let Some(end) = end else {
+                break;
+            };
+            match &data[i + 1..=end] {
+                b"%G" => in_utf8 = true,
+                b"%@" => {
+                    res.push_str(&String::from_utf8_lossy(&utf8));
+                    utf8.clear();
+                    in_utf8 = false;
+                }
+                b"-A" => gr_latin1 = true,
+                [b'-' | b'$', ..] => gr_latin1 = false,
+                // Designations of left half (GL) are treated as ASCII.
+                _ => {}
+            }
+            i = end + 1;
+            continue;
+        }
+        if in_utf8 {
+            utf8.push(b);
+        } else if b < 0x80 || gr_latin1 {
+            res.push(b as char);
+        } else if !res.ends_with(char::REPLACEMENT_CHARACTER) {
+            res.push(char::REPLACEMENT_CHARACTER);
+        }
+        i += 1;
+    }
+    res.push_str(&String::from_utf8_lossy(&utf8));
+    res
+}
+
+fn encode_compound_text(str: &str) -> Vec<u8> {
+    if str
+        .chars()
+        .all(|c| (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)))
+    {
+        Charset::Iso8859_1.encode(str)
+    } else {
+        let mut res = vec![ESC, b'%', b'G'];
+        res.extend_from_slice(str.as_bytes());
+        res.extend_from_slice(&[ESC, b'%', b'@']);
+        res
+    }
 }
 
 pub trait CoerceToData {
@@ -27,6 +236,7 @@ impl CoerceToData for Value {
                     data.push(0);
                     Some(unsafe { transform_slice(&data) }.to_owned())
                 }
+                StringFormat::Charset(charset) => Some(charset.encode(str)),
             },
             Value::I8List(data) => Some(unsafe { transform_slice(data) }.to_owned()),
             Value::U8List(data) => Some(data.to_owned()),
@@ -46,6 +256,89 @@ impl CoerceToData for Value {
     }
 }
 
+pub trait CoerceToString {
+    /// Returns string value, decoding binary data with given charset.
+    fn coerce_to_string(&self, charset: Option<Charset>) -> Option<String>;
+}
+
+impl CoerceToString for Value {
+    fn coerce_to_string(&self, charset: Option<Charset>) -> Option<String> {
+        match self {
+            Value::String(str) => Some(str.clone()),
+            value => value
+                .coerce_to_data(StringFormat::Utf8)
+                .map(|data| decode_text(&data, charset)),
+        }
+    }
+}
+
 unsafe fn transform_slice<T>(s: &[T]) -> &[u8] {
     std::slice::from_raw_parts(s.as_ptr() as *const u8, std::mem::size_of_val(s))
 }
+
+#[cfg(test)]
+mod tests {
+    use irondash_message_channel::Value;
+
+    use super::{decode_text, Charset, CoerceToData, CoerceToString, StringFormat};
+
+    #[test]
+    fn test_charset_from_mime_type() {
+        assert_eq!(
+            Charset::from_mime_type("text/plain;charset=utf-16le"),
+            Some(Charset::Utf16Le)
+        );
+        assert_eq!(
+            Charset::from_mime_type("text/plain; format=flowed; charset=\"ISO-8859-1\""),
+            Some(Charset::Iso8859_1)
+        );
+        assert_eq!(Charset::from_mime_type("STRING"), Some(Charset::Iso8859_1));
+        assert_eq!(Charset::from_mime_type("text/plain"), None);
+        assert_eq!(Charset::from_mime_type("text/plain;charset=koi8-r"), None);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let text = "Žluťoučký kůň €";
+        for charset in [Charset::Utf8, Charset::Utf16Le, Charset::Utf16Be] {
+            assert_eq!(charset.decode(&charset.encode(text)), text);
+        }
+        assert_eq!(Charset::Windows1252.encode("€é"), vec![0x80, 0xE9]);
+        assert_eq!(Charset::Windows1252.decode(&[0x80, 0xE9]), "€é");
+        assert_eq!(Charset::Iso8859_1.encode("€é"), vec![b'?', 0xE9]);
+        assert_eq!(
+            Value::String("é".into()).coerce_to_data(StringFormat::for_mime_type("STRING")),
+            Some(vec![0xE9])
+        );
+    }
+
+    #[test]
+    fn test_decode_text() {
+        // Byte order mark wins over declared Unicode charset.
+        let mut data = vec![0xFE, 0xFF];
+        data.extend(Charset::Utf16Be.encode("ab\0"));
+        assert_eq!(decode_text(&data, Some(Charset::Utf16Le)), "ab");
+        assert_eq!(decode_text(&[0xFF, 0xFE, b'a', 0], None), "a");
+        assert_eq!(decode_text(&[0xFF, 0xFE], Some(Charset::Iso8859_1)), "ÿþ");
+        // Invalid input falls back lossily.
+        assert_eq!(decode_text(&[b'a', 0xFF], None), "a\u{FFFD}");
+        assert_eq!(
+            Value::U8List(vec![0xE9]).coerce_to_string(Some(Charset::Windows1252)),
+            Some("é".into())
+        );
+    }
+
+    #[test]
+    fn test_compound_text() {
+        for text in ["abc é", "Příliš"] {
+            let encoded = Charset::CompoundText.encode(text);
+            assert_eq!(Charset::CompoundText.decode(&encoded), text);
+        }
+        assert_eq!(Charset::CompoundText.encode("é"), vec![0xE9]);
+        // Latin-2 right half is not supported.
+        assert_eq!(
+            Charset::CompoundText.decode(b"a\x1b-B\xe9\xe8\x1b-A\xe9"),
+            "a\u{FFFD}é"
+        );
+    }
+}
@@ -23,7 +23,7 @@ use windows::{
 };
 
 use crate::{
-    api_model::ImageData,
+    api_model::{ImageData, PixelFormat},
     error::{NativeExtensionsError, NativeExtensionsResult},
 };
 
@@ -63,7 +63,11 @@ pub fn make_format_with_tymed_index(format: u32, tymed: TYMED, index: i32) -> FO
 
 impl From<windows::core::Error> for NativeExtensionsError {
     fn from(error: windows::core::Error) -> Self {
-        NativeExtensionsError::OtherError(format!("Windows Error: {error}"))
+        NativeExtensionsError::SystemError {
+            domain: "windows",
+            code: Some(error.code().0 as i64),
+            message: error.message().to_string(),
+        }
     }
 }
 
@@ -121,33 +125,12 @@ pub fn image_data_to_hbitmap(image: &ImageData) -> NativeExtensionsResult<HBITMA
         )?;
 
         // Bitmap needs to be flipped and unpremultiplied
-
-        let dst_stride = (image.width * 4) as isize;
-        let ptr = ptr as *mut u8;
-        for y in 0..image.height as isize {
-            let src_line = image
-                .data
-                .as_ptr()
-                .offset((image.height as isize - y - 1) * image.bytes_per_row as isize);
-
-            let dst_line = ptr.offset(y * dst_stride);
-
-            for x in (0..dst_stride).step_by(4) {
-                let (r, g, b, a) = (
-                    *src_line.offset(x) as i32,
-                    *src_line.offset(x + 1) as i32,
-                    *src_line.offset(x + 2) as i32,
-                    *src_line.offset(x + 3) as i32,
-                );
-
-                // ByteFormat.rawStraightRgba already has unpremultiplied alpha
-                // but channel order is different.
-
-                *dst_line.offset(x) = b as u8;
-                *dst_line.offset(x + 1) = g as u8;
-                *dst_line.offset(x + 2) = r as u8;
-                *dst_line.offset(x + 3) = a as u8;
-            }
+        let image = image.to_format(PixelFormat::Bgra);
+        let dst_stride = (image.width * 4) as usize;
+        let dst =
+            std::slice::from_raw_parts_mut(ptr as *mut u8, dst_stride * image.height as usize);
+        for (y, dst_line) in dst.chunks_exact_mut(dst_stride).enumerate() {
+            dst_line.copy_from_slice(image.row(image.height - y as i32 - 1));
         }
 
         ReleaseDC(HWND(0), dc);
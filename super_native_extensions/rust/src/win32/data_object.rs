@@ -1,6 +1,7 @@
 use std::{
     cell::{Cell, RefCell},
     collections::HashMap,
+    ffi::c_void,
     mem::{size_of, ManuallyDrop},
     rc::Rc,
     slice,
@@ -8,11 +9,11 @@ use std::{
     time::Duration,
 };
 
-use irondash_message_channel::IsolateId;
+use irondash_message_channel::{IsolateId, Value};
 use irondash_run_loop::{platform::PollSession, RunLoop};
 use threadpool::ThreadPool;
 use windows::{
-    core::{implement, HRESULT, HSTRING},
+    core::{implement, Interface, HRESULT, HSTRING},
     Win32::{
         Foundation::{
             GlobalFree, BOOL, DATA_S_SAMEFORMATETC, DV_E_FORMATETC, E_NOTIMPL, E_OUTOFMEMORY,
@@ -40,9 +41,11 @@ use windows::{
 use crate::{
     api_model::{DataProviderValueId, DataRepresentation, VirtualFileStorage},
     data_provider_manager::{DataProviderHandle, PlatformDataProviderDelegate, VirtualFileResult},
+    html_codec::encode_cf_html_data,
     log::OkLog,
     segmented_queue::{new_segmented_queue, QueueConfiguration},
-    util::DropNotifier,
+    util::{DropNotifier, NextId},
+    value_cbor::value_for_format,
     value_coerce::{CoerceToData, StringFormat},
     value_promise::{Promise, ValuePromiseResult},
 };
@@ -65,6 +68,19 @@ struct ProviderEntry {
     _handle: Arc<DataProviderHandle>,
 }
 
+/// Converts value provided by Dart to data for given clipboard format.
+fn data_for_value(value: &Value, format: &str) -> Option<Vec<u8>> {
+    let value = value_for_format(value, format);
+    if format == "HTML Format" {
+        // CF_HTML is always UTF-8 and needs header with byte offsets.
+        value
+            .coerce_to_data(StringFormat::Utf8)
+            .map(encode_cf_html_data)
+    } else {
+        value.coerce_to_data(StringFormat::Utf16NullTerminated)
+    }
+}
+
 #[implement(IDataObject, IDataObjectAsyncCapability)]
 pub struct DataObject {
     providers: Vec<ProviderEntry>,
@@ -72,6 +88,7 @@ pub struct DataObject {
     in_operation: Cell<bool>, // async stream
     virtual_stream_notifiers: RefCell<Vec<Arc<DropNotifier>>>,
     thread_pool: RefCell<Option<ThreadPool>>,
+    clipboard_id: Option<i64>,
 }
 
 /// These formats are not commonly supported on Windows. If they
@@ -80,11 +97,45 @@ pub struct DataObject {
 /// DIB or DIBV5)
 static FOREIGN_IMAGE_FORMATS: &[&str] = &["PNG", "GIF", "JFIF"];
 
+thread_local! {
+    static NEXT_CLIPBOARD_ID: Cell<i64> = const { Cell::new(0) };
+    /// Data object most recently created for clipboard. The object is owned
+    /// by OLE clipboard and not retained here; the entry is removed when the
+    /// object is destroyed.
+    static CLIPBOARD_DATA_OBJECT: Cell<Option<(i64, *mut c_void)>> = const { Cell::new(None) };
+}
+
 impl DataObject {
     pub fn create(
         providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
     ) -> IDataObject {
-        let data_object = Self {
+        Self::new(providers, None).into()
+    }
+
+    /// Creates data object to be set as clipboard. While alive the object
+    /// can be accessed through [`DataObject::with_clipboard_object`].
+    pub fn create_for_clipboard(
+        providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
+    ) -> IDataObject {
+        let id = NEXT_CLIPBOARD_ID.with(|id| id.next_id());
+        let data_object: IDataObject = Self::new(providers, Some(id)).into();
+        CLIPBOARD_DATA_OBJECT.with(|o| o.set(Some((id, data_object.as_raw()))));
+        data_object
+    }
+
+    /// Invokes the callback with data object most recently created for
+    /// clipboard, provided that it has not been destroyed yet.
+    pub fn with_clipboard_object<T, F: FnOnce(&IDataObject) -> T>(f: F) -> Option<T> {
+        let (_, raw) = CLIPBOARD_DATA_OBJECT.with(|o| o.get())?;
+        // Entry is removed in drop so the object is still alive.
+        unsafe { IDataObject::from_raw_borrowed(&raw) }.map(f)
+    }
+
+    fn new(
+        providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
+        clipboard_id: Option<i64>,
+    ) -> Self {
+        Self {
             providers: providers
                 .into_iter()
                 .map(|p| ProviderEntry {
@@ -96,8 +147,8 @@ impl DataObject {
             in_operation: Cell::new(false),
             virtual_stream_notifiers: RefCell::new(Vec::new()),
             thread_pool: RefCell::new(None),
-        };
-        data_object.into()
+            clipboard_id,
+        }
     }
 
     fn global_from_data(&self, data: &[u8]) -> windows::core::Result<HGLOBAL> {
@@ -119,6 +170,7 @@ impl DataObject {
         &self,
         provider: &PlatformDataProvider,
         id: DataProviderValueId,
+        format: &str,
     ) -> Option<Vec<u8>> {
         let delegate = provider.delegate.upgrade();
         if let Some(delegate) = delegate {
@@ -127,7 +179,7 @@ impl DataObject {
             loop {
                 match data.try_take() {
                     Some(ValuePromiseResult::Ok { value }) => {
-                        return value.coerce_to_data(StringFormat::Utf16NullTerminated)
+                        return data_for_value(&value, format)
                     }
                     Some(ValuePromiseResult::Cancelled) => return None,
                     None => RunLoop::current()
@@ -149,12 +201,12 @@ impl DataObject {
                 match representation {
                     DataRepresentation::Simple { format, data } => {
                         if &format_string == format {
-                            return data.coerce_to_data(StringFormat::Utf16NullTerminated);
+                            return data_for_value(data, format);
                         }
                     }
                     DataRepresentation::Lazy { format, id } => {
                         if &format_string == format {
-                            return self.lazy_data_for_id(provider, *id);
+                            return self.lazy_data_for_id(provider, *id, format);
                         }
                     }
                     _ => {}
@@ -468,6 +520,13 @@ impl DataObject {
 
 impl Drop for DataObject {
     fn drop(&mut self) {
+        if let Some(id) = self.clipboard_id {
+            CLIPBOARD_DATA_OBJECT.with(|o| {
+                if matches!(o.get(), Some((current, _)) if current == id) {
+                    o.set(None);
+                }
+            });
+        }
         // Keep the streams alive for one second after disposing data object
         // to give the client chance to interact with stream.
         // Otherwise the streams will be disposed to prevent leaks.
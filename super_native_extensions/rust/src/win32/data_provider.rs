@@ -7,12 +7,16 @@ use std::{
 
 use irondash_message_channel::{IsolateId, Late};
 use once_cell::sync::Lazy;
-use windows::Win32::System::Ole::OleSetClipboard;
+use windows::Win32::{
+    Foundation::S_OK,
+    System::Ole::{OleIsCurrentClipboard, OleSetClipboard},
+};
 
 use crate::{
     api_model::DataProvider,
     data_provider_manager::{DataProviderHandle, PlatformDataProviderDelegate},
     error::NativeExtensionsResult,
+    log::OkLog,
     segmented_queue::SegmentedQueueWriter,
 };
 
@@ -58,6 +62,12 @@ pub fn platform_stream_close(handle: i32, _delete: bool) {
     }
 }
 
+/// Lazy values are written to the requested medium and not retained by the
+/// provider.
+pub fn platform_provider_cache_bytes() -> Option<usize> {
+    None
+}
+
 pub struct PlatformDataProvider {
     weak_self: Late<Weak<Self>>,
     pub(super) isolate_id: IsolateId,
@@ -83,10 +93,21 @@ impl PlatformDataProvider {
         self.weak_self.set(weak_self);
     }
 
+    /// Empties the clipboard if it still holds data object written by this
+    /// plugin. OLE then releases the object together with its providers.
+    pub fn release_clipboard() {
+        let is_current = DataObject::with_clipboard_object(|data_object| unsafe {
+            OleIsCurrentClipboard(data_object) == S_OK
+        });
+        if is_current == Some(true) {
+            unsafe { OleSetClipboard(None) }.ok_log();
+        }
+    }
+
     pub async fn write_to_clipboard(
         providers: Vec<(Rc<PlatformDataProvider>, Arc<DataProviderHandle>)>,
     ) -> NativeExtensionsResult<()> {
-        let data_object = DataObject::create(providers);
+        let data_object = DataObject::create_for_clipboard(providers);
         unsafe {
             OleSetClipboard(&data_object)?;
         }
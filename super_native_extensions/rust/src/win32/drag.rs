@@ -27,6 +27,7 @@ use windows::{
 
 use crate::{
     api_model::{DataProviderId, DragConfiguration, DragRequest, DropOperation, Point},
+    drag_image::{combine_drag_images, CombinedImageStyle},
     drag_manager::{
         DataProviderEntry, DragSessionId, PlatformDragContextDelegate, PlatformDragContextId,
     },
@@ -180,11 +181,17 @@ impl PlatformDragContext {
             })
             .collect();
 
-        let drag_image = &request.combined_drag_image.ok_or_else(|| {
-            NativeExtensionsError::OtherError("Missing combined drag image".into())
-        })?;
+        let drag_image = request.combined_drag_image.unwrap_or_else(|| {
+            combine_drag_images(&request.configuration.items, &CombinedImageStyle::default())
+        });
 
-        let drag_image = drag_image.with_shadow(10);
+        let shadow_style = request.configuration.shadow_style.clone();
+        let drag_image = request
+            .configuration
+            .preview_size_policy
+            .unwrap_or_default()
+            .apply_to_targetted_image(drag_image)
+            .with_shadow_or_default(shadow_style.as_ref());
 
         let data_object = DataObject::create(providers);
         let helper: IDragSourceHelper = create_instance(&CLSID_DragDropHelper)?;
@@ -21,6 +21,7 @@ use windows::Win32::{
 use crate::{
     error::NativeExtensionsResult,
     hot_key_manager::{HotKeyCreateRequest, HotKeyHandle, HotKeyManagerDelegate},
+    log::OkLog,
 };
 
 pub struct PlatformHotKeyManager {
@@ -127,6 +128,10 @@ impl Drop for PlatformHotKeyManager {
     fn drop(&mut self) {
         let message_listener: Weak<dyn MessageListener> = self.weak_self.clone();
         if let Ok(run_loop) = RunLoop::try_current() {
+            let hwnd = HWND(run_loop.platform_run_loop.hwnd());
+            for (id, _) in self.hot_keys.get_mut().drain() {
+                unsafe { UnregisterHotKey(hwnd, id) }.ok_log();
+            }
             run_loop
                 .platform_run_loop
                 .unregister_message_listener(&message_listener);
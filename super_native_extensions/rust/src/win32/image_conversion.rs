@@ -1,5 +1,11 @@
-use std::{ptr::null_mut, slice};
+use std::{ptr::null_mut, slice, thread};
 
+use async_trait::async_trait;
+use irondash_message_channel::Value;
+use irondash_run_loop::{
+    util::{Capsule, FutureCompleter},
+    RunLoop,
+};
 use windows::{
     core::PWSTR,
     Win32::{
@@ -16,12 +22,20 @@ use windows::{
                 },
             },
             Memory::{GlobalLock, GlobalSize, GlobalUnlock},
+            Ole::{CF_DIB, CF_DIBV5},
             Variant::{VariantInit, VT_BOOL},
         },
+        UI::Shell::SHCreateMemStream,
     },
 };
 
-use super::common::create_instance;
+use crate::{
+    error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::FormatConverter,
+    value_coerce::{CoerceToData, StringFormat},
+};
+
+use super::common::{create_instance, format_to_string};
 
 /// Convert image from input_stream to PNG
 pub fn convert_to_png(input_stream: IStream) -> windows::core::Result<Vec<u8>> {
@@ -98,3 +112,62 @@ pub fn convert_to_dib(input_stream: IStream, use_v5: bool) -> windows::core::Res
         Ok(res)
     }
 }
+
+/// Synthesizes PNG from `CF_DIBV5` or `CF_DIB`. `CF_DIBV5` is preferred
+/// because it can carry alpha channel.
+pub struct DibToPng {
+    source_formats: Vec<String>,
+}
+
+impl DibToPng {
+    pub fn new() -> Self {
+        Self {
+            source_formats: vec![
+                format_to_string(CF_DIBV5.0 as u32),
+                format_to_string(CF_DIB.0 as u32),
+            ],
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl FormatConverter for DibToPng {
+    fn target_format(&self) -> &str {
+        "PNG"
+    }
+
+    fn source_formats(&self) -> &[String] {
+        &self.source_formats
+    }
+
+    async fn convert(&self, _source_format: &str, data: Value) -> NativeExtensionsResult<Value> {
+        let data = data
+            .coerce_to_data(StringFormat::Utf8)
+            .ok_or(NativeExtensionsError::InvalidData)?;
+        let mut bmp = Vec::<u8>::new();
+        bmp.extend_from_slice(&[0x42, 0x4D]); // BM
+        bmp.extend_from_slice(&((data.len() + 14) as u32).to_le_bytes()); // File size
+        bmp.extend_from_slice(&[0, 0]); // reserved 1
+        bmp.extend_from_slice(&[0, 0]); // reserved 2
+        bmp.extend_from_slice(&[0, 0, 0, 0]); // data starting address; not required by decoder
+        bmp.extend_from_slice(&data);
+
+        let (future, completer) = FutureCompleter::new();
+
+        let mut completer = Capsule::new(completer);
+        let sender = RunLoop::current().new_sender();
+
+        // Do the actual encoding on worker thread
+        thread::spawn(move || {
+            let res = unsafe { SHCreateMemStream(Some(&bmp)) }
+                .ok_or(NativeExtensionsError::InvalidData)
+                .and_then(|stream| convert_to_png(stream).map_err(NativeExtensionsError::from));
+            sender.send(move || {
+                let completer = completer.take().unwrap();
+                completer.complete(res);
+            });
+        });
+
+        Ok(future.await?.into())
+    }
+}
@@ -1,24 +1,39 @@
+mod capabilities;
+#[cfg(feature = "clipboard-events")]
 mod clipboard_events;
 mod common;
 mod data_object;
 mod data_provider;
+#[cfg(feature = "drag-drop")]
 mod drag;
+#[cfg(feature = "drag-drop")]
 mod drag_common;
+#[cfg(feature = "drag-drop")]
 mod drop;
+#[cfg(feature = "hot-key")]
 mod hot_key;
 mod image_conversion;
+#[cfg(feature = "keyboard-layout")]
 mod keyboard_layout;
+#[cfg(feature = "menu")]
 mod menu;
 mod ole_initializer;
 mod reader;
 mod virtual_file_stream;
 
+pub use capabilities::*;
+#[cfg(feature = "clipboard-events")]
 pub use clipboard_events::*;
 pub use data_provider::*;
+#[cfg(feature = "drag-drop")]
 pub use drag::*;
+#[cfg(feature = "drag-drop")]
 pub use drop::*;
+#[cfg(feature = "hot-key")]
 pub use hot_key::*;
+#[cfg(feature = "keyboard-layout")]
 pub use keyboard_layout::*;
+#[cfg(feature = "menu")]
 pub use menu::*;
 pub use ole_initializer::*;
 pub use reader::*;
@@ -5,6 +5,7 @@ use irondash_run_loop::{
     util::{Capsule, FutureCompleter},
     RunLoop, RunLoopSender,
 };
+use log::Level;
 use rand::{distributions::Alphanumeric, Rng};
 use std::{
     cell::{Cell, RefCell},
@@ -22,7 +23,7 @@ use std::{
 };
 use threadpool::ThreadPool;
 use windows::{
-    core::{w, HSTRING},
+    core::HSTRING,
     Win32::{
         Foundation::S_OK,
         Storage::FileSystem::{
@@ -36,10 +37,7 @@ use windows::{
             },
             DataExchange::RegisterClipboardFormatW,
             Memory::{GlobalLock, GlobalSize, GlobalUnlock},
-            Ole::{
-                OleGetClipboard, ReleaseStgMedium, CF_DIB, CF_DIBV5, CF_HDROP, CF_TIFF,
-                CF_UNICODETEXT,
-            },
+            Ole::{OleGetClipboard, ReleaseStgMedium, CF_HDROP, CF_TIFF, CF_UNICODETEXT},
         },
         UI::Shell::{
             SHCreateMemStream, CFSTR_FILECONTENTS, CFSTR_FILEDESCRIPTOR, DROPFILES,
@@ -49,7 +47,10 @@ use windows::{
 };
 
 use crate::{
+    api_model::ClipboardIntent,
     error::{NativeExtensionsError, NativeExtensionsResult},
+    format_converter::FormatConverter,
+    html_codec::{decode_cf_html, decode_html, html_fragment},
     log::OkLog,
     platform_impl::platform::common::make_format_with_tymed_index,
     reader_manager::{ReadProgress, VirtualFileReader},
@@ -62,9 +63,16 @@ use super::{
         read_stream_fully,
     },
     data_object::{DataObject, GetData},
-    image_conversion::convert_to_png,
+    image_conversion::DibToPng,
 };
 
+/// Converters used to synthesize formats not provided by data object. Text
+/// converters are not registered because plain text (`CF_UNICODETEXT`) is
+/// read as UTF-16 data.
+pub fn platform_format_converters() -> Vec<Rc<dyn FormatConverter>> {
+    vec![Rc::new(DibToPng::new())]
+}
+
 pub struct PlatformDataReader {
     data_object: IDataObject,
     _drop_notifier: Option<Arc<DropNotifier>>,
@@ -105,7 +113,7 @@ impl PlatformDataReader {
     }
 
     /// Returns formats that DataObject can provide.
-    fn data_object_formats_raw(&self) -> NativeExtensionsResult<Vec<u32>> {
+    fn data_object_formats(&self) -> NativeExtensionsResult<Vec<u32>> {
         let formats = self.formats_raw.clone().take();
         match formats {
             Some(formats) => Ok(formats),
@@ -128,24 +136,6 @@ impl PlatformDataReader {
         }
     }
 
-    fn need_to_synthesize_png(&self) -> NativeExtensionsResult<bool> {
-        let png = unsafe { RegisterClipboardFormatW(w!("PNG")) };
-        let formats = self.data_object_formats_raw()?;
-        let has_dib =
-            formats.contains(&(CF_DIBV5.0 as u32)) || formats.contains(&(CF_DIB.0 as u32));
-        let has_png = formats.contains(&png);
-        Ok(has_dib && !has_png)
-    }
-
-    fn data_object_formats(&self) -> NativeExtensionsResult<Vec<u32>> {
-        let mut res = self.data_object_formats_raw()?;
-        if self.need_to_synthesize_png()? {
-            let png = unsafe { RegisterClipboardFormatW(w!("PNG")) };
-            res.push(png);
-        }
-        Ok(res)
-    }
-
     pub fn get_formats_for_item_sync(&self, item: i64) -> NativeExtensionsResult<Vec<String>> {
         let mut formats = if item == 0 {
             self.data_object_formats()?
@@ -178,9 +168,9 @@ impl PlatformDataReader {
     pub fn item_format_is_synthesized(
         &self,
         _item: i64,
-        format: &str,
+        _format: &str,
     ) -> NativeExtensionsResult<bool> {
-        Ok(format == "PNG" && self.need_to_synthesize_png()?)
+        Ok(false)
     }
 
     pub async fn can_copy_virtual_file_for_item(
@@ -218,45 +208,6 @@ impl PlatformDataReader {
         Ok(None)
     }
 
-    async fn generate_png(&self) -> NativeExtensionsResult<Vec<u8>> {
-        let formats = self.data_object_formats()?;
-        // prefer DIBV5 with alpha channel
-        let data = if formats.contains(&(CF_DIBV5.0 as u32)) {
-            Ok(self.data_object.get_data(CF_DIBV5.0 as u32)?)
-        } else if formats.contains(&(CF_DIB.0 as u32)) {
-            Ok(self.data_object.get_data(CF_DIB.0 as u32)?)
-        } else {
-            Err(NativeExtensionsError::OtherError(
-                "No DIB or DIBV5 data found in data object".into(),
-            ))
-        }?;
-        let mut bmp = Vec::<u8>::new();
-        bmp.extend_from_slice(&[0x42, 0x4D]); // BM
-        bmp.extend_from_slice(&((data.len() + 14) as u32).to_le_bytes()); // File size
-        bmp.extend_from_slice(&[0, 0]); // reserved 1
-        bmp.extend_from_slice(&[0, 0]); // reserved 2
-        bmp.extend_from_slice(&[0, 0, 0, 0]); // data starting address; not required by decoder
-        bmp.extend_from_slice(&data);
-
-        let (future, completer) = FutureCompleter::new();
-
-        let mut completer = Capsule::new(completer);
-        let sender = RunLoop::current().new_sender();
-
-        // Do the actual encoding on worker thread
-        thread::spawn(move || {
-            let stream = unsafe { SHCreateMemStream(Some(&bmp)) };
-            let stream = stream.unwrap();
-            let res = convert_to_png(stream).map_err(NativeExtensionsError::from);
-            sender.send(move || {
-                let completer = completer.take().unwrap();
-                completer.complete(res);
-            });
-        });
-
-        future.await
-    }
-
     pub async fn get_data_for_item(
         &self,
         item: i64,
@@ -264,7 +215,6 @@ impl PlatformDataReader {
         _progress: Option<Arc<ReadProgress>>,
     ) -> NativeExtensionsResult<Value> {
         let format = format_from_string(&data_type);
-        let png = unsafe { RegisterClipboardFormatW(w!("PNG")) };
         if format == CF_HDROP.0 as u32 {
             let hdrop = self.hdrop_for_item(item)?;
             if let Some(hdrop) = hdrop {
@@ -272,9 +222,6 @@ impl PlatformDataReader {
             } else {
                 Ok(Value::Null)
             }
-        } else if format == png && self.need_to_synthesize_png()? {
-            let png_data = self.generate_png().await?;
-            Ok(png_data.into())
         } else {
             let formats = self.data_object_formats()?;
             if formats.contains(&format) {
@@ -287,6 +234,12 @@ impl PlatformDataReader {
                         data.truncate(terminator * 2);
                     }
                 }
+                // Strip CF_HTML header and context so that Dart gets the
+                // same HTML fragment as on other platforms.
+                if data_type == "HTML Format" {
+                    let html = decode_cf_html(&data).unwrap_or_else(|| decode_html(&data));
+                    return Ok(html_fragment(&html).to_owned().into());
+                }
                 Ok(data.into())
             } else {
                 // possibly virtual
@@ -633,6 +586,18 @@ impl PlatformDataReader {
         }
     }
 
+    pub async fn get_clipboard_intent(&self) -> NativeExtensionsResult<Option<ClipboardIntent>> {
+        Ok(None)
+    }
+
+    /// Not provided; Dropped files are read from `CF_HDROP` instead.
+    pub async fn get_local_path_for_item(
+        &self,
+        _item: i64,
+    ) -> NativeExtensionsResult<Option<PathBuf>> {
+        Ok(None)
+    }
+
     pub async fn get_item_format_for_uri(
         &self,
         item: i64,
@@ -880,7 +845,8 @@ impl AsyncVirtualStreamCopier {
                 Ok(path)
             }
             Err(err) => {
-                fs::remove_file(temp_path).ok_log();
+                // The file may not have been created at all.
+                fs::remove_file(temp_path).ok_log_at(Level::Debug);
                 Err(err)
             }
         }
@@ -964,16 +930,15 @@ fn mime_to_windows(fmt: String) -> String {
 }
 
 fn mime_from_name(name: &str) -> String {
+    #[cfg(feature = "mime_guess")]
+    if let Some(mime) = mime_guess::from_path(name).first() {
+        return mime.to_string();
+    }
     let ext = Path::new(name).extension();
-    mime_guess::from_path(name)
-        .first()
-        .map(|m| m.to_string())
-        .unwrap_or_else(|| {
-            format!(
-                "application/octet-stream;extension={}",
-                ext.unwrap_or_default().to_string_lossy()
-            )
-        })
+    format!(
+        "application/octet-stream;extension={}",
+        ext.unwrap_or_default().to_string_lossy()
+    )
 }
 
 #[cfg(test)]